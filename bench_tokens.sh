@@ -63,7 +63,7 @@ for query in "${queries[@]}"; do
     echo "🔍 Query: \"$query\""
 
     # Run searchgrep and capture JSON output
-    search_output=$(cargo run --release -- search "$query" -m 5 --content --json 2>/dev/null | grep -v "^Loading\|^✓" || echo "{}")
+    search_output=$(cargo run --release -- search "$query" -m 5 --content --format json-summary 2>/dev/null | grep -v "^Loading\|^✓" || echo "{}")
 
     # Get character count of results
     search_chars=$(echo "$search_output" | wc -c | tr -d ' ')
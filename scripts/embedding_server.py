@@ -207,7 +207,12 @@ def rerank(query: str, documents: List[str], top_k: int = None) -> List[dict]:
 
     # Create results with scores
     results = [
-        {"index": i, "score": float(score), "document": doc}
+        {
+            "index": i,
+            "score": float(score),
+            "relevance_score": float(score),
+            "document": doc,
+        }
         for i, (doc, score) in enumerate(zip(documents, scores))
     ]
 
@@ -266,8 +271,9 @@ class EmbeddingHandler(BaseHTTPRequestHandler):
             try:
                 data = json.loads(body)
                 query = data.get("query", "")
-                documents = data.get("documents", [])
-                top_k = data.get("top_k")
+                # Accept Jina/Cohere ("documents", "top_n") and TEI ("texts")
+                documents = data.get("documents") or data.get("texts") or []
+                top_k = data.get("top_n", data.get("top_k"))
 
                 if not query:
                     self.send_json_response({"error": "No query provided"}, 400)
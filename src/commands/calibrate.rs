@@ -0,0 +1,158 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::core::calibration::{calibration_key, model_key, Calibration, Calibrations};
+use crate::core::config::Config;
+use crate::core::filter::SearchFilter;
+use crate::core::labels::load_labels;
+use crate::core::query::{repo_path_for, ParsedQuery};
+use crate::core::session::{EmbedMode, PipelineOptions, SearchSession};
+use crate::core::store::VectorStore;
+
+pub struct CalibrateOptions {
+    /// JSON label file (see `core::labels`)
+    pub labels: String,
+    pub path: Option<String>,
+    pub store: Option<String>,
+    /// Results per query used as samples
+    pub max_count: usize,
+    pub code: bool,
+    pub hybrid: bool,
+    /// Route queries by intent, as `sgrep search` does without a mode flag
+    pub route: bool,
+    /// Print the fit without saving it
+    pub dry_run: bool,
+}
+
+pub async fn run(options: CalibrateOptions) -> Result<()> {
+    let config = Config::load()?;
+    let store = VectorStore::load(options.store.as_deref())?;
+    if store.chunk_count() == 0 {
+        bail!("No files indexed yet. Run: sgrep watch [path]");
+    }
+
+    let labels = load_labels(&options.labels)?;
+    if labels.is_empty() {
+        bail!("No labeled queries in {}", options.labels);
+    }
+
+    let mode = EmbedMode::from_flags(options.code, options.hybrid, false);
+    let routed = options.route && !(options.code || options.hybrid);
+
+    let mut base_filter = SearchFilter::new();
+    if let Some(ref path) = options.path {
+        base_filter = base_filter.with_root(path)?;
+    }
+    let repo_path = repo_path_for(options.path.as_deref());
+
+    // Calibrate retrieval scores only; reranker scores are on their own scale
+    let mut session = SearchSession::new(
+        store,
+        config.clone(),
+        mode,
+        PipelineOptions {
+            max_count: options.max_count,
+            rerank: false,
+            merge: false,
+            ..Default::default()
+        },
+    );
+    // Scores are fitted under the routing searches use, one fit per
+    // model and intent
+    if routed {
+        session = session.with_routing(&config);
+    }
+
+    println!(
+        "{} {} labeled queries{}",
+        "Calibrating".cyan(),
+        labels.len(),
+        if routed { ", routed by intent" } else { "" }
+    );
+
+    let mut samples: BTreeMap<String, Vec<(f32, bool)>> = BTreeMap::new();
+    for label in &labels {
+        let query = ParsedQuery::parse(&label.query)?;
+        let filter = query.apply(base_filter.clone(), &repo_path)?;
+        let results = session.search(&query, &filter, &|_| {}).await?;
+
+        let relevant = results
+            .iter()
+            .filter(|r| label.is_relevant(&r.chunk))
+            .count();
+        let key = calibration_key(
+            &model_key(&config, session.embedder.mode()),
+            session.route.as_ref(),
+        );
+        println!(
+            "  {} {}/{} relevant  {}  {}",
+            if relevant > 0 {
+                "✓".green()
+            } else {
+                "✗".red()
+            },
+            relevant,
+            results.len(),
+            label.query.dimmed(),
+            key.dimmed()
+        );
+
+        samples
+            .entry(key)
+            .or_default()
+            .extend(results.iter().map(|r| {
+                (
+                    session.trace.retrieval_score(r),
+                    label.is_relevant(&r.chunk),
+                )
+            }));
+    }
+
+    let mut fitted: Vec<(String, Calibration)> = Vec::new();
+    for (key, samples) in &samples {
+        println!();
+        let calibration = match Calibration::fit(samples) {
+            Ok(calibration) => calibration,
+            Err(e) => {
+                println!("{} {}: {}", "Skipped".yellow(), key.bold(), e);
+                continue;
+            }
+        };
+        let positives = samples.iter().filter(|(_, relevant)| *relevant).count();
+        println!(
+            "{} fitted on {} results ({} relevant): a = {:.3}, b = {:.3}",
+            key.bold(),
+            samples.len(),
+            positives,
+            calibration.a,
+            calibration.b
+        );
+        for score in [0.3, 0.4, 0.5, 0.6, 0.7, 0.8] {
+            println!(
+                "  score {:.1} -> {:>3.0}% confidence",
+                score,
+                calibration.confidence(score) * 100.0
+            );
+        }
+        fitted.push((key.clone(), calibration));
+    }
+    if fitted.is_empty() {
+        bail!("No calibration could be fitted; label more queries with both relevant and irrelevant results");
+    }
+
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let mut calibrations = Calibrations::load()?;
+    for (key, calibration) in &fitted {
+        calibrations.set(key, *calibration);
+    }
+    calibrations.save()?;
+    for (key, _) in &fitted {
+        println!("{} Saved calibration for {}", "✓".green(), key);
+    }
+
+    Ok(())
+}
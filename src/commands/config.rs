@@ -2,7 +2,9 @@ use anyhow::Result;
 use colored::Colorize;
 
 use crate::core::config::Config;
+use crate::core::hybrid_embedder::FusionStrategy;
 use crate::core::local_embeddings::{download_model, LocalEmbedder};
+use crate::core::reranker::{RerankFormat, RerankerEndpoint};
 use crate::core::store::VectorStore;
 
 pub struct ConfigOptions {
@@ -11,6 +13,15 @@ pub struct ConfigOptions {
     pub base_url: Option<String>,
     pub provider: Option<String>,
     pub local_url: Option<String>,
+    pub rerank_model: Option<String>,
+    pub rerank_top_n: Option<usize>,
+    pub rerank_url: Option<String>,
+    pub rerank_format: Option<String>,
+    pub rerank_endpoint_model: Option<String>,
+    pub rerank_auth_env: Option<String>,
+    pub rerank_timeout: Option<u64>,
+    pub route_embedder: Option<bool>,
+    pub hybrid_fusion: Option<FusionStrategy>,
     pub show: bool,
     pub clear: bool,
     pub download_model: bool,
@@ -94,6 +105,95 @@ pub async fn run(options: ConfigOptions) -> Result<()> {
         println!("{}", "✓ Local URL updated".green());
     }
 
+    if let Some(model) = options.rerank_model {
+        config.rerank_model = Some(model);
+        updated = true;
+        println!("{}", "✓ Rerank model updated".green());
+    }
+
+    if let Some(top_n) = options.rerank_top_n {
+        config.rerank_top_n = Some(top_n);
+        updated = true;
+        println!("{}", "✓ Rerank top-n updated".green());
+    }
+
+    if let Some(route_embedder) = options.route_embedder {
+        config.route_embedder = Some(route_embedder);
+        updated = true;
+        println!("{}", "✓ Embedder routing updated".green());
+    }
+
+    if let Some(strategy) = options.hybrid_fusion {
+        config.hybrid_fusion = Some(strategy.name().to_string());
+        updated = true;
+        println!("{}", "✓ Hybrid fusion updated".green());
+    }
+
+    let rerank_format = match options.rerank_format.as_deref() {
+        Some(format) => match RerankFormat::parse(format) {
+            Some(format) => Some(format),
+            None => {
+                println!(
+                    "{}",
+                    "Error: rerank format must be 'jina', 'cohere', or 'tei'".red()
+                );
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    if options.rerank_timeout == Some(0) {
+        println!(
+            "{}",
+            "Error: rerank timeout must be at least 1 second".red()
+        );
+        return Ok(());
+    }
+
+    // A new URL keeps the endpoint's other settings
+    if let Some(url) = options.rerank_url {
+        if url == "none" {
+            config.reranker = None;
+            println!("{}", "✓ Rerank endpoint removed".green());
+        } else {
+            match config.reranker.as_mut() {
+                Some(endpoint) => endpoint.url = url,
+                None => config.reranker = Some(RerankerEndpoint::new(url, RerankFormat::default())),
+            }
+            println!("{}", "✓ Rerank endpoint updated".green());
+        }
+        updated = true;
+    }
+
+    let endpoint_settings = rerank_format.is_some()
+        || options.rerank_endpoint_model.is_some()
+        || options.rerank_auth_env.is_some()
+        || options.rerank_timeout.is_some();
+    if endpoint_settings {
+        let Some(endpoint) = config.reranker.as_mut() else {
+            println!("{}", "Error: set an endpoint first with --rerank-url".red());
+            return Ok(());
+        };
+        if let Some(format) = rerank_format {
+            endpoint.format = format;
+            println!("{}", "✓ Rerank format updated".green());
+        }
+        if let Some(model) = options.rerank_endpoint_model {
+            endpoint.model = (model != "none").then_some(model);
+            println!("{}", "✓ Rerank endpoint model updated".green());
+        }
+        if let Some(var) = options.rerank_auth_env {
+            endpoint.api_key_env = (var != "none").then_some(var);
+            println!("{}", "✓ Rerank API key variable updated".green());
+        }
+        if let Some(secs) = options.rerank_timeout {
+            endpoint.timeout_secs = secs;
+            println!("{}", "✓ Rerank timeout updated".green());
+        }
+        updated = true;
+    }
+
     if updated {
         config.save()?;
     } else {
@@ -172,6 +272,67 @@ fn show_config(config: &Config) {
         println!("  {}", "Run: sgrep config --download-model".yellow());
     }
 
+    if let Some(ref endpoint) = config.reranker {
+        println!();
+        println!("{}", "Rerank endpoint:".bold());
+        println!("  {} {}", "URL:".dimmed(), endpoint.url);
+        println!("  {} {:?}", "Format:".dimmed(), endpoint.format);
+        if let Some(ref model) = endpoint.model {
+            println!("  {} {}", "Model:".dimmed(), model);
+        }
+        if let Some(ref var) = endpoint.api_key_env {
+            println!("  {} ${}", "API key:".dimmed(), var);
+        }
+        println!("  {} {}s", "Timeout:".dimmed(), endpoint.timeout_secs);
+    }
+
+    println!();
+    println!("{}", "Local reranker:".bold());
+    match config.get_rerank_model() {
+        Some(model) => {
+            println!("  {} {}", "Model:".dimmed(), model);
+            println!("  {} {}", "Top-n:".dimmed(), config.get_rerank_top_n());
+        }
+        None => println!(
+            "  {} {} {}",
+            "Model:".dimmed(),
+            "off".yellow(),
+            "(sgrep config --rerank-model default)".dimmed()
+        ),
+    }
+
+    println!();
+    println!("{}", "Retrieval fusion:".bold());
+    let (bm25_weight, vector_weight) = config.get_fusion_weights();
+    let source = |tuned: bool| if tuned { "(tuned)" } else { "(default)" }.dimmed();
+    println!(
+        "  {} bm25 {:.2}, vector {:.2} {}",
+        "Weights:".dimmed(),
+        bm25_weight,
+        vector_weight,
+        source(config.bm25_weight.is_some())
+    );
+    println!(
+        "  {} {:.2} {}",
+        "Hybrid alpha:".dimmed(),
+        config.get_hybrid_alpha(),
+        source(config.hybrid_alpha.is_some())
+    );
+    println!(
+        "  {} {}",
+        "Hybrid fusion:".dimmed(),
+        config.hybrid_strategy().name()
+    );
+    println!(
+        "  {} {}",
+        "Routed embedder:".dimmed(),
+        if config.get_route_embedder() {
+            "on"
+        } else {
+            "off"
+        }
+    );
+
     println!();
     println!("{}", "Environment variables:".dimmed());
 
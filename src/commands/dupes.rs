@@ -0,0 +1,121 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::core::dupes::{find_duplicates, DupeOptions};
+use crate::core::filter::SearchFilter;
+use crate::core::store::VectorStore;
+use crate::ui::output::relative_path;
+
+pub struct DupesOptions {
+    /// Only report chunks under this path
+    pub path: Option<String>,
+    /// Include globs (`!glob` excludes)
+    pub globs: Vec<String>,
+    /// Exclude globs
+    pub excludes: Vec<String>,
+    pub store: Option<String>,
+    pub detect: DupeOptions,
+    /// Show the first cluster member's code
+    pub content: bool,
+    /// Output as JSON
+    pub json: bool,
+    /// Exit with an error when duplicates are found (for CI)
+    pub fail: bool,
+}
+
+pub async fn run(options: DupesOptions) -> Result<()> {
+    let store = VectorStore::load(options.store.as_deref())?;
+    if store.chunk_count() == 0 {
+        bail!("No files indexed yet. Run: sgrep watch [path]");
+    }
+
+    let mut filter = SearchFilter::new().with_globs(&options.globs, &options.excludes)?;
+    if let Some(ref path) = options.path {
+        filter = filter.with_root(path)?;
+    }
+
+    let clusters = find_duplicates(&store, &options.detect, Some(&filter));
+    let duplicated_lines: usize = clusters.iter().map(|c| c.duplicated_lines()).sum();
+
+    if options.json {
+        let json_clusters: Vec<serde_json::Value> = clusters
+            .iter()
+            .map(|cluster| {
+                serde_json::json!({
+                    "similarity": cluster.similarity,
+                    "token_similarity": cluster.token_similarity,
+                    "duplicated_lines": cluster.duplicated_lines(),
+                    "chunks": cluster.chunks.iter().map(|c| serde_json::json!({
+                        "file": c.file_path,
+                        "start_line": c.start_line,
+                        "end_line": c.end_line,
+                        "symbol": c.symbol_name,
+                    })).collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "clusters": json_clusters,
+                "count": clusters.len(),
+                "duplicated_lines": duplicated_lines,
+                "threshold": options.detect.threshold,
+                "token_threshold": options.detect.token_threshold,
+            }))?
+        );
+    } else if clusters.is_empty() {
+        println!("{}", "No duplicate code found.".green());
+    } else {
+        println!(
+            "{} {} duplicate clusters ({} duplicated lines)",
+            "Found".bold(),
+            clusters.len(),
+            duplicated_lines
+        );
+
+        for (i, cluster) in clusters.iter().enumerate() {
+            println!();
+            println!(
+                "{} {}",
+                format!("Cluster {}", i + 1).cyan().bold(),
+                format!(
+                    "{} copies · {:.0}% similar · {:.0}% token overlap · {} duplicated lines",
+                    cluster.chunks.len(),
+                    cluster.similarity * 100.0,
+                    cluster.token_similarity * 100.0,
+                    cluster.duplicated_lines()
+                )
+                .dimmed()
+            );
+            for chunk in &cluster.chunks {
+                println!(
+                    "  {}:{}-{}{}",
+                    relative_path(&chunk.file_path),
+                    chunk.start_line,
+                    chunk.end_line,
+                    chunk
+                        .symbol_name
+                        .as_ref()
+                        .map(|name| format!("  {}", name.yellow()))
+                        .unwrap_or_default()
+                );
+            }
+            if options.content {
+                for line in cluster.chunks[0].content.lines().take(10) {
+                    println!("    {}", line.dimmed());
+                }
+            }
+        }
+    }
+
+    if options.fail && !clusters.is_empty() {
+        bail!(
+            "{} duplicate clusters ({} duplicated lines)",
+            clusters.len(),
+            duplicated_lines
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+
+use crate::core::config::Config;
+use crate::core::eval::{diff_runs, score_query, EvalReport, RunReport};
+use crate::core::filter::SearchFilter;
+use crate::core::labels::load_labels;
+use crate::core::query::{repo_path_for, ParsedQuery};
+use crate::core::session::{EmbedMode, PipelineOptions, SearchSession};
+use crate::core::store::VectorStore;
+
+pub struct EvalOptions {
+    /// JSON label file (see `core::labels`)
+    pub labels: String,
+    pub path: Option<String>,
+    /// Default store for modes given without `@store`
+    pub store: Option<String>,
+    /// Embedding modes, each optionally `mode@store`
+    pub modes: Vec<String>,
+    /// Rerank settings to run each mode with
+    pub rerank: Vec<bool>,
+    /// Results scored per query
+    pub k: usize,
+    /// Earlier report to compare against
+    pub compare: Option<String>,
+    /// Write the report here
+    pub save: Option<String>,
+    pub json: bool,
+}
+
+/// Parse `--rerank`: `on`, `off` or `both`
+pub fn parse_rerank(s: &str) -> Result<Vec<bool>> {
+    match s.to_lowercase().as_str() {
+        "on" => Ok(vec![true]),
+        "off" => Ok(vec![false]),
+        "both" => Ok(vec![false, true]),
+        other => bail!(
+            "Unknown rerank setting '{}' (expected on, off or both)",
+            other
+        ),
+    }
+}
+
+pub async fn run(options: EvalOptions) -> Result<()> {
+    let config = Config::load()?;
+    let labels = load_labels(&options.labels)?;
+    if labels.is_empty() {
+        bail!("No labeled queries in {}", options.labels);
+    }
+    let baseline = options
+        .compare
+        .as_deref()
+        .map(EvalReport::load)
+        .transpose()?;
+
+    let mut base_filter = SearchFilter::new();
+    if let Some(ref path) = options.path {
+        base_filter = base_filter.with_root(path)?;
+    }
+    let repo_path = repo_path_for(options.path.as_deref());
+    let queries = labels
+        .iter()
+        .map(|label| {
+            let query = ParsedQuery::parse(&label.query)?;
+            let filter = query.apply(base_filter.clone(), &repo_path)?;
+            Ok((query, filter))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if !options.json {
+        println!(
+            "{} {} labeled queries at k = {}",
+            "Evaluating".cyan(),
+            labels.len(),
+            options.k
+        );
+    }
+
+    let mut report = EvalReport {
+        labels: options.labels.clone(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        runs: Vec::new(),
+    };
+
+    for spec in &options.modes {
+        let (mode_name, store_name) = match spec.split_once('@') {
+            Some((mode, store)) => (mode, Some(store.to_string())),
+            None => (spec.as_str(), options.store.clone()),
+        };
+        let mode = EmbedMode::parse(mode_name).with_context(|| {
+            format!(
+                "Unknown mode '{}' (expected default, code, hybrid or colbert)",
+                mode_name
+            )
+        })?;
+
+        let store = VectorStore::load(store_name.as_deref())?;
+        if store.chunk_count() == 0 {
+            bail!(
+                "Store '{}' has no indexed files",
+                store_name.as_deref().unwrap_or("default")
+            );
+        }
+        let dimensions = store
+            .chunks
+            .values()
+            .next()
+            .map_or(0, |c| c.embedding.len());
+
+        let mut session = SearchSession::new(
+            store,
+            config.clone(),
+            mode,
+            PipelineOptions {
+                max_count: options.k,
+                ..Default::default()
+            },
+        );
+
+        // Query embeddings from a different model than the store's are noise
+        let (probe, _) = session.embedder.embed(&queries[0].0.text).await?;
+        if probe.len() != dimensions {
+            eprintln!(
+                "{} Skipping {}: queries embed to {} dimensions, the store has {}",
+                "!".yellow(),
+                spec,
+                probe.len(),
+                dimensions
+            );
+            continue;
+        }
+
+        for &rerank in &options.rerank {
+            session.options.rerank = rerank;
+            let mut name = mode.as_str().to_string();
+            if rerank {
+                name.push_str("+rerank");
+            }
+            if let Some((_, store)) = spec.split_once('@') {
+                name = format!("{}@{}", name, store);
+            }
+
+            let mut run = RunReport {
+                name,
+                k: options.k,
+                queries: Vec::with_capacity(labels.len()),
+            };
+            for (label, (query, filter)) in labels.iter().zip(&queries) {
+                let results = session.search(query, filter, &|_| {}).await?;
+                run.queries.push(score_query(label, &results, options.k));
+            }
+            report.runs.push(run);
+        }
+    }
+
+    if let Some(ref path) = options.save {
+        report.save(path)?;
+    }
+
+    if options.json {
+        let diffs: Vec<serde_json::Value> = baseline
+            .iter()
+            .flat_map(|baseline| {
+                report.runs.iter().filter_map(|run| {
+                    let before = baseline.run(&run.name)?;
+                    let queries: Vec<serde_json::Value> = diff_runs(before, run)
+                        .iter()
+                        .map(|d| {
+                            serde_json::json!({
+                                "query": d.after.query,
+                                "before": d.before,
+                                "after": d.after,
+                            })
+                        })
+                        .collect();
+                    Some(serde_json::json!({ "run": run.name, "queries": queries }))
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "report": report,
+                "summary": report.runs.iter().map(|run| serde_json::json!({
+                    "run": run.name,
+                    "recall": run.recall(),
+                    "mrr": run.mrr(),
+                    "ndcg": run.ndcg(),
+                })).collect::<Vec<_>>(),
+                "diffs": diffs,
+            }))?
+        );
+        return Ok(());
+    }
+
+    print_summary(&report, baseline.as_ref(), options.k);
+    if let Some(ref baseline) = baseline {
+        print_diffs(&report, baseline);
+    }
+    if let Some(ref path) = options.save {
+        println!();
+        println!("{} Saved report to {}", "✓".green(), path);
+    }
+
+    Ok(())
+}
+
+fn print_summary(report: &EvalReport, baseline: Option<&EvalReport>, k: usize) {
+    println!();
+    println!(
+        "  {:<24} {:>10} {:>7} {:>9}",
+        "run".dimmed(),
+        format!("recall@{}", k).dimmed(),
+        "MRR".dimmed(),
+        format!("nDCG@{}", k).dimmed()
+    );
+    for run in &report.runs {
+        let before = baseline.and_then(|b| b.run(&run.name));
+        let delta = |now: f32, then: Option<f32>| match then {
+            Some(then) if (now - then).abs() >= 0.0005 => {
+                let text = format!("{:+.3}", now - then);
+                if now > then {
+                    format!(" {}", text.green())
+                } else {
+                    format!(" {}", text.red())
+                }
+            }
+            _ => String::new(),
+        };
+        println!(
+            "  {:<24} {:>10.3}{} {:>7.3}{} {:>9.3}{}",
+            run.name,
+            run.recall(),
+            delta(run.recall(), before.map(RunReport::recall)),
+            run.mrr(),
+            delta(run.mrr(), before.map(RunReport::mrr)),
+            run.ndcg(),
+            delta(run.ndcg(), before.map(RunReport::ndcg))
+        );
+    }
+}
+
+fn print_diffs(report: &EvalReport, baseline: &EvalReport) {
+    let rank = |hit: Option<usize>| hit.map_or("-".to_string(), |r| r.to_string());
+
+    for run in &report.runs {
+        let Some(before) = baseline.run(&run.name) else {
+            continue;
+        };
+        let diffs = diff_runs(before, run);
+        println!();
+        println!(
+            "{} {} queries changed",
+            format!("{} vs {}", run.name, baseline.created_at).bold(),
+            diffs.len()
+        );
+        for diff in diffs {
+            let delta = diff.ndcg_delta();
+            let marker = if delta > 0.0 {
+                format!("↑ {:+.2}", delta).green()
+            } else if delta < 0.0 {
+                format!("↓ {:+.2}", delta).red()
+            } else {
+                format!("· {:+.2}", delta).dimmed()
+            };
+            println!(
+                "  {}  {}  {}",
+                marker,
+                diff.after.query,
+                format!(
+                    "first hit {} → {}",
+                    rank(diff.before.first_hit),
+                    rank(diff.after.first_hit)
+                )
+                .dimmed()
+            );
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::core::feedback::{record, FeedbackLabel};
+use crate::ui::output::relative_path;
+
+pub struct FeedbackOptions {
+    /// Query id printed with the search results
+    pub query_id: String,
+    /// 1-based rank of the result on that page
+    pub rank: usize,
+    pub label: FeedbackLabel,
+}
+
+pub async fn run(options: FeedbackOptions) -> Result<()> {
+    let entry = record(&options.query_id, options.rank, options.label)?;
+
+    let label = match entry.label {
+        FeedbackLabel::Good => entry.label.as_str().green(),
+        FeedbackLabel::Bad => entry.label.as_str().red(),
+    };
+    println!(
+        "{} Marked {}:{}-{} {} for {}",
+        "✓".green(),
+        relative_path(&entry.file_path),
+        entry.start_line,
+        entry.end_line,
+        label,
+        format!("\"{}\"", entry.query).dimmed()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::fs;
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::intent::{fit_intents, load_examples, IntentModel, Router};
+use crate::core::store::VectorStore;
+
+pub struct IntentOptions {
+    /// Query to classify and route
+    pub query: Option<String>,
+    /// Labeled queries (JSON lines) to fit the classifier to
+    pub train: Option<String>,
+    /// Store whose embeddings decide the routed embedder
+    pub store: Option<String>,
+    /// Print the fit without saving it
+    pub dry_run: bool,
+    /// Remove the trained classifier and go back to the rules
+    pub reset: bool,
+    pub json: bool,
+}
+
+pub async fn run(options: IntentOptions) -> Result<()> {
+    if options.reset {
+        let path = IntentModel::path()?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        println!("{} Query intents are classified by the rules", "✓".green());
+        return Ok(());
+    }
+
+    if let Some(ref train) = options.train {
+        return run_train(Path::new(train), options.dry_run, options.json);
+    }
+
+    let Some(ref query) = options.query else {
+        bail!("Give a query to route, or labeled queries with --train <examples.jsonl>");
+    };
+    let config = Config::load()?;
+    let store = VectorStore::load(options.store.as_deref())?;
+    let route = Router::new(&config, &store, config.provider == "local").route(query);
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&route.to_json())?);
+        return Ok(());
+    }
+    println!("{} {}", "Intent:".dimmed(), route.intent.as_str().bold());
+    println!(
+        "  {} {:.0}% ({})",
+        "Confidence:".dimmed(),
+        route.confidence * 100.0,
+        if route.trained { "trained" } else { "rules" }
+    );
+    println!("  {} {}", "Embedder:".dimmed(), route.mode.as_str());
+    println!("  {} {}", "BM25 terms:".dimmed(), route.tokenizer.as_str());
+    println!(
+        "  {} bm25 {:.2}, vector {:.2}",
+        "Weights:".dimmed(),
+        route.bm25_weight,
+        route.vector_weight
+    );
+
+    Ok(())
+}
+
+fn run_train(path: &Path, dry_run: bool, json: bool) -> Result<()> {
+    let examples = load_examples(path)?;
+    let (model, fit) = fit_intents(&examples)?;
+    let save = fit.improved() && !dry_run;
+    if save {
+        model.save(&IntentModel::path()?)?;
+    }
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "examples": examples.len(),
+                "fit": fit,
+                "saved": save,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} labeled queries ({} held out)",
+        "Fitting on".cyan(),
+        fit.train_examples,
+        fit.held_out_examples
+    );
+    println!(
+        "  held-out accuracy {:.0}% → {:.0}% {}",
+        fit.rules_accuracy * 100.0,
+        fit.trained_accuracy * 100.0,
+        "(rules → trained)".dimmed()
+    );
+
+    if !fit.improved() {
+        println!(
+            "{}",
+            "The trained classifier did worse than the rules on held-out queries; not saving"
+                .yellow()
+        );
+    } else if save {
+        println!("{} Saved {}", "✓".green(), IntentModel::path()?.display());
+    }
+
+    Ok(())
+}
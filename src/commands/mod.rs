@@ -1,9 +1,21 @@
+pub mod calibrate;
 pub mod clean;
 pub mod compile;
 pub mod config;
+pub mod dupes;
+pub mod eval;
+pub mod feedback;
 pub mod graph;
 pub mod history;
 pub mod index;
+pub mod intent;
+pub mod repl;
 pub mod search;
+pub mod similar;
 pub mod status;
+pub mod topics;
+pub mod trace;
+pub mod train_fusion;
+pub mod tui;
+pub mod tune;
 pub mod watch;
@@ -0,0 +1,424 @@
+//! Interactive query session
+//!
+//! Loads the store once and keeps query models warm across queries, so each
+//! query after the first costs only the search itself. Lines starting with
+//! `:` are commands (see `:help`); anything else is a query, with the same
+//! inline operators as `sgrep search`.
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+use crate::core::config::Config;
+use crate::core::explain::explain_results;
+use crate::core::filter::SearchFilter;
+use crate::core::query::{normalize_kind, normalize_language, repo_path_for, ParsedQuery};
+use crate::core::search::SearchResult;
+use crate::core::session::{EmbedMode, PipelineOptions, SearchSession};
+use crate::core::store::VectorStore;
+use crate::ui::output::relative_path;
+use crate::ui::search_display;
+use crate::ui::theme::Theme;
+
+pub struct ReplOptions {
+    pub path: Option<String>,
+    pub store: Option<String>,
+    pub max_count: usize,
+    pub code: bool,
+    pub hybrid: bool,
+    pub colbert: bool,
+    /// Route each query by intent until a mode is set with `:mode`
+    pub route: bool,
+}
+
+const HELP: &str = "\
+Commands:
+  :mode [default|code|hybrid|colbert]  Show or set the embedding mode (routed by intent until set)
+  :max N                               Maximum number of results
+  :lang [LANG,...]                     Only these languages (no argument clears)
+  :type [TYPE,...]                     Only these chunk types (no argument clears)
+  :glob [GLOB,...]                     Include globs, !GLOB excludes (no argument clears)
+  :rerank on|off                       Toggle reranking
+  :content on|off                      Toggle content snippets
+  :scores [on|off]                     Score breakdown of the last results, or toggle it
+  :explain on|off                      Explain why each result matched
+  :filters                             Show current settings
+  :help                                Show this help
+  :quit                                Exit";
+
+/// Persistent filters applied to every query
+#[derive(Default)]
+struct Filters {
+    languages: Vec<String>,
+    kinds: Vec<String>,
+    globs: Vec<String>,
+}
+
+struct Repl {
+    session: SearchSession,
+    path: Option<String>,
+    filters: Filters,
+    content: bool,
+    show_scores: bool,
+    explain: bool,
+    last: Vec<SearchResult>,
+}
+
+pub async fn run(options: ReplOptions) -> Result<()> {
+    let config = Config::load()?;
+    let store = VectorStore::load(options.store.as_deref())?;
+
+    if store.chunk_count() == 0 {
+        println!("{}", "No files indexed yet. Run:".yellow());
+        println!("  sgrep watch [path]");
+        return Ok(());
+    }
+
+    println!(
+        "{} {} chunks from {} files. Type {} for commands.",
+        "sgrep repl".color(Theme::NEON_CYAN).bold(),
+        store.chunk_count(),
+        store.file_count(),
+        ":help".color(Theme::NEON_MAGENTA)
+    );
+
+    let mut session = SearchSession::new(
+        store,
+        config.clone(),
+        EmbedMode::from_flags(options.code, options.hybrid, options.colbert),
+        PipelineOptions {
+            max_count: options.max_count,
+            ..Default::default()
+        },
+    );
+    // Same rule as `sgrep search`: route only when no mode was chosen
+    if options.route && !(options.code || options.hybrid || options.colbert) {
+        session = session.with_routing(&config);
+    }
+
+    let mut repl = Repl {
+        session,
+        path: options.path,
+        filters: Filters::default(),
+        content: false,
+        show_scores: false,
+        explain: false,
+        last: Vec::new(),
+    };
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("{} ", "sgrep>".color(Theme::NEON_CYAN).bold());
+        io::stdout().flush()?;
+
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+
+        let result = if let Some(command) = input.strip_prefix(':') {
+            match repl.command(command) {
+                Ok(true) => continue,
+                Ok(false) => return Ok(()),
+                Err(e) => Err(e),
+            }
+        } else if input == "exit" || input == "quit" {
+            return Ok(());
+        } else {
+            repl.query(input).await
+        };
+
+        if let Err(e) = result {
+            println!("{} {}", "Error:".red(), e);
+        }
+    }
+}
+
+impl Repl {
+    /// Run a `:command`; returns false to quit
+    fn command(&mut self, input: &str) -> Result<bool> {
+        let (name, arg) = match input.split_once(char::is_whitespace) {
+            Some((name, arg)) => (name, arg.trim()),
+            None => (input, ""),
+        };
+
+        match name {
+            "q" | "quit" | "exit" => return Ok(false),
+            "h" | "help" => println!("{}", HELP),
+            "mode" if arg.is_empty() => println!("mode: {}", self.mode()),
+            "mode" => match EmbedMode::parse(arg) {
+                Some(mode) => {
+                    self.session.stop_routing();
+                    self.session.embedder.set_mode(mode);
+                }
+                None => bail!("Unknown mode '{}' (default, code, hybrid, colbert)", arg),
+            },
+            "max" | "m" => match arg.parse::<usize>() {
+                Ok(n) if n > 0 => self.session.options.max_count = n,
+                _ => bail!("Expected a positive number: {}", arg),
+            },
+            "lang" => {
+                self.filters.languages = split_list(arg)
+                    .iter()
+                    .map(|l| normalize_language(l))
+                    .collect()
+            }
+            "type" => {
+                self.filters.kinds = split_list(arg).iter().map(|k| normalize_kind(k)).collect()
+            }
+            "glob" => self.filters.globs = split_list(arg),
+            "rerank" => self.session.options.rerank = parse_switch(arg)?,
+            "content" => self.content = parse_switch(arg)?,
+            "scores" if arg.is_empty() => print_scores(&self.last),
+            "scores" => self.show_scores = parse_switch(arg)?,
+            "explain" => self.explain = parse_switch(arg)?,
+            "filters" => self.print_settings(),
+            _ => bail!("Unknown command ':{}' (see :help)", name),
+        }
+        Ok(true)
+    }
+
+    async fn query(&mut self, input: &str) -> Result<()> {
+        let start_time = Instant::now();
+        let query = ParsedQuery::parse(input)?;
+        if query.text.is_empty() {
+            bail!("Query has no search text besides filters: {}", input);
+        }
+
+        let mut filter = SearchFilter::new();
+        if let Some(ref path) = self.path {
+            filter = filter.with_root(path)?;
+        }
+        filter = filter
+            .with_globs(&self.filters.globs, &[])?
+            .with_languages(&self.filters.languages, &[])
+            .with_kinds(&self.filters.kinds, &[]);
+        let filter = query.apply(filter, &repo_path_for(self.path.as_deref()))?;
+
+        let results = self.session.search(&query, &filter, &|_| {}).await?;
+        let duration = start_time.elapsed().as_millis();
+
+        let explanations = self.explain.then(|| {
+            explain_results(
+                &self.session.store,
+                &self.session.searcher,
+                &query.text,
+                &results,
+                &self.session.trace,
+            )
+        });
+        search_display::display_results(
+            input,
+            &results,
+            self.content,
+            None,
+            explanations.as_deref(),
+        );
+        if self.show_scores {
+            print_scores(&results);
+        }
+        println!(
+            "{}",
+            format!("{} results in {}ms", results.len(), duration).color(Theme::SUBTLE)
+        );
+
+        self.last = results;
+        Ok(())
+    }
+
+    /// Embedding mode, or "routed" with the last query's mode
+    fn mode(&self) -> String {
+        let mode = self.session.embedder.mode().as_str();
+        match self.session.route {
+            Some(_) => format!("routed (last: {})", mode),
+            None if self.session.is_routed() => "routed".to_string(),
+            None => mode.to_string(),
+        }
+    }
+
+    fn print_settings(&self) {
+        let list = |values: &[String]| {
+            if values.is_empty() {
+                "any".to_string()
+            } else {
+                values.join(",")
+            }
+        };
+        let options = &self.session.options;
+        println!("mode:    {}", self.mode());
+        println!("max:     {}", options.max_count);
+        println!("lang:    {}", list(&self.filters.languages));
+        println!("type:    {}", list(&self.filters.kinds));
+        println!("glob:    {}", list(&self.filters.globs));
+        println!("rerank:  {}", on_off(options.rerank));
+        println!("content: {}", on_off(self.content));
+        println!("scores:  {}", on_off(self.show_scores));
+        println!("explain: {}", on_off(self.explain));
+    }
+}
+
+/// Final score next to the retrieval components it was built from
+fn print_scores(results: &[SearchResult]) {
+    if results.is_empty() {
+        println!("{}", "No results".color(Theme::SUBTLE));
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{:>3}  {:>6}  {:>6}  {:>6}  {:>7}  location",
+            "#", "score", "vector", "bm25", "colbert"
+        )
+        .color(Theme::SUBTLE)
+    );
+    for (i, r) in results.iter().enumerate() {
+        let colbert = r
+            .colbert_score
+            .map_or("-".to_string(), |s| format!("{:.3}", s));
+        println!(
+            "{:>3}  {}  {:>6.3}  {:>6.3}  {:>7}  {}:{}",
+            i + 1,
+            format!("{:>6.3}", r.score).color(Theme::score_color(r.score)),
+            r.vector_score,
+            r.bm25_score,
+            colbert,
+            relative_path(&r.chunk.file_path),
+            r.chunk.start_line
+        );
+    }
+}
+
+fn split_list(arg: &str) -> Vec<String> {
+    arg.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_switch(arg: &str) -> Result<bool> {
+    match arg {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => bail!("Expected on or off: {}", arg),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repl() -> Repl {
+        Repl {
+            session: SearchSession::new(
+                VectorStore::with_test_chunks(vec![]),
+                Config::default(),
+                EmbedMode::Default,
+                PipelineOptions::default(),
+            ),
+            path: None,
+            filters: Filters::default(),
+            content: false,
+            show_scores: false,
+            explain: false,
+            last: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(
+            split_list("rust, python,,go "),
+            vec!["rust", "python", "go"]
+        );
+        assert_eq!(split_list("src/**,!*.md"), vec!["src/**", "!*.md"]);
+        assert!(split_list("").is_empty());
+        assert!(split_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_switch() {
+        for value in ["on", "true", "yes"] {
+            assert!(parse_switch(value).unwrap());
+        }
+        for value in ["off", "false", "no"] {
+            assert!(!parse_switch(value).unwrap());
+        }
+        assert!(parse_switch("").is_err());
+        assert!(parse_switch("On").is_err());
+    }
+
+    #[test]
+    fn test_command_settings() {
+        let mut repl = repl();
+
+        assert!(repl.command("mode code").unwrap());
+        assert_eq!(repl.session.embedder.mode(), EmbedMode::Code);
+        assert!(repl.command("mode nope").is_err());
+        assert_eq!(repl.session.embedder.mode(), EmbedMode::Code);
+
+        assert!(repl.command("max   7").unwrap());
+        assert_eq!(repl.session.options.max_count, 7);
+        assert!(repl.command("m 0").is_err());
+        assert!(repl.command("max").is_err());
+        assert_eq!(repl.session.options.max_count, 7);
+
+        repl.command("lang rust, ts").unwrap();
+        repl.command("type fn,class").unwrap();
+        repl.command("glob src/**,!*.md").unwrap();
+        assert_eq!(repl.filters.languages, vec!["rust", "typescript"]);
+        assert_eq!(repl.filters.kinds, vec!["function", "class"]);
+        assert_eq!(repl.filters.globs, vec!["src/**", "!*.md"]);
+        repl.command("lang").unwrap();
+        assert!(repl.filters.languages.is_empty());
+
+        repl.command("rerank on").unwrap();
+        repl.command("content yes").unwrap();
+        repl.command("scores on").unwrap();
+        repl.command("explain true").unwrap();
+        assert!(repl.session.options.rerank);
+        assert!(repl.content && repl.show_scores && repl.explain);
+        assert!(repl.command("content maybe").is_err());
+        assert!(repl.content);
+    }
+
+    #[test]
+    fn test_mode_stops_routing() {
+        let mut repl = repl();
+        repl.session = repl.session.with_routing(&Config::default());
+        assert_eq!(repl.mode(), "routed");
+
+        repl.command("mode hybrid").unwrap();
+        assert!(!repl.session.is_routed());
+        assert!(repl.session.route("how does parsing work").is_none());
+        assert_eq!(repl.mode(), "hybrid");
+    }
+
+    #[test]
+    fn test_command_quit_and_unknown() {
+        let mut repl = repl();
+        for quit in ["q", "quit", "exit"] {
+            assert!(!repl.command(quit).unwrap());
+        }
+        assert!(repl.command("help").unwrap());
+        assert!(repl.command("scores").unwrap());
+        let err = repl.command("frobnicate now").unwrap_err();
+        assert!(err.to_string().contains(":frobnicate"));
+    }
+}
@@ -1,56 +1,117 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use std::collections::HashSet;
 use std::time::Instant;
 
+use crate::commands::trace::{self, TraceOptions};
+use crate::core::calibration::{calibration_key, model_key, Calibrations};
 use crate::core::config::Config;
-use crate::core::embeddings::EmbeddingProvider;
+use crate::core::embeddings::cosine_similarity;
+use crate::core::explain::{explain_results, Explanation};
+use crate::core::filter::SearchFilter;
 use crate::core::graph::make_file_id;
-use crate::core::hybrid_embedder::HybridEmbedder;
-use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
-use crate::core::reranker::{simple_rerank, Reranker};
-use crate::core::search::{HybridSearcher, SearchResult};
+use crate::core::grep::{FileMatches, GrepMatcher};
+use crate::core::intent::Route;
+use crate::core::levels::SearchLevel;
+use crate::core::local_embeddings::SpeedMode;
+use crate::core::query::{repo_path_for, ParsedQuery};
+use crate::core::result_cache::{
+    cache_key, cursor, parse_cursor, Cached, Page, RankedQuery, ResultCache,
+};
+use crate::core::search::{CandidateSource, SearchResult};
+use crate::core::session::{EmbedMode, PipelineOptions, QueryEmbedding, SearchSession};
+use crate::core::snippet::{load_snippet, Snippet};
+use crate::core::stacktrace::TraceParser;
 use crate::core::store::VectorStore;
+use crate::ui::output::{
+    print_json_events, print_quickfix, print_vimgrep, relative_path, OutputFile, OutputFormat,
+    OutputMatch,
+};
 use crate::ui::progress::SearchAnimation;
 use crate::ui::search_display;
+use crate::ui::theme::Theme;
 
 pub struct SearchOptions {
     pub pattern: String,
     pub path: Option<String>,
     pub max_count: usize,
     pub content: bool,
+    /// Lines of on-disk context before each result
+    pub before_context: usize,
+    /// Lines of on-disk context after each result
+    pub after_context: usize,
     pub answer: bool,
     pub sync: bool,
     pub rerank: bool,
     pub colbert: bool,
     pub file_types: Option<Vec<String>>,
+    /// Include globs (`!glob` excludes), relative to the search path
+    pub globs: Vec<String>,
+    /// Exclude globs
+    pub excludes: Vec<String>,
     pub store: Option<String>,
     pub code: bool,
     pub hybrid: bool,
-    pub json: bool,
+    /// Pick the embedder, BM25 terms and keyword weight from the query's
+    /// intent when no mode flag is given
+    pub route: bool,
+    /// Output format (pretty UI, JSON, vimgrep, quickfix)
+    pub format: OutputFormat,
     /// Include related files (imports/importers) in results
     pub related: bool,
     /// Depth for related file traversal
     pub related_depth: usize,
+    /// Diversify results with maximal marginal relevance
+    pub diverse: bool,
+    /// MMR trade-off between relevance (1.0) and diversity (0.0)
+    pub lambda: f32,
+    /// Maximum number of results from a single file
+    pub max_per_file: Option<usize>,
+    /// Merge overlapping/adjacent hits from the same file into one span
+    pub merge: bool,
+    /// Match lines against a regex instead of searching semantically
+    pub regex: Option<String>,
+    /// Match lines against a literal string
+    pub fixed: Option<String>,
+    /// Case-insensitive regex/literal matching
+    pub ignore_case: bool,
+    /// Show why each result matched (pretty and json-summary output)
+    pub explain: bool,
+    /// Drop results whose calibrated confidence is below this (0.0-1.0)
+    pub min_score: Option<f32>,
+    /// Skip this many results (pagination)
+    pub offset: usize,
+    /// Continue from a cursor returned by an earlier page of the same query
+    pub cursor: Option<String>,
+    /// Rank chunks, files or directories
+    pub level: SearchLevel,
+    /// Only search the chunks of this many files, picked directory → file
+    pub narrow: Option<usize>,
+    /// Run each line of this file (`-` for stdin) as a query, printing JSON lines
+    pub batch: Option<String>,
 }
 
 pub async fn run(options: SearchOptions) -> Result<()> {
     let start_time = Instant::now();
     let config = Config::load()?;
     let store = VectorStore::load(options.store.as_deref())?;
+    let grep_mode = options.regex.is_some() || options.fixed.is_some();
 
-    if store.chunk_count() == 0 {
-        if options.json {
-            println!(
+    // Grep mode can still match files on disk under an explicit path
+    if store.chunk_count() == 0 && !(grep_mode && options.path.is_some()) {
+        match options.format {
+            OutputFormat::JsonSummary => println!(
                 "{}",
                 serde_json::json!({
                     "error": "No files indexed",
                     "results": []
                 })
-            );
-        } else {
-            println!("{}", "No files indexed yet. Run:".yellow());
-            println!("  sgrep watch [path]");
+            ),
+            OutputFormat::Pretty => {
+                println!("{}", "No files indexed yet. Run:".yellow());
+                println!("  sgrep watch [path]");
+            }
+            _ => eprintln!("No files indexed yet. Run: sgrep watch [path]"),
         }
         return Ok(());
     }
@@ -69,8 +130,91 @@ pub async fn run(options: SearchOptions) -> Result<()> {
             .await?;
     }
 
-    // Start search animation (skip if JSON output)
-    let animation = if !options.json {
+    if let Some(ref batch) = options.batch {
+        return run_batch(&options, batch, store, config).await;
+    }
+
+    // A stack trace pasted for a person goes to its frames rather than
+    // through ranking. Machine formats and scoped searches keep the search
+    // schema and flags.
+    let unscoped = options.path.is_none()
+        && options.globs.is_empty()
+        && options.excludes.is_empty()
+        && options.file_types.is_none()
+        && options.min_score.is_none();
+    if routed(&options)
+        && unscoped
+        && !grep_mode
+        && options.level == SearchLevel::Chunk
+        && options.format == OutputFormat::Pretty
+    {
+        let trace = TraceParser::new()?.parse(&options.pattern);
+        if !trace.frames.is_empty() {
+            let trace_options = TraceOptions {
+                text: options.pattern.clone(),
+                store: options.store.clone(),
+                max_count: options.max_count,
+                semantic: true,
+                json: false,
+            };
+            return trace::report(trace, store, config, &trace_options).await;
+        }
+    }
+
+    // Split inline operators (lang:, path:, ...) from the free text
+    let query = ParsedQuery::parse(&options.pattern)?;
+    if query.text.is_empty() && !grep_mode {
+        anyhow::bail!(
+            "Query has no search text besides filters: {}",
+            options.pattern
+        );
+    }
+
+    // Scope results to the path argument, globs, file types and query filters
+    let mut filter = SearchFilter::new();
+    if let Some(ref path) = options.path {
+        filter = filter.with_root(path)?;
+    }
+    filter = filter.with_globs(&options.globs, &options.excludes)?;
+    if let Some(ref file_types) = options.file_types {
+        filter = filter.with_file_types(file_types);
+    }
+    filter = query.apply(filter, &repo_path_for(options.path.as_deref()))?;
+
+    let mut session = SearchSession::new(
+        store,
+        config.clone(),
+        EmbedMode::from_flags(options.code, options.hybrid, options.colbert),
+        PipelineOptions {
+            max_count: options.max_count,
+            rerank: options.rerank,
+            merge: options.merge,
+            diverse: options.diverse,
+            lambda: options.lambda,
+            max_per_file: options.max_per_file,
+            narrow: options.narrow,
+        },
+    );
+    if routed(&options) {
+        session = session.with_routing(&config);
+    }
+
+    if grep_mode {
+        if options.offset > 0 || options.cursor.is_some() {
+            anyhow::bail!("--offset, --page and --cursor apply to semantic search only");
+        }
+        return run_grep(&options, &mut session, &query, &filter, start_time).await;
+    }
+
+    if options.level != SearchLevel::Chunk {
+        if options.offset > 0 || options.cursor.is_some() {
+            anyhow::bail!("--offset, --page and --cursor apply to chunk-level search only");
+        }
+        return run_levels(&options, &mut session, &query, &filter, start_time).await;
+    }
+
+    // Start search animation (skip for machine-readable output)
+    let animation = if options.format.is_pretty() {
         let anim = SearchAnimation::new(&options.pattern);
         anim.start();
         Some(anim)
@@ -78,91 +222,153 @@ pub async fn run(options: SearchOptions) -> Result<()> {
         None
     };
 
-    // Generate query embedding based on mode
-    if let Some(ref anim) = animation {
-        anim.update_stage("Generating embeddings...");
-    }
-
-    let (query_embedding, query_tokens) = if options.hybrid {
-        // Use hybrid fusion model (BGE + CodeRankEmbed)
-        let mut embedder = HybridEmbedder::new()?;
-        let embedding = embedder.embed_query(&options.pattern)?;
-        (embedding, vec![])
-    } else if options.code {
-        // Use local CodeRankEmbed for code search
-        let mut embedder = LocalEmbedder::with_speed_mode(SpeedMode::Code)?;
-        let embedding = embedder.embed_query(&options.pattern)?;
-        (embedding, vec![])
-    } else if options.colbert {
-        let embeddings = EmbeddingProvider::new(config.clone());
-        embeddings.embed_with_tokens(&options.pattern).await?
-    } else {
-        let embeddings = EmbeddingProvider::new(config.clone());
-        (embeddings.embed_single(&options.pattern).await?, vec![])
+    // The ranked list is cached per query and store generation, so later
+    // pages are read from the cache instead of searching again
+    session.route(&query.text);
+    let mode = session.embedder.mode();
+    let cwd = std::env::current_dir()?;
+    let key = cache_key(&[
+        "search",
+        &options.pattern,
+        &cwd.to_string_lossy(),
+        options.path.as_deref().unwrap_or(""),
+        &options.globs.join("\n"),
+        &options.excludes.join("\n"),
+        &options.file_types.as_deref().unwrap_or_default().join("\n"),
+        options.store.as_deref().unwrap_or(""),
+        mode.as_str(),
+        session.route.as_ref().map_or("", |r| r.intent.as_str()),
+        &format!(
+            "{} {} {} {} {:?} {:?} {:?} {}",
+            options.rerank,
+            options.merge,
+            options.diverse,
+            options.lambda,
+            options.max_per_file,
+            options.narrow,
+            config.get_fusion_weights(),
+            config.get_hybrid_alpha()
+        ),
+    ]);
+    let offset = match options.cursor {
+        Some(ref cursor) => parse_cursor(cursor, &key)?,
+        None => options.offset,
     };
+    let needed = offset + options.max_count;
+    let generation = VectorStore::generation(options.store.as_deref())?;
+    let cache = ResultCache::open()?;
 
-    // Search
-    if let Some(ref anim) = animation {
-        anim.update_stage("Searching index...");
-    }
-    let searcher = HybridSearcher::default();
-    let file_types = options.file_types.as_ref().map(|v| v.as_slice());
-
-    let mut results = searcher.search(
-        &store,
-        &query_embedding,
-        &options.pattern,
-        options.max_count * 3, // Get more for reranking
-        file_types,
-        options.colbert,
-        if options.colbert {
-            Some(&query_tokens)
-        } else {
-            None
-        },
-    );
+    let cached = match cache.get(&key, generation, &session.store, needed) {
+        Some(cached) => cached,
+        None => {
+            session.options.max_count = needed;
+            let results = session
+                .search(&query, &filter, &|stage| {
+                    if let Some(ref anim) = animation {
+                        anim.update_stage(stage);
+                    }
+                })
+                .await?;
+            // A failed cache write only costs a later page its speed
+            let ranked_for = RankedQuery {
+                text: &query.text,
+                mode: mode.as_str(),
+            };
+            let _ = cache.put(
+                &key,
+                generation,
+                ranked_for,
+                &results,
+                &session.trace,
+                needed,
+            );
+            Cached {
+                exhausted: results.len() < needed,
+                results,
+                trace: session.trace.clone(),
+            }
+        }
+    };
+    let page = Page::new(&key, &cached, offset, options.max_count);
+    session.trace = cached.trace;
+    let mut results = page.results;
+    let shown = results.len();
+    let store = &session.store;
 
     if results.is_empty() {
         if let Some(ref anim) = animation {
             anim.finish(0, start_time.elapsed().as_millis());
         }
-        if options.json {
-            println!(
+        match options.format {
+            OutputFormat::JsonSummary => println!(
                 "{}",
                 serde_json::json!({
                     "query": options.pattern,
                     "results": [],
+                    "outcome": "no_results",
                     "duration_ms": start_time.elapsed().as_millis()
                 })
-            );
-        } else {
-            println!("{}", "No results found".yellow());
+            ),
+            OutputFormat::Json => print_json_events(&[], start_time.elapsed()),
+            OutputFormat::Pretty => println!("{}", "No results found".yellow()),
+            OutputFormat::Vimgrep | OutputFormat::Quickfix => {}
         }
         return Ok(());
     }
 
-    // Rerank if enabled
-    if options.rerank && results.len() > 1 {
-        if let Some(ref anim) = animation {
-            anim.update_stage("Reranking results...");
-        }
-        let reranker = Reranker::new(config.clone());
-        let results_clone = results.clone();
-        results = match reranker
-            .rerank(&options.pattern, results_clone, options.max_count)
-            .await
-        {
-            Ok(reranked) => reranked,
-            Err(_) => {
-                // Fall back to simple reranking
-                simple_rerank(&options.pattern, results)
-                    .into_iter()
-                    .take(options.max_count)
-                    .collect()
+    // Calibrated probability that each result is relevant, from the
+    // retrieval score under this embedding model and route
+    let calibration = Calibrations::load()?.get(&calibration_key(
+        &model_key(&config, session.embedder.mode()),
+        session.route.as_ref(),
+    ));
+    let trace = &session.trace;
+    let confidence = |r: &SearchResult| calibration.confidence(trace.retrieval_score(r));
+
+    if let Some(min_score) = options.min_score {
+        let best = results.iter().map(confidence).fold(0.0, f32::max);
+        results.retain(|r| confidence(r) >= min_score);
+
+        if results.is_empty() {
+            if let Some(ref anim) = animation {
+                anim.finish(0, start_time.elapsed().as_millis());
             }
-        };
-    } else {
-        results.truncate(options.max_count);
+            match options.format {
+                OutputFormat::JsonSummary => println!(
+                    "{}",
+                    serde_json::json!({
+                        "query": options.pattern,
+                        "results": [],
+                        "outcome": "no_confident_results",
+                        "best_confidence": best,
+                        "min_score": min_score,
+                        "duration_ms": start_time.elapsed().as_millis()
+                    })
+                ),
+                OutputFormat::Json => print_json_events(&[], start_time.elapsed()),
+                OutputFormat::Pretty => {
+                    println!("{}", "No confident results".yellow());
+                    println!(
+                        "{}",
+                        format!(
+                            "Best match has {:.0}% confidence, below --min-score {:.0}%",
+                            best * 100.0,
+                            min_score * 100.0
+                        )
+                        .dimmed()
+                    );
+                    if !calibration.is_fitted() {
+                        println!(
+                            "{}",
+                            "Scores use the default calibration; fit one with: sgrep calibrate <labels.json>"
+                                .dimmed()
+                        );
+                    }
+                }
+                OutputFormat::Vimgrep | OutputFormat::Quickfix => {}
+            }
+            return Ok(());
+        }
     }
 
     // Expand with related files if requested
@@ -223,12 +429,64 @@ pub async fn run(options: SearchOptions) -> Result<()> {
                         bm25_score: 0.0,
                         vector_score: 0.5,
                         colbert_score: None,
+                        matched_ranges: vec![(first_chunk.start_line, first_chunk.end_line)],
+                        source: CandidateSource::Graph,
                     });
                 }
             }
         }
     }
 
+    // Re-read results from disk for context lines and freshness. Editor
+    // formats always need current line numbers.
+    let snippets: Option<Vec<Snippet>> = if options.content
+        || options.before_context > 0
+        || options.after_context > 0
+        || !matches!(
+            options.format,
+            OutputFormat::Pretty | OutputFormat::JsonSummary
+        ) {
+        Some(
+            results
+                .iter()
+                .map(|r| {
+                    load_snippet(
+                        store,
+                        &r.chunk,
+                        options.before_context,
+                        options.after_context,
+                    )
+                })
+                .collect(),
+        )
+    } else {
+        None
+    };
+
+    let explanations: Option<Vec<Explanation>> = if options.explain {
+        Some(explain_results(
+            store,
+            &session.searcher,
+            &query.text,
+            &results,
+            &session.trace,
+        ))
+    } else {
+        None
+    };
+
+    // Results can be rated with `sgrep feedback` while ranks still match
+    // the cached list; the page is recorded so the id outlives the cache
+    let ranked_for = RankedQuery {
+        text: &query.text,
+        mode: mode.as_str(),
+    };
+    let query_id = (results.len() == shown
+        && cache
+            .record_shown(&key, ranked_for, page.offset, &results)
+            .is_ok())
+    .then(|| cursor(&key, page.offset));
+
     // Finish animation
     let duration = start_time.elapsed().as_millis();
     if let Some(ref anim) = animation {
@@ -236,18 +494,19 @@ pub async fn run(options: SearchOptions) -> Result<()> {
     }
 
     // Display results
-    if options.json {
-        // Output as JSON
+    if options.format == OutputFormat::JsonSummary {
+        // Output as a single JSON document
         let json_results: Vec<serde_json::Value> = results
             .iter()
-            .map(|r| {
-                serde_json::json!({
-                    "file": r.chunk.file_path,
-                    "start_line": r.chunk.start_line,
-                    "end_line": r.chunk.end_line,
-                    "score": r.score,
-                    "content": if options.content { Some(&r.chunk.content) } else { None }
-                })
+            .enumerate()
+            .map(|(i, r)| {
+                summary_result(
+                    r,
+                    confidence(r),
+                    options.content,
+                    snippets.as_ref().and_then(|s| s.get(i)),
+                    explanations.as_ref().and_then(|e| e.get(i)),
+                )
             })
             .collect();
 
@@ -256,20 +515,652 @@ pub async fn run(options: SearchOptions) -> Result<()> {
             serde_json::json!({
                 "query": options.pattern,
                 "results": json_results,
+                "outcome": "results",
                 "count": results.len(),
+                "offset": page.offset,
+                "next_cursor": page.next_cursor,
+                "query_id": query_id,
+                "route": session.route.as_ref().map(Route::to_json),
                 "duration_ms": duration
             })
         );
     } else if options.answer {
-        display_answer(&options.pattern, &results, &config).await?;
+        display_answer(&query.text, &results, &config).await?;
     } else {
-        // Use the beautiful new UI
-        search_display::display_results(&options.pattern, &results, options.content);
+        match options.format {
+            OutputFormat::Pretty => {
+                // Use the beautiful new UI
+                search_display::display_results(
+                    &options.pattern,
+                    &results,
+                    options.content,
+                    snippets.as_deref(),
+                    explanations.as_deref(),
+                );
+                if let Some(ref route) = session.route {
+                    println!(
+                        "{}",
+                        format!("Route: {}", route.summary()).color(Theme::SUBTLE)
+                    );
+                }
+                if let Some(ref next) = page.next_cursor {
+                    println!(
+                        "{}",
+                        format!("More results: --cursor {}", next).color(Theme::SUBTLE)
+                    );
+                }
+                if let Some(ref id) = query_id {
+                    println!(
+                        "{}",
+                        format!("Rate a result: sgrep feedback {} <rank> good|bad", id)
+                            .color(Theme::SUBTLE)
+                    );
+                }
+            }
+            format => {
+                let files = result_output_files(&results, snippets.as_deref());
+                match format {
+                    OutputFormat::Vimgrep => print_vimgrep(&files),
+                    OutputFormat::Quickfix => print_quickfix(&files),
+                    _ => print_json_events(&files, start_time.elapsed()),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// One result of a json-summary document
+fn summary_result(
+    r: &SearchResult,
+    confidence: f32,
+    content: bool,
+    snippet: Option<&Snippet>,
+    explanation: Option<&Explanation>,
+) -> serde_json::Value {
+    let mut value = serde_json::json!({
+        "file": r.chunk.file_path,
+        "start_line": r.chunk.start_line,
+        "end_line": r.chunk.end_line,
+        "score": r.score,
+        "confidence": confidence,
+        "matched_ranges": r.matched_ranges,
+        "content": if content { Some(&r.chunk.content) } else { None }
+    });
+    if let Some(snippet) = snippet {
+        let lines = |lines: &[(usize, String)]| -> Vec<String> {
+            lines.iter().map(|(_, l)| l.clone()).collect()
+        };
+        value["start_line"] = snippet.start_line.into();
+        value["end_line"] = snippet.end_line.into();
+        value["freshness"] = snippet.freshness.as_str().into();
+        value["content"] = lines(&snippet.lines).join("\n").into();
+        value["context_before"] = lines(&snippet.before).into();
+        value["context_after"] = lines(&snippet.after).into();
+    }
+    if let Some(explanation) = explanation {
+        value["explain"] = explanation.to_json();
+    }
+    value
+}
+
+/// Whether queries are routed by intent: only when no embedding mode was
+/// chosen
+fn routed(options: &SearchOptions) -> bool {
+    options.route && !(options.code || options.hybrid || options.colbert)
+}
+
+/// Embed `texts` with one `embed_batch` per routed embedding mode. A failed
+/// batch fails only the queries routed to its mode.
+async fn embed_routed(
+    session: &mut SearchSession,
+    texts: &[String],
+) -> Vec<Result<QueryEmbedding>> {
+    let modes: Vec<EmbedMode> = texts
+        .iter()
+        .map(|text| {
+            session.route(text);
+            session.embedder.mode()
+        })
+        .collect();
+
+    let mut embeddings: Vec<Option<Result<QueryEmbedding>>> =
+        (0..texts.len()).map(|_| None).collect();
+    let mut done: Vec<EmbedMode> = Vec::new();
+    for &mode in &modes {
+        if done.contains(&mode) {
+            continue;
+        }
+        done.push(mode);
+        let indices: Vec<usize> = (0..texts.len()).filter(|&i| modes[i] == mode).collect();
+        let batch: Vec<String> = indices.iter().map(|&i| texts[i].clone()).collect();
+        session.embedder.set_mode(mode);
+        match session.embedder.embed_batch(&batch).await {
+            Ok(batch) => {
+                for (i, embedding) in indices.into_iter().zip(batch) {
+                    embeddings[i] = Some(Ok(embedding));
+                }
+            }
+            Err(e) => {
+                for i in indices {
+                    embeddings[i] = Some(Err(anyhow::anyhow!("{:#}", e)));
+                }
+            }
+        }
+    }
+    embeddings
+        .into_iter()
+        .map(|e| e.unwrap_or_else(|| Err(anyhow::anyhow!("Missing query embedding"))))
+        .collect()
+}
+
+/// Queries of a batch file: one per line, skipping blank lines and `#` comments
+fn batch_patterns(input: &str) -> Vec<&str> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Query and filter of one batch line
+fn parse_batch_query(
+    pattern: &str,
+    base_filter: &SearchFilter,
+    repo_path: &str,
+) -> Result<(ParsedQuery, SearchFilter)> {
+    let query = ParsedQuery::parse(pattern)?;
+    if query.text.is_empty() {
+        anyhow::bail!("Query has no search text besides filters");
+    }
+    let filter = query.apply(base_filter.clone(), repo_path)?;
+    Ok((query, filter))
+}
+
+/// JSON line for a batch query that could not be searched
+fn batch_error(pattern: &str, error: &anyhow::Error) -> serde_json::Value {
+    serde_json::json!({
+        "query": pattern,
+        "outcome": "error",
+        "error": format!("{:#}", error),
+    })
+}
+
+/// Run one query per line of `batch` with the store and models loaded once,
+/// printing one json-summary document per query
+async fn run_batch(
+    options: &SearchOptions,
+    batch: &str,
+    store: VectorStore,
+    config: Config,
+) -> Result<()> {
+    if options.regex.is_some() || options.fixed.is_some() {
+        anyhow::bail!(
+            "--batch runs semantic queries; it cannot be combined with --regex or --fixed"
+        );
+    }
+    if options.answer || options.related || options.level != SearchLevel::Chunk {
+        anyhow::bail!("--batch cannot be combined with --answer, --related or --level");
+    }
+    if options.offset > 0 || options.cursor.is_some() {
+        anyhow::bail!("--offset, --page and --cursor do not apply to --batch");
+    }
+    if !matches!(
+        options.format,
+        OutputFormat::Pretty | OutputFormat::JsonSummary
+    ) {
+        anyhow::bail!("--batch always prints JSON lines; drop --format/--json");
+    }
+
+    let input = if batch == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read queries from stdin")?
+    } else {
+        std::fs::read_to_string(batch)
+            .with_context(|| format!("Failed to read queries: {}", batch))?
+    };
+    let patterns = batch_patterns(&input);
+
+    let mut base_filter = SearchFilter::new();
+    if let Some(ref path) = options.path {
+        base_filter = base_filter.with_root(path)?;
+    }
+    base_filter = base_filter.with_globs(&options.globs, &options.excludes)?;
+    if let Some(ref file_types) = options.file_types {
+        base_filter = base_filter.with_file_types(file_types);
+    }
+    let repo_path = repo_path_for(options.path.as_deref());
+
+    let mut session = SearchSession::new(
+        store,
+        config.clone(),
+        EmbedMode::from_flags(options.code, options.hybrid, options.colbert),
+        PipelineOptions {
+            max_count: options.max_count,
+            rerank: options.rerank,
+            merge: options.merge,
+            diverse: options.diverse,
+            lambda: options.lambda,
+            max_per_file: options.max_per_file,
+            narrow: options.narrow,
+        },
+    );
+    if routed(options) {
+        session = session.with_routing(&config);
+    }
+    let calibrations = Calibrations::load()?;
+
+    // Parse every query first so all valid ones are embedded together
+    let queries: Vec<Result<(ParsedQuery, SearchFilter)>> = patterns
+        .iter()
+        .map(|pattern| parse_batch_query(pattern, &base_filter, &repo_path))
+        .collect();
+    let texts: Vec<String> = queries
+        .iter()
+        .filter_map(|q| q.as_ref().ok())
+        .map(|(query, _)| query.text.clone())
+        .collect();
+    let mut embeddings = embed_routed(&mut session, &texts).await.into_iter();
+
+    // Failures are reported on the query's line and the batch goes on
+    for (pattern, parsed) in patterns.iter().zip(queries) {
+        let start_time = Instant::now();
+        let (query, filter) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                println!("{}", batch_error(pattern, &e));
+                continue;
+            }
+        };
+        let embedding = match embeddings.next() {
+            Some(Ok(embedding)) => embedding,
+            Some(Err(e)) => {
+                println!("{}", batch_error(pattern, &e));
+                continue;
+            }
+            None => {
+                println!(
+                    "{}",
+                    batch_error(pattern, &anyhow::anyhow!("Missing query embedding"))
+                );
+                continue;
+            }
+        };
+
+        session.route(&query.text);
+        let calibration = calibrations.get(&calibration_key(
+            &model_key(&config, session.embedder.mode()),
+            session.route.as_ref(),
+        ));
+        let mut results = match session
+            .search_embedded(&query, &filter, embedding, &|_| {})
+            .await
+        {
+            Ok(results) => results,
+            Err(e) => {
+                println!("{}", batch_error(pattern, &e));
+                continue;
+            }
+        };
+        let confidence =
+            |r: &SearchResult| calibration.confidence(session.trace.retrieval_score(r));
+        let retrieved = !results.is_empty();
+        if let Some(min_score) = options.min_score {
+            results.retain(|r| confidence(r) >= min_score);
+        }
+
+        let explanations: Option<Vec<Explanation>> = options.explain.then(|| {
+            explain_results(
+                &session.store,
+                &session.searcher,
+                &query.text,
+                &results,
+                &session.trace,
+            )
+        });
+        let json_results: Vec<serde_json::Value> = results
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                summary_result(
+                    r,
+                    confidence(r),
+                    options.content,
+                    None,
+                    explanations.as_ref().and_then(|e| e.get(i)),
+                )
+            })
+            .collect();
+        let outcome = match (retrieved, results.is_empty()) {
+            (false, _) => "no_results",
+            (true, true) => "no_confident_results",
+            (true, false) => "results",
+        };
+
+        println!(
+            "{}",
+            serde_json::json!({
+                "query": pattern,
+                "results": json_results,
+                "outcome": outcome,
+                "count": results.len(),
+                "route": session.route.as_ref().map(Route::to_json),
+                "duration_ms": start_time.elapsed().as_millis()
+            })
+        );
     }
 
     Ok(())
 }
 
+/// Rank files or directories by their pooled vectors
+async fn run_levels(
+    options: &SearchOptions,
+    session: &mut SearchSession,
+    query: &ParsedQuery,
+    filter: &SearchFilter,
+    start_time: Instant,
+) -> Result<()> {
+    if session.store.level_index().is_empty() {
+        bail!("No file vectors yet. Run sgrep watch to build them");
+    }
+    session.route(&query.text);
+    let (query_embedding, _) = session.embedder.embed(&query.text).await?;
+    let levels = session.store.level_index();
+    let hits = match options.level {
+        SearchLevel::Dir => levels.search_dirs(
+            &query_embedding,
+            &query.text,
+            options.max_count,
+            Some(filter),
+        ),
+        _ => levels.search_files(
+            &query_embedding,
+            &query.text,
+            options.max_count,
+            Some(filter),
+        ),
+    };
+    let level = options.level.as_str();
+
+    match options.format {
+        OutputFormat::JsonSummary => {
+            let json_results: Vec<serde_json::Value> = hits
+                .iter()
+                .map(|hit| {
+                    let names = if options.level == SearchLevel::Dir {
+                        "files"
+                    } else {
+                        "symbols"
+                    };
+                    serde_json::json!({
+                        "path": hit.path,
+                        "score": hit.score,
+                        "vector_score": hit.vector_score,
+                        "name_score": hit.name_score,
+                        names: hit.names,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "query": options.pattern,
+                    "level": level,
+                    "results": json_results,
+                    "outcome": if hits.is_empty() { "no_results" } else { "results" },
+                    "count": hits.len(),
+                    "duration_ms": start_time.elapsed().as_millis()
+                })
+            );
+        }
+        OutputFormat::Pretty => {
+            if hits.is_empty() {
+                println!("{}", "No results found".yellow());
+            }
+            for (i, hit) in hits.iter().enumerate() {
+                println!(
+                    "{} {}  {}",
+                    format!("{:>2}.", i + 1).color(Theme::SUBTLE),
+                    relative_path(&hit.path).bold(),
+                    format!("{:.0}%", hit.score * 100.0).color(Theme::SUBTLE)
+                );
+                if !hit.names.is_empty() {
+                    let names: Vec<String> = hit
+                        .names
+                        .iter()
+                        .take(8)
+                        .map(|name| match options.level {
+                            SearchLevel::Dir => relative_path(name),
+                            _ => name.clone(),
+                        })
+                        .collect();
+                    let more = hit.names.len().saturating_sub(names.len());
+                    let suffix = if more > 0 {
+                        format!(" +{}", more)
+                    } else {
+                        String::new()
+                    };
+                    println!("    {}{}", names.join(", ").dimmed(), suffix.dimmed());
+                }
+            }
+        }
+        format => {
+            if options.level == SearchLevel::Dir {
+                anyhow::bail!("--level dir supports pretty and json-summary output");
+            }
+            let files: Vec<OutputFile> = hits
+                .iter()
+                .map(|hit| OutputFile {
+                    path: hit.path.clone(),
+                    matches: vec![OutputMatch {
+                        line_number: 1,
+                        text: String::new(),
+                        submatches: vec![(0, 0)],
+                        label: Some(format!("file {}", hit.names.join(", "))),
+                        score: Some(hit.score),
+                    }],
+                })
+                .collect();
+            match format {
+                OutputFormat::Vimgrep => print_vimgrep(&files),
+                OutputFormat::Quickfix => print_quickfix(&files),
+                _ => print_json_events(&files, start_time.elapsed()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Group ranked results by file (in order of each file's best result),
+/// one chunk-sized match per result
+pub fn result_output_files(
+    results: &[SearchResult],
+    snippets: Option<&[Snippet]>,
+) -> Vec<OutputFile> {
+    let mut files: Vec<OutputFile> = Vec::new();
+
+    for (i, result) in results.iter().enumerate() {
+        let chunk = &result.chunk;
+        let (line_number, text) = match snippets.and_then(|s| s.get(i)) {
+            Some(snippet) => (
+                snippet.start_line,
+                snippet
+                    .lines
+                    .iter()
+                    .map(|(_, l)| l.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            None => (chunk.start_line, chunk.content.clone()),
+        };
+        let label = chunk
+            .symbol_name
+            .as_ref()
+            .map(|name| format!("{} {}", chunk.chunk_type, name));
+        let output_match = OutputMatch::chunk(line_number, &text, label, result.score);
+
+        match files.iter_mut().find(|f| f.path == chunk.file_path) {
+            Some(file) => file.matches.push(output_match),
+            None => files.push(OutputFile {
+                path: chunk.file_path.clone(),
+                matches: vec![output_match],
+            }),
+        }
+    }
+
+    files
+}
+
+/// Regex/literal search. Without query text every match is listed in path
+/// order; with query text, matching files are ranked by the similarity of
+/// their best matching chunk and limited to `max_count`.
+async fn run_grep(
+    options: &SearchOptions,
+    session: &mut SearchSession,
+    query: &ParsedQuery,
+    filter: &SearchFilter,
+    start_time: Instant,
+) -> Result<()> {
+    let matcher = match (&options.regex, &options.fixed) {
+        (Some(regex), _) => GrepMatcher::regex(regex, options.ignore_case)?,
+        (None, Some(fixed)) => GrepMatcher::fixed(fixed, options.ignore_case)?,
+        (None, None) => unreachable!("grep mode requires --regex or --fixed"),
+    };
+    let store = &session.store;
+    let pattern = options
+        .regex
+        .as_deref()
+        .or(options.fixed.as_deref())
+        .unwrap_or_default();
+
+    let mut files = matcher.search_store(store, filter);
+    if let Some(ref path) = options.path {
+        files.extend(matcher.search_disk(path, store, filter)?);
+    }
+
+    // Unindexed files have no embeddings and rank after indexed ones
+    let mut scores: Vec<Option<f32>> = vec![None; files.len()];
+    if !query.text.is_empty() && !files.is_empty() {
+        let (query_embedding, _) = session.embedder.embed(&query.text).await?;
+        let store = &session.store;
+        scores = files
+            .iter()
+            .map(|f| {
+                f.chunk_ids
+                    .iter()
+                    .filter_map(|id| store.chunks.get(id))
+                    .map(|c| cosine_similarity(&query_embedding, &c.embedding))
+                    .reduce(f32::max)
+            })
+            .collect();
+
+        let mut ranked: Vec<(FileMatches, Option<f32>)> = files.into_iter().zip(scores).collect();
+        ranked.sort_by(|a, b| {
+            b.1.unwrap_or(f32::MIN)
+                .partial_cmp(&a.1.unwrap_or(f32::MIN))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        ranked.truncate(options.max_count);
+        (files, scores) = ranked.into_iter().unzip();
+    }
+
+    let duration = start_time.elapsed().as_millis();
+
+    match options.format {
+        OutputFormat::Pretty | OutputFormat::JsonSummary => {}
+        format => {
+            let output: Vec<OutputFile> = files
+                .iter()
+                .zip(&scores)
+                .map(|(f, score)| OutputFile {
+                    path: f.file_path.clone(),
+                    matches: f
+                        .lines
+                        .iter()
+                        .map(|l| OutputMatch {
+                            line_number: l.line_number,
+                            text: l.line.clone(),
+                            submatches: l.ranges.clone(),
+                            label: None,
+                            score: *score,
+                        })
+                        .collect(),
+                })
+                .collect();
+            match format {
+                OutputFormat::Vimgrep => print_vimgrep(&output),
+                OutputFormat::Quickfix => print_quickfix(&output),
+                _ => print_json_events(&output, start_time.elapsed()),
+            }
+            return Ok(());
+        }
+    }
+
+    if options.format == OutputFormat::JsonSummary {
+        let matches: Vec<serde_json::Value> = files
+            .iter()
+            .zip(&scores)
+            .flat_map(|(f, score)| {
+                f.lines.iter().map(move |l| {
+                    serde_json::json!({
+                        "file": f.file_path,
+                        "line": l.line_number,
+                        "text": l.line,
+                        "ranges": l.ranges,
+                        "score": score,
+                    })
+                })
+            })
+            .collect();
+
+        println!(
+            "{}",
+            serde_json::json!({
+                "query": query.text,
+                "pattern": pattern,
+                "count": matches.len(),
+                "matches": matches,
+                "duration_ms": duration
+            })
+        );
+        return Ok(());
+    }
+
+    if files.is_empty() {
+        println!("{}", "No matches found".yellow());
+        return Ok(());
+    }
+
+    for file in &files {
+        let path = relative_path(&file.file_path);
+        for line in &file.lines {
+            println!(
+                "{}:{}:{}",
+                path.magenta(),
+                line.line_number.to_string().green(),
+                highlight_ranges(&line.line, &line.ranges)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Color matched byte ranges of a line, ripgrep style
+fn highlight_ranges(line: &str, ranges: &[(usize, usize)]) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    for &(start, end) in ranges {
+        out.push_str(&line[last..start]);
+        out.push_str(&line[start..end].red().bold().to_string());
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
 async fn display_answer(query: &str, results: &[SearchResult], config: &Config) -> Result<()> {
     let api_key = config
         .get_api_key()
@@ -348,3 +1239,51 @@ async fn display_answer(query: &str, results: &[SearchResult], config: &Config)
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_lines() {
+        let input = "retry logic\n\n  # a comment\n  lang:rust parse config  \n";
+        assert_eq!(
+            batch_patterns(input),
+            vec!["retry logic", "lang:rust parse config"]
+        );
+
+        let filter = SearchFilter::new();
+        let (query, _) = parse_batch_query("lang:rust parse config", &filter, ".").unwrap();
+        assert_eq!(query.text, "parse config");
+        assert!(parse_batch_query("lang:rust", &filter, ".").is_err());
+        assert!(parse_batch_query("retry lnag:rust", &filter, ".").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_embedding_failure_is_per_line() {
+        // Nothing listens on the discard port, so every request fails
+        let config = Config {
+            api_key: Some("test".to_string()),
+            base_url: Some("http://127.0.0.1:9".to_string()),
+            ..Config::default()
+        };
+        let mut session = SearchSession::new(
+            VectorStore::with_test_chunks(Vec::new()),
+            config,
+            EmbedMode::Default,
+            PipelineOptions::default(),
+        );
+        let texts = vec!["retry logic".to_string(), "parse config".to_string()];
+        let embeddings = embed_routed(&mut session, &texts).await;
+        assert_eq!(embeddings.len(), 2);
+
+        let error = embeddings.into_iter().next().unwrap().unwrap_err();
+        let line = batch_error("retry logic", &error);
+        assert_eq!(line["outcome"], "error");
+        assert_eq!(line["query"], "retry logic");
+        assert!(line["error"]
+            .as_str()
+            .unwrap()
+            .contains("embedding request"));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::time::Instant;
+
+use crate::commands::search::result_output_files;
+use crate::core::filter::SearchFilter;
+use crate::core::similar::{chunk_at, chunk_for_symbol, parse_location, similar_to};
+use crate::core::snippet::{load_snippet, Snippet};
+use crate::core::store::VectorStore;
+use crate::ui::output::{
+    print_json_events, print_quickfix, print_vimgrep, relative_path, OutputFormat,
+};
+use crate::ui::search_display;
+
+pub struct SimilarOptions {
+    /// `path:line` inside the chunk to match
+    pub location: Option<String>,
+    /// Symbol name (`name` or `Parent::name`) instead of a location
+    pub symbol: Option<String>,
+    pub max_count: usize,
+    pub content: bool,
+    /// Include globs (`!glob` excludes)
+    pub globs: Vec<String>,
+    /// Exclude globs
+    pub excludes: Vec<String>,
+    pub store: Option<String>,
+    pub format: OutputFormat,
+}
+
+pub async fn run(options: SimilarOptions) -> Result<()> {
+    let start_time = Instant::now();
+    let store = VectorStore::load(options.store.as_deref())?;
+    if store.chunk_count() == 0 {
+        bail!("No files indexed yet. Run: sgrep watch [path]");
+    }
+
+    let chunk = match (&options.location, &options.symbol) {
+        (Some(location), _) => {
+            let (path, line) = parse_location(location)?;
+            chunk_at(&store, path, line)?
+        }
+        (None, Some(symbol)) => chunk_for_symbol(&store, symbol)?,
+        (None, None) => bail!("Give a location (path:line) or --symbol"),
+    };
+
+    let filter = SearchFilter::new().with_globs(&options.globs, &options.excludes)?;
+    let results = similar_to(&store, chunk, options.max_count, Some(&filter));
+
+    let source = format!(
+        "{}:{}-{}{}",
+        relative_path(&chunk.file_path),
+        chunk.start_line,
+        chunk.end_line,
+        chunk
+            .symbol_name
+            .as_ref()
+            .map(|name| format!(" ({})", name))
+            .unwrap_or_default()
+    );
+
+    let snippets: Vec<Snippet> = results
+        .iter()
+        .map(|r| load_snippet(&store, &r.chunk, 0, 0))
+        .collect();
+
+    match options.format {
+        OutputFormat::Pretty => {
+            println!("{} {}", "Similar to".cyan(), source.bold());
+            search_display::display_results(
+                &source,
+                &results,
+                options.content,
+                options.content.then_some(snippets.as_slice()),
+                None,
+            );
+        }
+        OutputFormat::JsonSummary => {
+            let json_results: Vec<serde_json::Value> = results
+                .iter()
+                .zip(&snippets)
+                .map(|(r, snippet)| {
+                    serde_json::json!({
+                        "file": r.chunk.file_path,
+                        "start_line": snippet.start_line,
+                        "end_line": snippet.end_line,
+                        "symbol": r.chunk.symbol_name,
+                        "score": r.score,
+                        "content": if options.content { Some(&r.chunk.content) } else { None }
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "source": {
+                        "file": chunk.file_path,
+                        "start_line": chunk.start_line,
+                        "end_line": chunk.end_line,
+                        "symbol": chunk.symbol_name,
+                    },
+                    "results": json_results,
+                    "count": results.len(),
+                    "duration_ms": start_time.elapsed().as_millis()
+                })
+            );
+        }
+        format => {
+            let files = result_output_files(&results, Some(&snippets));
+            match format {
+                OutputFormat::Vimgrep => print_vimgrep(&files),
+                OutputFormat::Quickfix => print_quickfix(&files),
+                _ => print_json_events(&files, start_time.elapsed()),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::core::filter::SearchFilter;
+use crate::core::store::VectorStore;
+use crate::core::topics::{topic_map, TopicOptions};
+
+pub struct TopicsOptions {
+    /// Only cluster chunks under this path
+    pub path: Option<String>,
+    /// Include globs (`!glob` excludes)
+    pub globs: Vec<String>,
+    /// Exclude globs
+    pub excludes: Vec<String>,
+    pub store: Option<String>,
+    pub cluster: TopicOptions,
+    /// Show every directory, not only mixed ones
+    pub all_dirs: bool,
+    /// Output as JSON
+    pub json: bool,
+}
+
+pub async fn run(options: TopicsOptions) -> Result<()> {
+    let store = VectorStore::load(options.store.as_deref())?;
+    if store.chunk_count() == 0 {
+        bail!("No files indexed yet. Run: sgrep watch [path]");
+    }
+
+    let mut filter = SearchFilter::new().with_globs(&options.globs, &options.excludes)?;
+    if let Some(ref path) = options.path {
+        filter = filter.with_root(path)?;
+    }
+
+    let map = topic_map(&store, &options.cluster, Some(&filter));
+    if map.topics.is_empty() {
+        bail!("No indexed chunks match the given path and globs");
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&map)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} {} topics in {}",
+        "Found".bold(),
+        map.topics.len(),
+        map.root.dimmed()
+    );
+
+    for topic in &map.topics {
+        println!();
+        println!(
+            "{} {}",
+            format!("Topic {}", topic.id).cyan().bold(),
+            format!("{} chunks", topic.size).dimmed()
+        );
+        if !topic.terms.is_empty() {
+            println!("  {}", topic.terms.join(", ").yellow());
+        }
+        let paths: Vec<String> = topic
+            .paths
+            .iter()
+            .map(|(path, share)| format!("{} {:.0}%", path, share * 100.0))
+            .collect();
+        println!("  {}", paths.join("  ").dimmed());
+    }
+
+    let directories: Vec<_> = map
+        .directories
+        .iter()
+        .filter(|d| options.all_dirs || d.mixed)
+        .collect();
+    if directories.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    println!(
+        "{}",
+        if options.all_dirs {
+            "Directories".bold()
+        } else {
+            "Directories mixing several topics".bold()
+        }
+    );
+    for dir in directories {
+        let topics: Vec<String> = dir
+            .topics
+            .iter()
+            .map(|(topic, share)| format!("#{} {:.0}%", topic, share * 100.0))
+            .collect();
+        println!(
+            "  {}  {}  {}",
+            dir.path,
+            format!("{} chunks", dir.chunks).dimmed(),
+            topics.join(" ")
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::collections::HashSet;
+
+use crate::core::config::Config;
+use crate::core::filter::SearchFilter;
+use crate::core::query::ParsedQuery;
+use crate::core::search::SearchResult;
+use crate::core::session::{EmbedMode, PipelineOptions, SearchSession};
+use crate::core::stacktrace::{map_frames, MessageMatcher, StackTrace, TraceParser};
+use crate::core::store::{FileChunk, VectorStore};
+use crate::ui::output::relative_path;
+
+pub struct TraceOptions {
+    /// Pasted panic, traceback or error message
+    pub text: String,
+    pub store: Option<String>,
+    /// Most raise sites and related chunks
+    pub max_count: usize,
+    /// Also search semantically for the error message
+    pub semantic: bool,
+    pub json: bool,
+}
+
+/// The trace in `path`, or stdin when it is missing or `-`
+pub fn read_input(path: Option<&str>) -> Result<String> {
+    match path {
+        None | Some("-") => {
+            std::io::read_to_string(std::io::stdin()).context("Failed to read trace from stdin")
+        }
+        Some(path) => {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read trace: {}", path))
+        }
+    }
+}
+
+pub async fn run(options: TraceOptions) -> Result<()> {
+    let trace = TraceParser::new()?.parse(&options.text);
+    if trace.is_empty() {
+        bail!("No stack frames or error message found in the input");
+    }
+
+    let config = Config::load()?;
+    let store = VectorStore::load(options.store.as_deref())?;
+    if store.chunk_count() == 0 {
+        bail!("No files indexed yet. Run: sgrep watch [path]");
+    }
+    report(trace, store, config, &options).await
+}
+
+/// Map a parsed trace onto `store` and print frames, raise sites and
+/// related code
+pub async fn report(
+    trace: StackTrace,
+    store: VectorStore,
+    config: Config,
+    options: &TraceOptions,
+) -> Result<()> {
+    let frames = map_frames(&store, &trace.frames);
+    let raised = match trace.message {
+        Some(ref message) => MessageMatcher::new()?.raise_sites(&store, message, options.max_count),
+        None => Vec::new(),
+    };
+
+    let query = trace.message.clone().or_else(|| trace.error_type.clone());
+    let mut related = Vec::new();
+    if let Some(text) = query.filter(|_| options.semantic) {
+        let shown: HashSet<String> = frames
+            .iter()
+            .filter_map(|f| f.chunk.as_ref())
+            .chain(raised.iter().map(|r| &r.chunk))
+            .map(|c| c.id.clone())
+            .collect();
+        let mut session = SearchSession::new(
+            store,
+            config.clone(),
+            EmbedMode::Default,
+            PipelineOptions {
+                max_count: options.max_count + shown.len(),
+                ..PipelineOptions::default()
+            },
+        )
+        .with_routing(&config);
+        let query = ParsedQuery {
+            text,
+            ..ParsedQuery::default()
+        };
+        // Frames and raise sites don't need a model; still show them
+        match session.search(&query, &SearchFilter::new(), &|_| {}).await {
+            Ok(results) => {
+                related = results
+                    .into_iter()
+                    .filter(|r| !shown.contains(&r.chunk.id))
+                    .take(options.max_count)
+                    .collect::<Vec<SearchResult>>()
+            }
+            Err(e) => eprintln!("{} {}", "Skipping related code:".yellow(), e),
+        }
+    }
+
+    if options.json {
+        let location = |c: &FileChunk| {
+            serde_json::json!({
+                "file": c.file_path,
+                "start_line": c.start_line,
+                "end_line": c.end_line,
+                "symbol": c.symbol_name,
+            })
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "format": trace.format,
+                "error_type": trace.error_type,
+                "message": trace.message,
+                "frames": frames.iter().map(|f| serde_json::json!({
+                    "file": f.frame.file,
+                    "line": f.frame.line,
+                    "function": f.frame.function,
+                    "indexed_file": f.file,
+                    "chunk": f.chunk.as_ref().map(location),
+                })).collect::<Vec<_>>(),
+                "raised_at": raised.iter().map(|r| serde_json::json!({
+                    "line": r.line,
+                    "literal": r.literal,
+                    "coverage": r.coverage,
+                    "chunk": location(&r.chunk),
+                })).collect::<Vec<_>>(),
+                "related": related.iter().map(|r| serde_json::json!({
+                    "score": r.score,
+                    "chunk": location(&r.chunk),
+                })).collect::<Vec<_>>(),
+            }))?
+        );
+        return Ok(());
+    }
+
+    let kind = trace.format.map(|f| f.as_str()).unwrap_or("error");
+    let headline = match (&trace.error_type, &trace.message) {
+        (Some(error_type), Some(message)) => format!("{}: {}", error_type, message),
+        (None, Some(message)) => message.clone(),
+        (Some(error_type), None) => error_type.clone(),
+        (None, None) => String::new(),
+    };
+    println!("{} {}", format!("{} trace", kind).cyan(), headline.bold());
+
+    let mapped: Vec<_> = frames.iter().filter(|f| f.file.is_some()).collect();
+    if !frames.is_empty() {
+        println!();
+        println!("{}", "Frames".bold());
+        for frame in &mapped {
+            let at = match frame.frame.line {
+                Some(line) => format!(
+                    "{}:{}",
+                    relative_path(frame.file.as_deref().unwrap_or("")),
+                    line
+                ),
+                None => relative_path(frame.file.as_deref().unwrap_or("")),
+            };
+            let inside = frame
+                .chunk
+                .as_ref()
+                .map(|c| {
+                    format!(
+                        "{}-{} {}",
+                        c.start_line,
+                        c.end_line,
+                        c.symbol_name.as_deref().unwrap_or(&c.chunk_type)
+                    )
+                })
+                .unwrap_or_else(|| "not in an indexed chunk".to_string());
+            println!(
+                "  {}  {}  {}",
+                at.green(),
+                frame.frame.function.as_deref().unwrap_or("").dimmed(),
+                format!("({})", inside).dimmed()
+            );
+        }
+        let outside = frames.len() - mapped.len();
+        if outside > 0 {
+            println!(
+                "  {}",
+                format!("{} frame(s) outside the index", outside).dimmed()
+            );
+        }
+    }
+
+    if !raised.is_empty() {
+        println!();
+        println!("{}", "Raised at".bold());
+        for site in &raised {
+            println!(
+                "  {}  \"{}\"  {}",
+                format!("{}:{}", relative_path(&site.chunk.file_path), site.line).green(),
+                site.literal,
+                format!("{:.0}%", site.coverage * 100.0).dimmed()
+            );
+        }
+    }
+
+    if !related.is_empty() {
+        println!();
+        println!("{}", "Related".bold());
+        for result in &related {
+            println!(
+                "  {}  {}  {}",
+                format!(
+                    "{}:{}-{}",
+                    relative_path(&result.chunk.file_path),
+                    result.chunk.start_line,
+                    result.chunk.end_line
+                )
+                .green(),
+                result.chunk.symbol_name.as_deref().unwrap_or("").dimmed(),
+                format!("{:.2}", result.score).dimmed()
+            );
+        }
+    }
+
+    if mapped.is_empty() && raised.is_empty() && related.is_empty() {
+        println!(
+            "{}",
+            "Nothing in the index matches this trace; is the right repository indexed?".yellow()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::core::embeddings::cosine_similarity;
+use crate::core::fusion_layer::{train, FusionLayer, TrainOptions, TripleEmbeddings};
+use crate::core::git::{is_git_repo, GitRepo};
+use crate::core::hybrid_embedder::{FusionStrategy, HybridEmbedder};
+use crate::core::scanner::FileScanner;
+use crate::core::store::VectorStore;
+use crate::core::triples::{commit_triples, doc_triples, Triple};
+use crate::ui::progress::ProgressBar;
+
+/// Languages whose comments don't document code
+const NON_CODE: &[&str] = &[
+    "markdown", "json", "yaml", "toml", "xml", "html", "css", "scss", "less",
+];
+
+/// Documents embedded per model call
+const BATCH: usize = 16;
+/// Stored chunks re-embedded to tell which model built the store
+const PROBE_CHUNKS: usize = 3;
+/// Similarity at which a re-embedded chunk counts as reproduced
+const SAME_MODEL: f32 = 0.98;
+
+pub struct TrainFusionOptions {
+    /// Repository to mine triples from
+    pub path: Option<String>,
+    /// Store whose chunk embeddings hybrid queries are compared to
+    pub store: Option<String>,
+    /// Adaptive or Concatenate
+    pub strategy: FusionStrategy,
+    /// Most doc-comment triples
+    pub docs: usize,
+    /// Most commit-message triples
+    pub commits: usize,
+    pub epochs: usize,
+    /// Print the fit without saving it
+    pub dry_run: bool,
+}
+
+pub async fn run(options: TrainFusionOptions) -> Result<()> {
+    if !matches!(
+        options.strategy,
+        FusionStrategy::Adaptive | FusionStrategy::Concatenate
+    ) {
+        bail!(
+            "{} fusion has no trainable weights (expected adaptive or concatenate)",
+            options.strategy.name()
+        );
+    }
+    let path = options.path.clone().unwrap_or_else(|| ".".to_string());
+    let store = VectorStore::load(options.store.as_deref())?;
+
+    let files: Vec<_> = FileScanner::new(&path)
+        .scan()?
+        .into_iter()
+        .filter(|f| {
+            f.language
+                .as_deref()
+                .is_some_and(|l| !NON_CODE.contains(&l))
+        })
+        .collect();
+    let mut triples = doc_triples(&files, options.docs);
+    let doc_count = triples.len();
+    if options.commits > 0 && is_git_repo(&path) {
+        let patches = GitRepo::open(&path)?.commit_patches(options.commits * 2)?;
+        triples.extend(commit_triples(&patches, options.commits));
+    }
+    println!(
+        "{} {} doc-comment and {} commit triples from {}",
+        "Mined".cyan(),
+        doc_count,
+        triples.len() - doc_count,
+        path
+    );
+    if triples.len() < 10 {
+        bail!("Too few triples to train on; point sgrep train-fusion at a documented repository");
+    }
+
+    let data = embed_triples(&triples, &store)?;
+    let train_options = TrainOptions {
+        epochs: options.epochs,
+        ..TrainOptions::for_strategy(options.strategy)
+    };
+    let (layer, report) = train(options.strategy, &data, &train_options)?;
+
+    println!();
+    println!(
+        "Trained {} fusion on {} triples, final loss {:.3}",
+        options.strategy.name().bold(),
+        report.train_triples,
+        report.final_loss
+    );
+    println!(
+        "  held-out accuracy {:.1}% → {:.1}% {}",
+        report.initial_accuracy * 100.0,
+        report.trained_accuracy * 100.0,
+        format!(
+            "({} triples, weighted average → trained)",
+            report.held_out_triples
+        )
+        .dimmed()
+    );
+
+    if !report.improved() {
+        println!(
+            "{}",
+            "The trained weights did worse than the weighted average on held-out triples; not saving"
+                .yellow()
+        );
+        return Ok(());
+    }
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let weights_path = FusionLayer::path(options.strategy)?;
+    layer.save(&weights_path)?;
+    println!("{} Saved {}", "✓".green(), weights_path.display());
+    println!(
+        "  Use it for --hybrid with: {}",
+        format!("sgrep config --hybrid-fusion {}", options.strategy.name()).yellow()
+    );
+
+    Ok(())
+}
+
+/// Which half of the hybrid embedding the store's chunks were embedded with
+#[derive(Debug, Clone, Copy)]
+enum StoreModel {
+    Bge,
+    Code,
+}
+
+impl StoreModel {
+    fn name(self) -> &'static str {
+        match self {
+            StoreModel::Bge => "BGE",
+            StoreModel::Code => "CodeRankEmbed",
+        }
+    }
+
+    fn pick(self, (bge, code): (Vec<f32>, Vec<f32>)) -> Vec<f32> {
+        match self {
+            StoreModel::Bge => bge,
+            StoreModel::Code => code,
+        }
+    }
+}
+
+/// Re-embed a few stored chunks with both models and keep the one that
+/// reproduces their stored embeddings
+fn store_model(embedder: &mut HybridEmbedder, store: &VectorStore) -> Result<StoreModel> {
+    let mut probes: Vec<_> = store
+        .all_chunks()
+        .filter(|c| !c.embedding.is_empty())
+        .collect();
+    if probes.is_empty() {
+        bail!("No embedded chunks in the store; index the repository first: sgrep watch");
+    }
+    probes.sort_by(|a, b| a.id.cmp(&b.id));
+    probes.truncate(PROBE_CHUNKS);
+
+    let texts: Vec<String> = probes.iter().map(|c| c.content.clone()).collect();
+    let parts = embedder.embed_document_parts(&texts)?;
+    let agrees = |model: StoreModel| {
+        probes.iter().zip(&parts).all(|(chunk, part)| {
+            cosine_similarity(&chunk.embedding, &model.pick(part.clone())) >= SAME_MODEL
+        })
+    };
+    if agrees(StoreModel::Bge) {
+        Ok(StoreModel::Bge)
+    } else if agrees(StoreModel::Code) {
+        Ok(StoreModel::Code)
+    } else {
+        bail!(
+            "The store wasn't embedded with the local BGE or CodeRankEmbed model, so --hybrid can't search it"
+        )
+    }
+}
+
+/// BGE and CodeRankEmbed embeddings of every query, and of the documents as
+/// the store embeds its chunks
+fn embed_triples(triples: &[Triple], store: &VectorStore) -> Result<TripleEmbeddings> {
+    let mut embedder = HybridEmbedder::new()?;
+    let model = store_model(&mut embedder, store)?;
+    println!(
+        "{} documents with {}, the store's model",
+        "Embedding".cyan(),
+        model.name()
+    );
+    let mut progress = ProgressBar::new(triples.len(), "Embedding triples");
+
+    let (mut queries, mut positives, mut negatives) = (Vec::new(), Vec::new(), Vec::new());
+    for batch in triples.chunks(BATCH) {
+        for triple in batch {
+            queries.push(embedder.embed_query_parts(&triple.query)?);
+        }
+        let mut documents = |pick: fn(&Triple) -> &String| -> Result<Vec<Vec<f32>>> {
+            let texts: Vec<String> = batch.iter().map(|t| pick(t).clone()).collect();
+            let parts = embedder.embed_document_parts(&texts)?;
+            Ok(parts.into_iter().map(|p| model.pick(p)).collect())
+        };
+        positives.extend(documents(|t| &t.positive)?);
+        negatives.extend(documents(|t| &t.negative)?);
+        progress.set(queries.len());
+    }
+    progress.finish();
+
+    TripleEmbeddings::new(&queries, &positives, &negatives)
+}
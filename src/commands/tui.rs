@@ -0,0 +1,62 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::core::config::Config;
+use crate::core::filter::SearchFilter;
+use crate::core::query::repo_path_for;
+use crate::core::session::{EmbedMode, PipelineOptions, SearchSession};
+use crate::core::store::VectorStore;
+use crate::ui::tui::Browser;
+
+pub struct TuiOptions {
+    /// Initial query
+    pub query: Option<String>,
+    pub path: Option<String>,
+    pub globs: Vec<String>,
+    pub excludes: Vec<String>,
+    pub store: Option<String>,
+    pub max_count: usize,
+    pub code: bool,
+    pub hybrid: bool,
+    /// Route each query by intent until a mode is picked with Ctrl-E
+    pub route: bool,
+}
+
+pub async fn run(options: TuiOptions) -> Result<()> {
+    let config = Config::load()?;
+    let store = VectorStore::load(options.store.as_deref())?;
+
+    if store.chunk_count() == 0 {
+        println!("{}", "No files indexed yet. Run:".yellow());
+        println!("  sgrep watch [path]");
+        return Ok(());
+    }
+
+    let mut filter = SearchFilter::new();
+    if let Some(ref path) = options.path {
+        filter = filter.with_root(path)?;
+    }
+    filter = filter.with_globs(&options.globs, &options.excludes)?;
+
+    let mut session = SearchSession::new(
+        store,
+        config.clone(),
+        EmbedMode::from_flags(options.code, options.hybrid, false),
+        PipelineOptions {
+            max_count: options.max_count,
+            ..Default::default()
+        },
+    );
+    if options.route && !(options.code || options.hybrid) {
+        session = session.with_routing(&config);
+    }
+
+    Browser::new(
+        session,
+        filter,
+        repo_path_for(options.path.as_deref()),
+        options.query.unwrap_or_default(),
+    )
+    .run()
+    .await
+}
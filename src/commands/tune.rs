@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+
+use crate::core::config::Config;
+use crate::core::embeddings::cosine_similarity;
+use crate::core::feedback::{
+    fit_fusion, load_feedback, log_path, FeedbackEntry, FeedbackSample, FusionFit, VectorScore,
+};
+use crate::core::hybrid_embedder::{FusionStrategy, HybridEmbedder};
+use crate::core::session::EmbedMode;
+use crate::core::store::VectorStore;
+
+pub struct TuneOptions {
+    /// Store holding the chunks of hybrid queries
+    pub store: Option<String>,
+    /// Print the fit without saving it
+    pub dry_run: bool,
+    /// Remove tuned weights from the config
+    pub reset: bool,
+    pub json: bool,
+}
+
+pub async fn run(options: TuneOptions) -> Result<()> {
+    let mut config = Config::load()?;
+
+    if options.reset {
+        config.bm25_weight = None;
+        config.vector_weight = None;
+        config.hybrid_alpha = None;
+        config.save()?;
+        println!("{} Fusion weights reset to defaults", "✓".green());
+        return Ok(());
+    }
+
+    let entries = load_feedback()?;
+    if entries.is_empty() {
+        bail!(
+            "No feedback in {}. Rate results with: sgrep feedback <query-id> <rank> good|bad",
+            log_path()?.display()
+        );
+    }
+
+    // Alpha only models the weighted fusion; other strategies keep the
+    // logged vector scores
+    let weighted = matches!(
+        config.hybrid_strategy(),
+        FusionStrategy::WeightedAverage { .. }
+    );
+    let samples = feedback_samples(&entries, options.store.as_deref(), weighted)?;
+    let report = fit_fusion(
+        &samples,
+        config.get_fusion_weights(),
+        config.get_hybrid_alpha(),
+    )?;
+    let save = report.improved() && !options.dry_run;
+
+    if save {
+        config.bm25_weight = Some(report.fitted.bm25_weight);
+        config.vector_weight = Some(report.fitted.vector_weight);
+        if report.hybrid_pairs > 0 {
+            config.hybrid_alpha = Some(report.fitted.alpha);
+        }
+        config.save()?;
+    }
+
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "entries": entries.len(),
+                "report": report,
+                "saved": save,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} {} ratings, {} good/bad pairs ({} hybrid)",
+        "Tuning on".cyan(),
+        entries.len(),
+        report.pairs,
+        report.hybrid_pairs
+    );
+    if !weighted && entries.iter().any(|e| e.mode == EmbedMode::Hybrid.as_str()) {
+        println!(
+            "{}",
+            format!(
+                "Hybrid alpha not fitted: the {} fusion doesn't use it",
+                config.hybrid_strategy().name()
+            )
+            .dimmed()
+        );
+    }
+    println!();
+    print_fit("current", &report.current);
+    print_fit("fitted", &report.fitted);
+    println!();
+
+    if !report.improved() {
+        println!(
+            "{}",
+            "No weights order more pairs correctly; keeping the current ones".yellow()
+        );
+    } else if save {
+        println!("{} Saved fusion weights to config", "✓".green());
+        println!(
+            "{}",
+            "Retrieval scores changed scale; refit confidence with: sgrep calibrate <labels.json>"
+                .dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+fn print_fit(name: &str, fit: &FusionFit) {
+    println!(
+        "  {:<8} bm25 {:.2}  vector {:.2}  alpha {:.2}  {}",
+        name,
+        fit.bm25_weight,
+        fit.vector_weight,
+        fit.alpha,
+        format!("{:.0}% of pairs ordered", fit.accuracy * 100.0).dimmed()
+    );
+}
+
+/// Samples for the logged entries. With weighted fusion, hybrid queries are
+/// embedded again with both models so the vector score can be recomputed
+/// for any alpha.
+fn feedback_samples(
+    entries: &[FeedbackEntry],
+    store: Option<&str>,
+    weighted: bool,
+) -> Result<Vec<FeedbackSample>> {
+    let hybrid = EmbedMode::Hybrid.as_str();
+    if !weighted || !entries.iter().any(|e| e.mode == hybrid) {
+        return Ok(entries
+            .iter()
+            .map(|e| FeedbackSample::new(e, VectorScore::Fixed(e.vector_score)))
+            .collect());
+    }
+
+    let store = VectorStore::load(store)?;
+    let mut embedder = HybridEmbedder::new()?;
+    let mut queries: HashMap<&str, (Vec<f32>, Vec<f32>)> = HashMap::new();
+
+    let mut samples = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let chunk = store.chunks.get(&entry.chunk_id);
+        let vector = match chunk {
+            Some(chunk) if entry.mode == hybrid => {
+                if !queries.contains_key(entry.query.as_str()) {
+                    let parts = embedder.embed_query_parts(&entry.query)?;
+                    queries.insert(&entry.query, parts);
+                }
+                let (bge, code) = &queries[entry.query.as_str()];
+                if chunk.embedding.len() == bge.len() {
+                    VectorScore::Hybrid {
+                        bge: cosine_similarity(bge, &chunk.embedding),
+                        code: cosine_similarity(code, &chunk.embedding),
+                        cross: cosine_similarity(bge, code),
+                    }
+                } else {
+                    VectorScore::Fixed(entry.vector_score)
+                }
+            }
+            _ => VectorScore::Fixed(entry.vector_score),
+        };
+        samples.push(FeedbackSample::new(entry, vector));
+    }
+    Ok(samples)
+}
@@ -9,6 +9,7 @@ use std::time::Duration;
 use crate::core::chunker::CodeChunker;
 use crate::core::config::Config;
 use crate::core::embeddings::EmbeddingProvider;
+use crate::core::levels::embed_names;
 use crate::core::local_embeddings::SpeedMode;
 use crate::core::scanner::FileScanner;
 use crate::core::store::{
@@ -190,8 +191,10 @@ pub async fn sync_files(path: &str, store_name: Option<&str>, speed_mode: SpeedM
 
     pb.finish_and_clear();
 
-    // Update BM25 stats
+    // Update BM25 stats and the vectors of changed files
     store.update_bm25_stats();
+    let names = embed_names(&embeddings, store.stale_level_names()).await;
+    store.sync_levels(&names);
     store.save(store_name)?;
 
     println!(
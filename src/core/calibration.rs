@@ -0,0 +1,199 @@
+//! Score calibration
+//!
+//! Retrieval scores are not probabilities, and their scale depends on the
+//! embedding model: a cosine of 0.5 is a strong match for one model and
+//! noise for another. A per-model logistic (Platt) fit maps the retrieval
+//! score to the probability that a result is relevant. Fits come from
+//! labeled queries (`sgrep calibrate`) and are kept in
+//! `~/.sgrep/calibration.json`; unfitted models use a conservative default.
+//!
+//! Intent routing (see `core::intent`) changes the BM25 tokenizer and the
+//! lexical share of the score, so routed queries are fitted and looked up
+//! per model and intent (`calibration_key`).
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::core::config::Config;
+use crate::core::intent::Route;
+use crate::core::session::EmbedMode;
+
+/// Confidence below which results are reported as weak
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 0.5;
+
+/// Model key for the local BGE embedder (MCP "balanced" mode)
+pub const LOCAL_BALANCED: &str = "local:balanced";
+/// Model key for CodeRankEmbed
+pub const LOCAL_CODE: &str = "local:code";
+/// Model key for BGE + CodeRankEmbed fusion
+pub const HYBRID: &str = "hybrid";
+
+/// `confidence = sigmoid(a * score + b)`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Calibration {
+    pub a: f32,
+    pub b: f32,
+    /// Number of labeled results the fit used (0 = built-in default)
+    #[serde(default)]
+    pub samples: usize,
+}
+
+impl Default for Calibration {
+    /// Confidence crosses 50% at a retrieval score of 0.6
+    fn default() -> Self {
+        Self {
+            a: 12.0,
+            b: -7.2,
+            samples: 0,
+        }
+    }
+}
+
+impl Calibration {
+    pub fn confidence(&self, score: f32) -> f32 {
+        1.0 / (1.0 + (-(self.a * score + self.b)).exp())
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        self.samples > 0
+    }
+
+    /// Fit by gradient descent on the log loss, with light L2 regularization
+    /// so perfectly separable samples still give finite parameters
+    pub fn fit(samples: &[(f32, bool)]) -> Result<Self> {
+        let positives = samples.iter().filter(|(_, relevant)| *relevant).count();
+        if positives == 0 || positives == samples.len() {
+            bail!(
+                "Calibration needs both relevant and irrelevant results ({} of {} relevant)",
+                positives,
+                samples.len()
+            );
+        }
+
+        let n = samples.len() as f32;
+        let (mut a, mut b) = (1.0f32, 0.0f32);
+        let learning_rate = 1.0;
+        let l2 = 1e-3;
+        for _ in 0..5000 {
+            let (mut grad_a, mut grad_b) = (0.0, 0.0);
+            for &(score, relevant) in samples {
+                let p = 1.0 / (1.0 + (-(a * score + b)).exp());
+                let error = p - if relevant { 1.0 } else { 0.0 };
+                grad_a += error * score;
+                grad_b += error;
+            }
+            a -= learning_rate * (grad_a / n + l2 * a);
+            b -= learning_rate * grad_b / n;
+        }
+
+        Ok(Self {
+            a,
+            b,
+            samples: samples.len(),
+        })
+    }
+}
+
+/// Identifies the embedding model behind a query mode
+pub fn model_key(config: &Config, mode: EmbedMode) -> String {
+    match mode {
+        EmbedMode::Hybrid => HYBRID.to_string(),
+        EmbedMode::Code => LOCAL_CODE.to_string(),
+        EmbedMode::Default | EmbedMode::Colbert if config.provider == "local" => {
+            LOCAL_BALANCED.to_string()
+        }
+        EmbedMode::Default | EmbedMode::Colbert => {
+            format!("{}:{}", config.provider, config.model)
+        }
+    }
+}
+
+/// Key of the calibration for scores under `model`, with the intent of the
+/// query's route when it was routed
+pub fn calibration_key(model: &str, route: Option<&Route>) -> String {
+    match route {
+        Some(route) => format!("{}@{}", model, route.intent.as_str()),
+        None => model.to_string(),
+    }
+}
+
+/// Fitted calibrations per calibration key
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Calibrations {
+    models: HashMap<String, Calibration>,
+}
+
+impl Calibrations {
+    fn path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("calibration.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(Self::path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Fitted calibration for `model`, or the default
+    pub fn get(&self, model: &str) -> Calibration {
+        self.models.get(model).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, model: &str, calibration: Calibration) {
+        self.models.insert(model.to_string(), calibration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_separates_scores() {
+        let mut samples = Vec::new();
+        for i in 0..20 {
+            let jitter = i as f32 * 0.005;
+            samples.push((0.3 + jitter, false));
+            samples.push((0.45 + jitter, i % 4 == 0));
+            samples.push((0.6 + jitter, true));
+        }
+        let calibration = Calibration::fit(&samples).unwrap();
+
+        assert!(calibration.is_fitted());
+        assert!(calibration.confidence(0.3) < 0.2);
+        assert!(calibration.confidence(0.65) > 0.8);
+        assert!(calibration.confidence(0.4) < calibration.confidence(0.5));
+    }
+
+    #[test]
+    fn test_routed_queries_have_their_own_key() {
+        let config = Config::default();
+        let model = model_key(&config, EmbedMode::Hybrid);
+        assert_eq!(calibration_key(&model, None), "hybrid");
+
+        let store = crate::core::store::VectorStore::default();
+        let route =
+            crate::core::intent::Router::new(&config, &store, false).route("VectorStore::save");
+        assert_eq!(
+            calibration_key(&model, Some(&route)),
+            format!("hybrid@{}", route.intent.as_str())
+        );
+    }
+
+    #[test]
+    fn test_fit_needs_both_labels() {
+        assert!(Calibration::fit(&[(0.5, true), (0.6, true)]).is_err());
+        assert!(!Calibration::default().is_fitted());
+        assert!((Calibration::default().confidence(0.6) - 0.5).abs() < 1e-5);
+    }
+}
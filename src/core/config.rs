@@ -3,6 +3,11 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::core::cross_encoder::DEFAULT_RERANK_MODEL;
+use crate::core::hybrid_embedder::{FusionStrategy, DEFAULT_ALPHA};
+use crate::core::reranker::RerankerEndpoint;
+use crate::core::search::{DEFAULT_BM25_WEIGHT, DEFAULT_VECTOR_WEIGHT};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -15,6 +20,26 @@ pub struct Config {
     pub provider: String,
     #[serde(default)]
     pub local_url: Option<String>,
+    #[serde(default)]
+    pub rerank_model: Option<String>,
+    #[serde(default)]
+    pub rerank_top_n: Option<usize>,
+    #[serde(default)]
+    pub reranker: Option<RerankerEndpoint>,
+    /// Retrieval score weights fitted by `sgrep tune`
+    #[serde(default)]
+    pub bm25_weight: Option<f32>,
+    #[serde(default)]
+    pub vector_weight: Option<f32>,
+    /// BGE weight of hybrid query fusion, fitted by `sgrep tune`
+    #[serde(default)]
+    pub hybrid_alpha: Option<f32>,
+    /// Hybrid fusion strategy; `sgrep train-fusion` sets the learned ones
+    #[serde(default)]
+    pub hybrid_fusion: Option<String>,
+    /// Let intent routing switch code-shaped queries to hybrid embedding
+    #[serde(default)]
+    pub route_embedder: Option<bool>,
 }
 
 fn default_model() -> String {
@@ -33,6 +58,14 @@ impl Default for Config {
             base_url: None,
             provider: default_provider(),
             local_url: None,
+            rerank_model: None,
+            rerank_top_n: None,
+            reranker: None,
+            bm25_weight: None,
+            vector_weight: None,
+            hybrid_alpha: None,
+            hybrid_fusion: None,
+            route_embedder: None,
         }
     }
 }
@@ -88,6 +121,54 @@ impl Config {
             .unwrap_or_else(|| "http://localhost:11434".to_string())
     }
 
+    /// Cross-encoder used for local reranking. Off unless configured, since
+    /// it downloads a model and runs on the CPU for every reranked search
+    /// (searches note once that reranking was skipped while it is unset);
+    /// "default" picks `DEFAULT_RERANK_MODEL`, "none" turns it off quietly.
+    pub fn get_rerank_model(&self) -> Option<String> {
+        match self.rerank_model.as_deref() {
+            None | Some("none") | Some("off") => None,
+            Some("default") => Some(DEFAULT_RERANK_MODEL.to_string()),
+            Some(model) => Some(model.to_string()),
+        }
+    }
+
+    /// Whether routing may change the query embedder. Off by default: it
+    /// mixes CodeRankEmbed into queries against a BGE-only index, which
+    /// should only be turned on once `sgrep eval` shows it helps.
+    pub fn get_route_embedder(&self) -> bool {
+        self.route_embedder.unwrap_or(false)
+    }
+
+    /// Number of candidates scored by the local cross-encoder
+    pub fn get_rerank_top_n(&self) -> usize {
+        self.rerank_top_n.unwrap_or(30)
+    }
+
+    /// BM25 and vector weights of the combined retrieval score
+    pub fn get_fusion_weights(&self) -> (f32, f32) {
+        (
+            self.bm25_weight.unwrap_or(DEFAULT_BM25_WEIGHT),
+            self.vector_weight.unwrap_or(DEFAULT_VECTOR_WEIGHT),
+        )
+    }
+
+    pub fn get_hybrid_alpha(&self) -> f32 {
+        self.hybrid_alpha.unwrap_or(DEFAULT_ALPHA)
+    }
+
+    /// Fusion of BGE and CodeRankEmbed embeddings in hybrid mode
+    pub fn hybrid_strategy(&self) -> FusionStrategy {
+        match self.hybrid_fusion.as_deref().map(str::parse) {
+            Some(Ok(FusionStrategy::WeightedAverage { .. })) | Some(Err(_)) | None => {
+                FusionStrategy::WeightedAverage {
+                    alpha: self.get_hybrid_alpha(),
+                }
+            }
+            Some(Ok(strategy)) => strategy,
+        }
+    }
+
     pub fn is_local(&self) -> bool {
         self.provider == "local"
     }
@@ -0,0 +1,196 @@
+//! Local cross-encoder reranker
+//!
+//! Scores (query, document) pairs jointly with a BERT cross-encoder
+//! (e.g. cross-encoder/ms-marco-MiniLM-L-6-v2) loaded through Candle.
+//! Unlike the bi-encoder used for retrieval, the cross-encoder attends over
+//! query and code together, which gives a much sharper relevance signal
+//! for the final ordering - with no network access once the model is cached.
+//!
+//! Only BERT sequence-classification checkpoints with `model.safetensors`
+//! load; other architectures (e.g. the XLM-RoBERTa based BGE rerankers) are
+//! rejected up front.
+
+use anyhow::{anyhow, bail, Result};
+use candle_core::{DType, Device, IndexOp, Module, Tensor};
+use candle_nn::{linear, Linear, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+/// Default cross-encoder model (22M params, trained on MS MARCO)
+pub const DEFAULT_RERANK_MODEL: &str = "cross-encoder/ms-marco-MiniLM-L-6-v2";
+
+/// Number of (query, document) pairs scored per forward pass
+const BATCH_SIZE: usize = 16;
+
+/// Maximum sequence length for a (query, document) pair
+const MAX_SEQ_LEN: usize = 512;
+
+/// BERT cross-encoder with a sequence classification head
+pub struct CrossEncoder {
+    model: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl CrossEncoder {
+    /// Load a BERT-architecture cross-encoder from the Hugging Face Hub
+    pub fn load(model_id: &str) -> Result<Self> {
+        let device = Device::Cpu;
+
+        let api = Api::new()?;
+        let repo = api.repo(Repo::with_revision(
+            model_id.to_string(),
+            RepoType::Model,
+            "main".to_string(),
+        ));
+
+        // Check the architecture before downloading the weights
+        let config_path = repo.get("config.json")?;
+        let raw: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+        check_architecture(model_id, &raw)?;
+        let tokenizer_path = repo.get("tokenizer.json")?;
+        let weights_path = repo.get("model.safetensors")?;
+
+        let mut config: BertConfig = serde_json::from_value(raw)?;
+        // Sequence classification checkpoints nest the encoder under "bert."
+        if config.model_type.is_none() {
+            config.model_type = Some("bert".to_string());
+        }
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_SEQ_LEN,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure truncation: {}", e))?;
+
+        let vb =
+            unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DType::F32, &device)? };
+        let model = BertModel::load(vb.clone(), &config)?;
+        let pooler = linear(
+            config.hidden_size,
+            config.hidden_size,
+            vb.pp("bert.pooler.dense"),
+        )?;
+        let classifier = linear(config.hidden_size, 1, vb.pp("classifier"))?;
+
+        Ok(Self {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// Score each document against the query.
+    /// Returns relevance probabilities in [0, 1], in document order.
+    pub fn score(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let mut scores = Vec::with_capacity(documents.len());
+        for batch in documents.chunks(BATCH_SIZE) {
+            scores.extend(self.score_batch(query, batch)?);
+        }
+        Ok(scores)
+    }
+
+    fn score_batch(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let pairs: Vec<(&str, &str)> = documents.iter().map(|d| (query, d.as_str())).collect();
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+
+        let batch = encodings.len();
+        let seq_len = encodings.first().map(|e| e.get_ids().len()).unwrap_or(0);
+        if batch == 0 || seq_len == 0 {
+            return Ok(vec![0.0; documents.len()]);
+        }
+
+        let mut input_ids = Vec::with_capacity(batch * seq_len);
+        let mut type_ids = Vec::with_capacity(batch * seq_len);
+        let mut attention_mask = Vec::with_capacity(batch * seq_len);
+        for encoding in &encodings {
+            input_ids.extend_from_slice(encoding.get_ids());
+            type_ids.extend_from_slice(encoding.get_type_ids());
+            attention_mask.extend_from_slice(encoding.get_attention_mask());
+        }
+
+        let input_ids = Tensor::from_vec(input_ids, (batch, seq_len), &self.device)?;
+        let type_ids = Tensor::from_vec(type_ids, (batch, seq_len), &self.device)?;
+        let attention_mask = Tensor::from_vec(attention_mask, (batch, seq_len), &self.device)?;
+
+        // [batch, seq, hidden] -> [CLS] token -> pooler (tanh) -> logit
+        let hidden = self
+            .model
+            .forward(&input_ids, &type_ids, Some(&attention_mask))?;
+        let cls = hidden.i((.., 0))?;
+        let pooled = self.pooler.forward(&cls)?.tanh()?;
+        let logits = self.classifier.forward(&pooled)?.squeeze(1)?;
+
+        let logits: Vec<f32> = logits.to_vec1()?;
+        Ok(logits.into_iter().map(sigmoid).collect())
+    }
+}
+
+/// Fail unless `config` (a model's config.json) describes a BERT model
+/// with a sequence classification head
+fn check_architecture(model_id: &str, config: &serde_json::Value) -> Result<()> {
+    let model_type = config
+        .get("model_type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("bert");
+    let architectures: Vec<&str> = config
+        .get("architectures")
+        .and_then(|a| a.as_array())
+        .map(|a| a.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    if model_type != "bert" || !architectures.contains(&"BertForSequenceClassification") {
+        bail!(
+            "{} is not supported by the local reranker ({} {}); only BERT cross-encoders \
+             (BertForSequenceClassification) such as {} load",
+            model_id,
+            model_type,
+            architectures.join(", "),
+            DEFAULT_RERANK_MODEL
+        );
+    }
+    Ok(())
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_architecture() {
+        let minilm = serde_json::json!({
+            "model_type": "bert",
+            "architectures": ["BertForSequenceClassification"],
+        });
+        assert!(check_architecture(DEFAULT_RERANK_MODEL, &minilm).is_ok());
+
+        let bge = serde_json::json!({
+            "model_type": "xlm-roberta",
+            "architectures": ["XLMRobertaForSequenceClassification"],
+        });
+        let err = check_architecture("BAAI/bge-reranker-base", &bge)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("xlm-roberta"), "{}", err);
+
+        // A bare encoder has no classification head to score with
+        let encoder = serde_json::json!({"model_type": "bert", "architectures": ["BertModel"]});
+        assert!(check_architecture("bert-base-uncased", &encoder).is_err());
+    }
+}
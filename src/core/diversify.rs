@@ -0,0 +1,192 @@
+//! Result diversification
+//!
+//! Maximal Marginal Relevance (MMR) re-selection of search results, so that
+//! near-duplicate chunks (adjacent fragments of one file, vendored copies)
+//! don't crowd out everything else. Redundancy is measured with the stored
+//! chunk embeddings, falling back to token overlap for BM25-only indexes.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::core::embeddings::cosine_similarity;
+use crate::core::search::SearchResult;
+
+/// Default trade-off between relevance (1.0) and diversity (0.0)
+pub const DEFAULT_LAMBDA: f32 = 0.7;
+
+#[derive(Debug, Clone)]
+pub struct DiversifyOptions {
+    /// Weight of relevance vs. redundancy penalty
+    pub lambda: f32,
+    /// Maximum number of results taken from a single file
+    pub max_per_file: Option<usize>,
+}
+
+impl Default for DiversifyOptions {
+    fn default() -> Self {
+        Self {
+            lambda: DEFAULT_LAMBDA,
+            max_per_file: None,
+        }
+    }
+}
+
+impl DiversifyOptions {
+    pub fn new(lambda: f32) -> Self {
+        Self {
+            lambda: lambda.clamp(0.0, 1.0),
+            max_per_file: None,
+        }
+    }
+
+    pub fn with_max_per_file(mut self, max_per_file: Option<usize>) -> Self {
+        self.max_per_file = max_per_file.filter(|&n| n > 0);
+        self
+    }
+}
+
+/// Greedily select `limit` results maximizing
+/// `lambda * relevance - (1 - lambda) * max_similarity_to_selected`.
+/// Original scores are kept; only the selection and order change.
+pub fn diversify(
+    results: Vec<SearchResult>,
+    limit: usize,
+    options: &DiversifyOptions,
+) -> Vec<SearchResult> {
+    if results.len() <= 1 || limit == 0 {
+        return results.into_iter().take(limit).collect();
+    }
+
+    // Normalize relevance to [0, 1] so it is comparable with cosine similarity
+    let max_score = results
+        .iter()
+        .map(|r| r.score)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let min_score = results
+        .iter()
+        .map(|r| r.score)
+        .fold(f32::INFINITY, f32::min);
+    let range = (max_score - min_score).max(1e-6);
+    let relevance: Vec<f32> = results
+        .iter()
+        .map(|r| (r.score - min_score) / range)
+        .collect();
+
+    // Token overlap stands in for cosine when embeddings are missing (BM25-only tier)
+    let dim = results[0].chunk.embedding.len();
+    let needs_tokens = dim == 0 || results.iter().any(|r| r.chunk.embedding.len() != dim);
+    let token_sets: Vec<HashSet<&str>> = if needs_tokens {
+        results.iter().map(|r| tokenize(&r.chunk.content)).collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut remaining: Vec<usize> = (0..results.len()).collect();
+    let mut selected: Vec<usize> = Vec::new();
+    let mut per_file: HashMap<&str, usize> = HashMap::new();
+
+    while selected.len() < limit && !remaining.is_empty() {
+        let mut best: Option<(usize, f32)> = None;
+
+        for (pos, &i) in remaining.iter().enumerate() {
+            if let Some(cap) = options.max_per_file {
+                let count = per_file
+                    .get(results[i].chunk.file_path.as_str())
+                    .copied()
+                    .unwrap_or(0);
+                if count >= cap {
+                    continue;
+                }
+            }
+
+            let redundancy = selected
+                .iter()
+                .map(|&j| similarity(&results, &token_sets, i, j))
+                .fold(0.0f32, f32::max);
+            let mmr = options.lambda * relevance[i] - (1.0 - options.lambda) * redundancy;
+
+            if best.map(|(_, s)| mmr > s).unwrap_or(true) {
+                best = Some((pos, mmr));
+            }
+        }
+
+        let Some((pos, _)) = best else {
+            break;
+        };
+        let i = remaining.remove(pos);
+        *per_file
+            .entry(results[i].chunk.file_path.as_str())
+            .or_insert(0) += 1;
+        selected.push(i);
+    }
+
+    let mut slots: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+    selected
+        .into_iter()
+        .filter_map(|i| slots[i].take())
+        .collect()
+}
+
+fn similarity(results: &[SearchResult], token_sets: &[HashSet<&str>], a: usize, b: usize) -> f32 {
+    if token_sets.is_empty() {
+        cosine_similarity(&results[a].chunk.embedding, &results[b].chunk.embedding)
+    } else {
+        jaccard(&token_sets[a], &token_sets[b])
+    }
+}
+
+fn tokenize(content: &str) -> HashSet<&str> {
+    content
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let intersection = a.intersection(b).count() as f32;
+    let union = a.union(b).count() as f32;
+    intersection / union
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+
+    fn result(file: &str, score: f32, embedding: Vec<f32>) -> SearchResult {
+        let id = format!("{}:{}", file, score);
+        SearchResult::test(
+            FileChunk::test(&id, file, (1, 10)).with_embedding(embedding),
+            score,
+        )
+    }
+
+    #[test]
+    fn test_mmr_skips_near_duplicates() {
+        let results = vec![
+            result("/a.rs", 0.95, vec![1.0, 0.0]),
+            result("/vendor/a.rs", 0.94, vec![1.0, 0.01]),
+            result("/b.rs", 0.80, vec![0.0, 1.0]),
+        ];
+
+        let picked = diversify(results, 2, &DiversifyOptions::new(0.5));
+        assert_eq!(picked[0].chunk.file_path, "/a.rs");
+        assert_eq!(picked[1].chunk.file_path, "/b.rs");
+    }
+
+    #[test]
+    fn test_max_per_file() {
+        let results = vec![
+            result("/a.rs", 0.9, vec![1.0, 0.0]),
+            result("/a.rs", 0.8, vec![0.0, 1.0]),
+            result("/b.rs", 0.1, vec![0.7, 0.7]),
+        ];
+
+        let options = DiversifyOptions::new(1.0).with_max_per_file(Some(1));
+        let picked = diversify(results, 3, &options);
+        assert_eq!(picked.len(), 2);
+        assert_eq!(picked[1].chunk.file_path, "/b.rs");
+    }
+}
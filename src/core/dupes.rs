@@ -0,0 +1,300 @@
+//! Duplicate code detection
+//!
+//! Each chunk's stored embedding is matched against its nearest neighbours
+//! (through the ANN index when the store has one, exhaustively otherwise).
+//! Pairs above the embedding threshold are confirmed by the overlap of their
+//! token shingles, so code that is merely about the same thing is not
+//! reported, and confirmed pairs are joined into clusters.
+
+use rayon::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::core::embeddings::cosine_similarity;
+use crate::core::filter::SearchFilter;
+use crate::core::store::{FileChunk, VectorStore};
+
+/// Default embedding similarity for a candidate pair
+pub const DEFAULT_THRESHOLD: f32 = 0.95;
+/// Default token shingle overlap that confirms a candidate pair
+pub const DEFAULT_TOKEN_THRESHOLD: f32 = 0.5;
+
+/// Tokens per shingle
+const SHINGLE_SIZE: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct DupeOptions {
+    /// Minimum cosine similarity of the stored embeddings
+    pub threshold: f32,
+    /// Minimum Jaccard overlap of token shingles
+    pub token_threshold: f32,
+    /// Chunks shorter than this many lines are ignored
+    pub min_lines: usize,
+    /// Neighbours looked up per chunk
+    pub neighbours: usize,
+}
+
+impl Default for DupeOptions {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            token_threshold: DEFAULT_TOKEN_THRESHOLD,
+            min_lines: 5,
+            neighbours: 10,
+        }
+    }
+}
+
+/// Chunks that are near-duplicates of each other
+#[derive(Debug)]
+pub struct DupeCluster<'a> {
+    /// Members ordered by path and line
+    pub chunks: Vec<&'a FileChunk>,
+    /// Lowest embedding similarity among the confirmed pairs
+    pub similarity: f32,
+    /// Lowest token overlap among the confirmed pairs
+    pub token_similarity: f32,
+}
+
+impl DupeCluster<'_> {
+    /// Lines that would go away if the cluster were reduced to one copy
+    pub fn duplicated_lines(&self) -> usize {
+        let lines: Vec<usize> = self.chunks.iter().map(|c| line_count(c)).collect();
+        lines.iter().sum::<usize>() - lines.iter().max().unwrap_or(&0)
+    }
+}
+
+/// Clusters of near-duplicate chunks, largest duplication first
+pub fn find_duplicates<'a>(
+    store: &'a VectorStore,
+    options: &DupeOptions,
+    filter: Option<&SearchFilter>,
+) -> Vec<DupeCluster<'a>> {
+    let filter = filter.filter(|f| !f.is_empty());
+    let mut chunks: Vec<&FileChunk> = store
+        .all_chunks()
+        .filter(|c| !c.embedding.is_empty() && line_count(c) >= options.min_lines)
+        .filter(|c| filter.is_none_or(|f| f.matches_chunk(c)))
+        .collect();
+    chunks.sort_by(|a, b| {
+        a.file_path
+            .cmp(&b.file_path)
+            .then(a.start_line.cmp(&b.start_line))
+    });
+    let index: HashMap<&str, usize> = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.as_str(), i))
+        .collect();
+    let shingles: Vec<HashSet<u64>> = chunks.par_iter().map(|c| shingles(&c.content)).collect();
+
+    let mut pairs: Vec<(usize, usize, f32, f32)> = (0..chunks.len())
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let neighbours = ann_neighbours(store, &chunks, &index, i, options.neighbours)
+                .unwrap_or_else(|| (i + 1..chunks.len()).collect());
+            neighbours
+                .into_iter()
+                .filter(|&j| j != i && !overlaps(chunks[i], chunks[j]))
+                .filter_map(|j| {
+                    let similarity = cosine_similarity(&chunks[i].embedding, &chunks[j].embedding);
+                    if similarity < options.threshold {
+                        return None;
+                    }
+                    let token_similarity = jaccard(&shingles[i], &shingles[j]);
+                    (token_similarity >= options.token_threshold).then_some((
+                        i.min(j),
+                        i.max(j),
+                        similarity,
+                        token_similarity,
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    // ANN lookups find most pairs from both ends
+    pairs.sort_by_key(|&(i, j, _, _)| (i, j));
+    pairs.dedup_by_key(|&mut (i, j, _, _)| (i, j));
+
+    let mut parents: Vec<usize> = (0..chunks.len()).collect();
+    for &(i, j, _, _) in &pairs {
+        let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+        parents[a.max(b)] = a.min(b);
+    }
+
+    let mut clusters: HashMap<usize, DupeCluster> = HashMap::new();
+    for &(i, j, similarity, token_similarity) in &pairs {
+        let root = find(&mut parents, i);
+        let cluster = clusters.entry(root).or_insert_with(|| DupeCluster {
+            chunks: Vec::new(),
+            similarity,
+            token_similarity,
+        });
+        for member in [i, j] {
+            if !cluster.chunks.iter().any(|c| c.id == chunks[member].id) {
+                cluster.chunks.push(chunks[member]);
+            }
+        }
+        cluster.similarity = cluster.similarity.min(similarity);
+        cluster.token_similarity = cluster.token_similarity.min(token_similarity);
+    }
+
+    let mut clusters: Vec<DupeCluster> = clusters.into_values().collect();
+    for cluster in &mut clusters {
+        cluster.chunks.sort_by(|a, b| {
+            a.file_path
+                .cmp(&b.file_path)
+                .then(a.start_line.cmp(&b.start_line))
+        });
+    }
+    clusters.sort_by(|a, b| {
+        b.duplicated_lines()
+            .cmp(&a.duplicated_lines())
+            .then_with(|| a.chunks[0].file_path.cmp(&b.chunks[0].file_path))
+            .then_with(|| a.chunks[0].start_line.cmp(&b.chunks[0].start_line))
+    });
+    clusters
+}
+
+/// The `wanted` nearest candidates to `chunks[i]` from the ANN index, or
+/// `None` without one. The chunk itself, chunks overlapping it and chunks
+/// left out by `min_lines` or the filter take up result slots, so the
+/// lookup widens until enough remain (as `similar_to` does).
+fn ann_neighbours(
+    store: &VectorStore,
+    chunks: &[&FileChunk],
+    index: &HashMap<&str, usize>,
+    i: usize,
+    wanted: usize,
+) -> Option<Vec<usize>> {
+    let overlapping = store
+        .chunks_for_file(&chunks[i].file_path)
+        .iter()
+        .filter(|c| overlaps(c, chunks[i]))
+        .count();
+    let mut limit = wanted + overlapping.max(1);
+    loop {
+        let hits = store.ann_search(&chunks[i].embedding, limit)?;
+        let neighbours: Vec<usize> = hits
+            .iter()
+            .filter_map(|(id, _)| index.get(id.as_str()).copied())
+            .filter(|&j| j != i && !overlaps(chunks[i], chunks[j]))
+            .take(wanted)
+            .collect();
+        if neighbours.len() >= wanted || hits.len() < limit || limit >= store.chunk_count() {
+            return Some(neighbours);
+        }
+        limit *= 2;
+    }
+}
+
+fn line_count(chunk: &FileChunk) -> usize {
+    chunk.end_line.saturating_sub(chunk.start_line) + 1
+}
+
+/// Same file and overlapping lines: a chunk nested in another
+fn overlaps(a: &FileChunk, b: &FileChunk) -> bool {
+    a.file_path == b.file_path && a.start_line <= b.end_line && b.start_line <= a.end_line
+}
+
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+/// Hashes of each run of `SHINGLE_SIZE` consecutive tokens
+fn shingles(content: &str) -> HashSet<u64> {
+    let tokens: Vec<&str> = content
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .collect();
+    tokens
+        .windows(SHINGLE_SIZE.min(tokens.len()).max(1))
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let intersection = a.intersection(b).count() as f32;
+    let union = a.union(b).count() as f32;
+    intersection / union
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(
+        id: &str,
+        file: &str,
+        lines: (usize, usize),
+        content: &str,
+        embedding: Vec<f32>,
+    ) -> FileChunk {
+        FileChunk::test(id, file, lines)
+            .with_content(content)
+            .with_embedding(embedding)
+    }
+
+    #[test]
+    fn test_clusters_confirmed_duplicates() {
+        let retry = "fn retry(op: F) { for attempt in 0..3 { if let Ok(v) = op() { return v; } sleep(backoff(attempt)); } }";
+        let retry_copy = "fn retry_io(op: F) { for attempt in 0..3 { if let Ok(v) = op() { return v; } sleep(backoff(attempt)); } }";
+        let other = "fn parse_config(path: &Path) -> Config { toml::from_str(&read(path)) }";
+
+        let store = VectorStore::with_test_chunks(vec![
+            chunk("a", "/r/a.rs", (1, 10), retry, vec![1.0, 0.0]),
+            chunk("b", "/r/b.rs", (20, 29), retry_copy, vec![0.99, 0.05]),
+            chunk("c", "/r/c.rs", (1, 10), retry, vec![0.98, 0.1]),
+            // Embedded alike but different code: not confirmed
+            chunk("d", "/r/d.rs", (1, 10), other, vec![1.0, 0.01]),
+            // Too short
+            chunk("e", "/r/e.rs", (1, 2), retry, vec![1.0, 0.0]),
+        ]);
+
+        let clusters = find_duplicates(&store, &DupeOptions::default(), None);
+        assert_eq!(clusters.len(), 1);
+        let ids: Vec<&str> = clusters[0].chunks.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(clusters[0].duplicated_lines(), 20);
+        assert!(clusters[0].token_similarity >= DEFAULT_TOKEN_THRESHOLD);
+    }
+
+    #[test]
+    fn test_ann_lookup_skips_overlapping_and_short_chunks() {
+        let retry = "fn retry(op: F) { for attempt in 0..3 { if let Ok(v) = op() { return v; } sleep(backoff(attempt)); } }";
+        let retry_copy = "fn retry_io(op: F) { for attempt in 0..3 { if let Ok(v) = op() { return v; } sleep(backoff(attempt)); } }";
+        let other = "fn parse_config(path: &Path) -> Config { toml::from_str(&read(path)) }";
+
+        // Each side's nearest neighbours are a nested chunk or short chunks
+        // that are never candidates
+        let mut store = VectorStore::with_test_chunks(vec![
+            chunk("a", "/r/a.rs", (1, 10), retry, vec![1.0, 0.0, 0.0]),
+            chunk("nested", "/r/a.rs", (2, 8), other, vec![1.0, 0.0, 0.01]),
+            chunk("b", "/r/b.rs", (20, 29), retry_copy, vec![0.97, 0.0, 0.2]),
+            chunk("s1", "/r/s.rs", (1, 2), retry, vec![0.97, 0.0, 0.2]),
+            chunk("s2", "/r/s.rs", (3, 4), retry, vec![0.97, 0.0, 0.2]),
+        ]);
+        store.build_ann_index().unwrap();
+
+        let options = DupeOptions {
+            neighbours: 1,
+            ..DupeOptions::default()
+        };
+        let clusters = find_duplicates(&store, &options, None);
+        assert_eq!(clusters.len(), 1);
+        let ids: Vec<&str> = clusters[0].chunks.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+}
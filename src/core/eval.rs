@@ -0,0 +1,234 @@
+//! Retrieval evaluation
+//!
+//! Scores ranked results against labeled queries (see `core::labels`):
+//! recall@k over the labeled targets, reciprocal rank of the first relevant
+//! result, and nDCG@k with binary gains. Reports are saved as JSON so a
+//! later run can be compared query by query.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+use crate::core::labels::LabeledQuery;
+use crate::core::search::SearchResult;
+use crate::core::store::FileChunk;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryMetrics {
+    pub query: String,
+    pub recall: f32,
+    pub reciprocal_rank: f32,
+    pub ndcg: f32,
+    /// 1-based rank of the first relevant result
+    pub first_hit: Option<usize>,
+    /// `path:start-end` of the top results, for diffs
+    pub top: Vec<String>,
+}
+
+/// One search configuration run over every labeled query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Configuration name, e.g. `code+rerank`
+    pub name: String,
+    pub k: usize,
+    pub queries: Vec<QueryMetrics>,
+}
+
+impl RunReport {
+    pub fn recall(&self) -> f32 {
+        self.mean(|q| q.recall)
+    }
+
+    pub fn mrr(&self) -> f32 {
+        self.mean(|q| q.reciprocal_rank)
+    }
+
+    pub fn ndcg(&self) -> f32 {
+        self.mean(|q| q.ndcg)
+    }
+
+    fn mean(&self, metric: impl Fn(&QueryMetrics) -> f32) -> f32 {
+        if self.queries.is_empty() {
+            return 0.0;
+        }
+        self.queries.iter().map(metric).sum::<f32>() / self.queries.len() as f32
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvalReport {
+    pub labels: String,
+    pub created_at: String,
+    pub runs: Vec<RunReport>,
+}
+
+impl EvalReport {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read eval report: {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid eval report: {}", path))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write eval report: {}", path))
+    }
+
+    pub fn run(&self, name: &str) -> Option<&RunReport> {
+        self.runs.iter().find(|r| r.name == name)
+    }
+}
+
+/// Score the top `k` of `results` for one labeled query
+pub fn score_query(label: &LabeledQuery, results: &[SearchResult], k: usize) -> QueryMetrics {
+    let top: Vec<&FileChunk> = results.iter().take(k).map(|r| &r.chunk).collect();
+    let relevant: Vec<bool> = top.iter().map(|c| label.is_relevant(c)).collect();
+
+    let targets = label.target_count();
+    let recall = if targets == 0 {
+        0.0
+    } else {
+        label.targets_found(&top) as f32 / targets as f32
+    };
+
+    let first_hit = relevant.iter().position(|&r| r).map(|i| i + 1);
+    // A result gains only for targets no higher result covered, so two
+    // chunks of one relevant file count once, as in the ideal ranking
+    let mut covered = HashSet::new();
+    let dcg: f32 = top
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| {
+            let mut new = false;
+            for target in label.targets_of(chunk) {
+                new |= covered.insert(target);
+            }
+            new
+        })
+        .map(|(i, _)| discount(i))
+        .sum();
+    let ideal: f32 = (0..targets.min(k)).map(discount).sum();
+
+    QueryMetrics {
+        query: label.query.clone(),
+        recall,
+        reciprocal_rank: first_hit.map_or(0.0, |rank| 1.0 / rank as f32),
+        ndcg: if ideal > 0.0 { dcg / ideal } else { 0.0 },
+        first_hit,
+        top: top
+            .iter()
+            .map(|c| format!("{}:{}-{}", c.file_path, c.start_line, c.end_line))
+            .collect(),
+    }
+}
+
+fn discount(rank: usize) -> f32 {
+    1.0 / (rank as f32 + 2.0).log2()
+}
+
+/// A query whose scores differ between two runs
+pub struct QueryDiff<'a> {
+    pub before: &'a QueryMetrics,
+    pub after: &'a QueryMetrics,
+}
+
+impl QueryDiff<'_> {
+    pub fn ndcg_delta(&self) -> f32 {
+        self.after.ndcg - self.before.ndcg
+    }
+}
+
+/// Queries present in both runs whose recall, rank or nDCG changed, largest
+/// change first
+pub fn diff_runs<'a>(before: &'a RunReport, after: &'a RunReport) -> Vec<QueryDiff<'a>> {
+    let mut diffs: Vec<QueryDiff> = after
+        .queries
+        .iter()
+        .filter_map(|a| {
+            let b = before.queries.iter().find(|b| b.query == a.query)?;
+            let changed = (a.ndcg - b.ndcg).abs() > 1e-4
+                || (a.recall - b.recall).abs() > 1e-4
+                || a.first_hit != b.first_hit;
+            changed.then_some(QueryDiff {
+                before: b,
+                after: a,
+            })
+        })
+        .collect();
+    diffs.sort_by(|a, b| b.ndcg_delta().abs().total_cmp(&a.ndcg_delta().abs()));
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(file: &str, start: usize) -> SearchResult {
+        let id = format!("{}:{}", file, start);
+        SearchResult::test(FileChunk::test(&id, file, (start, start + 9)), 1.0)
+    }
+
+    #[test]
+    fn test_score_query() {
+        let label = LabeledQuery {
+            query: "persist".to_string(),
+            relevant: vec!["store.rs:100-120".to_string(), "cache.rs".to_string()],
+            symbols: Vec::new(),
+        };
+        let results = vec![
+            result("/r/src/search.rs", 1),
+            result("/r/src/store.rs", 100),
+            result("/r/src/main.rs", 1),
+        ];
+
+        let metrics = score_query(&label, &results, 3);
+        assert_eq!(metrics.first_hit, Some(2));
+        assert_eq!(metrics.reciprocal_rank, 0.5);
+        assert_eq!(metrics.recall, 0.5);
+        // DCG 1/log2(3) against an ideal of 1 + 1/log2(3)
+        let expected = (1.0 / 3f32.log2()) / (1.0 + 1.0 / 3f32.log2());
+        assert!((metrics.ndcg - expected).abs() < 1e-6);
+
+        let missed = score_query(&label, &results, 1);
+        assert_eq!(missed.first_hit, None);
+        assert_eq!(missed.ndcg, 0.0);
+
+        let before = RunReport {
+            name: "default".to_string(),
+            k: 1,
+            queries: vec![missed],
+        };
+        let after = RunReport {
+            name: "default".to_string(),
+            k: 3,
+            queries: vec![metrics],
+        };
+        let diffs = diff_runs(&before, &after);
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].ndcg_delta() > 0.0);
+        assert!(diff_runs(&after, &after).is_empty());
+    }
+
+    #[test]
+    fn test_one_target_gains_once() {
+        let label = LabeledQuery {
+            query: "persist".to_string(),
+            relevant: vec!["store.rs".to_string(), "cache.rs".to_string()],
+            symbols: Vec::new(),
+        };
+        // Two chunks of the whole-file target, then the other target
+        let results = vec![
+            result("/r/src/store.rs", 1),
+            result("/r/src/store.rs", 50),
+            result("/r/src/cache.rs", 1),
+        ];
+
+        let metrics = score_query(&label, &results, 3);
+        assert_eq!(metrics.recall, 1.0);
+        let ideal = 1.0 + 1.0 / 3f32.log2();
+        let expected = (1.0 + 1.0 / 4f32.log2()) / ideal;
+        assert!((metrics.ndcg - expected).abs() < 1e-6);
+        assert!(metrics.ndcg < 1.0);
+    }
+}
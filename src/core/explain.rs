@@ -0,0 +1,176 @@
+//! Why a result matched
+//!
+//! Breaks a result's rank down into the retrieval score components, the
+//! query terms BM25 found in the chunk, how reranking moved it, and
+//! whether it was an ANN neighbour, a lexical candidate or neither.
+
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::core::search::{CandidateSource, HybridSearcher, ScoreComponent, SearchResult};
+use crate::core::session::SearchTrace;
+use crate::core::store::VectorStore;
+
+/// A query term and how it scored against the chunk
+#[derive(Debug, Clone)]
+pub struct TermMatch {
+    pub term: String,
+    pub idf: f32,
+    /// Occurrences in the chunk (0 = not matched)
+    pub tf: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub components: Vec<ScoreComponent>,
+    /// Sum of component contributions
+    pub retrieval_score: f32,
+    pub terms: Vec<TermMatch>,
+    /// 1-based rank after retrieval, if the chunk was retrieved directly
+    pub retrieval_rank: Option<usize>,
+    /// 1-based rank and score after reranking, if reranking ran
+    pub reranked: Option<(usize, f32)>,
+    pub final_rank: usize,
+    pub final_score: f32,
+    pub source: CandidateSource,
+}
+
+impl Explanation {
+    /// Share of each component in the retrieval score
+    pub fn shares(&self) -> Vec<f32> {
+        let total: f32 = self.components.iter().map(|c| c.contribution()).sum();
+        self.components
+            .iter()
+            .map(|c| {
+                if total > 0.0 {
+                    c.contribution() / total
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Score change from reranking, when reranking ran
+    pub fn rerank_delta(&self) -> Option<f32> {
+        self.reranked.map(|(_, score)| score - self.retrieval_score)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let shares = self.shares();
+        json!({
+            "source": self.source.as_str(),
+            "retrieval_score": self.retrieval_score,
+            "retrieval_rank": self.retrieval_rank,
+            "components": self.components.iter().zip(&shares).map(|(c, share)| json!({
+                "name": c.name,
+                "raw": c.raw,
+                "normalized": c.normalized,
+                "weight": c.weight,
+                "contribution": c.contribution(),
+                "share": share,
+            })).collect::<Vec<_>>(),
+            "terms": self.terms.iter().map(|t| json!({
+                "term": t.term,
+                "idf": t.idf,
+                "tf": t.tf,
+            })).collect::<Vec<_>>(),
+            "rerank": self.reranked.map(|(rank, score)| json!({
+                "rank": rank,
+                "score": score,
+                "delta": score - self.retrieval_score,
+            })),
+            "final_rank": self.final_rank,
+            "final_score": self.final_score,
+        })
+    }
+}
+
+/// Explain each of the final `results` of a session search
+pub fn explain_results(
+    store: &VectorStore,
+    searcher: &HybridSearcher,
+    query_text: &str,
+    results: &[SearchResult],
+    trace: &SearchTrace,
+) -> Vec<Explanation> {
+    let terms = searcher.terms(query_text, &store.bm25_idf);
+
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| {
+            let components =
+                searcher.components(result.bm25_score, result.vector_score, result.colbert_score);
+            let id = &result.chunk.id;
+            Explanation {
+                retrieval_score: trace
+                    .retrieval
+                    .get(id)
+                    .map(|&(_, score)| score)
+                    .unwrap_or_else(|| components.iter().map(|c| c.contribution()).sum()),
+                components,
+                terms: term_matches(&terms, &result.chunk.content, &store.bm25_idf),
+                retrieval_rank: trace.retrieval.get(id).map(|&(rank, _)| rank + 1),
+                reranked: trace
+                    .reranked
+                    .get(id)
+                    .map(|&(rank, score)| (rank + 1, score)),
+                final_rank: i + 1,
+                final_score: result.score,
+                source: result.source,
+            }
+        })
+        .collect()
+}
+
+/// Term frequencies with the same tokenization BM25 uses
+fn term_matches(terms: &[String], content: &str, idf: &HashMap<String, f32>) -> Vec<TermMatch> {
+    let content = content.to_lowercase();
+    let mut tf: HashMap<&str, usize> = HashMap::new();
+    for word in content.split_whitespace() {
+        *tf.entry(word).or_insert(0) += 1;
+    }
+
+    terms
+        .iter()
+        .map(|term| TermMatch {
+            term: term.clone(),
+            idf: idf.get(term).copied().unwrap_or(0.0),
+            tf: tf.get(term.as_str()).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::search::query_terms;
+
+    #[test]
+    fn test_term_matches_and_shares() {
+        let idf = HashMap::from([("parse".to_string(), 2.0)]);
+        let terms = query_terms("Parse config");
+        let matches = term_matches(&terms, "// parse the file\nparse(x)", &idf);
+        assert_eq!(matches[0].tf, 1);
+        assert_eq!(matches[0].idf, 2.0);
+        assert_eq!(matches[1].tf, 0);
+
+        let components = HybridSearcher::default().components(0.0, 0.8, None);
+        let explanation = Explanation {
+            retrieval_score: components.iter().map(|c| c.contribution()).sum(),
+            components,
+            terms: matches,
+            retrieval_rank: Some(1),
+            reranked: Some((1, 0.9)),
+            final_rank: 1,
+            final_score: 0.9,
+            source: CandidateSource::Exhaustive,
+        };
+        let shares = explanation.shares();
+        assert!((shares.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        // BM25 of 0 normalizes to 0.5 via the sigmoid
+        assert!((explanation.retrieval_score - (0.7 * 0.8 + 0.3 * 0.5)).abs() < 1e-5);
+        assert!(explanation.rerank_delta().unwrap() > 0.0);
+    }
+}
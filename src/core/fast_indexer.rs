@@ -17,6 +17,7 @@ use std::time::Instant;
 use crate::core::chunker::{Chunk as LegacyChunk, CodeChunker};
 use crate::core::config::Config;
 use crate::core::embeddings::EmbeddingProvider;
+use crate::core::levels::embed_names;
 use crate::core::local_embeddings::SpeedMode;
 use crate::core::scanner::{FileScanner, ScannedFile};
 use crate::core::store::{
@@ -261,6 +262,9 @@ impl FastIndexer {
         {
             let mut store_guard = store.lock().unwrap();
             store_guard.update_bm25_stats();
+            // Drops removed files; changed files were re-pooled with their
+            // embedded names above
+            store_guard.sync_levels(&HashMap::new());
             store_guard.save(store_name)?;
         }
 
@@ -441,6 +445,11 @@ impl FastIndexer {
             }
         }
 
+        // Re-pool the file vectors of changed files with their names
+        let stale = store.lock().unwrap().stale_level_names();
+        let names = embed_names(&embeddings_provider, stale).await;
+        store.lock().unwrap().sync_levels(&names);
+
         Ok(total_chunks)
     }
 }
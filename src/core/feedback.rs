@@ -0,0 +1,366 @@
+//! Relevance feedback
+//!
+//! `sgrep feedback` and the MCP `search_feedback` tool mark a result of an
+//! earlier search good or bad. The query id is the cursor of the page the
+//! result was shown on; the page and its score components are recorded
+//! when it is printed, read back here and appended to
+//! `~/.sgrep/feedback.jsonl`.
+//!
+//! `sgrep tune` fits the fusion weights to the log: for every good and bad
+//! result of the same query, the good one should score higher. The BM25
+//! weight (and, for hybrid queries, the BGE weight alpha of query fusion)
+//! is picked from a grid to order the most pairs correctly.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::core::config::Config;
+use crate::core::result_cache::{parse_cursor, ResultCache};
+use crate::core::search::HybridSearcher;
+
+/// Grid resolution for weights in [0, 1]
+const GRID_STEPS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackLabel {
+    Good,
+    Bad,
+}
+
+impl FromStr for FeedbackLabel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "good" | "+" => Ok(FeedbackLabel::Good),
+            "bad" | "-" => Ok(FeedbackLabel::Bad),
+            other => bail!("Unknown label '{}' (expected good or bad)", other),
+        }
+    }
+}
+
+impl FeedbackLabel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedbackLabel::Good => "good",
+            FeedbackLabel::Bad => "bad",
+        }
+    }
+}
+
+/// One rated result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedbackEntry {
+    /// Result cache key of the query
+    pub query_id: String,
+    pub query: String,
+    /// Embedding mode the query ran in
+    pub mode: String,
+    /// 1-based rank in the full result list
+    pub rank: usize,
+    pub chunk_id: String,
+    pub file_path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub bm25_score: f32,
+    pub vector_score: f32,
+    pub colbert_score: Option<f32>,
+    pub label: FeedbackLabel,
+    pub created_at: String,
+}
+
+pub fn log_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("feedback.jsonl"))
+}
+
+/// Rate the result at 1-based `rank` of the page `query_id` was printed with
+pub fn record(query_id: &str, rank: usize, label: FeedbackLabel) -> Result<FeedbackEntry> {
+    if rank == 0 {
+        bail!("Ranks start at 1");
+    }
+    let (key, _) = query_id
+        .rsplit_once(':')
+        .with_context(|| format!("Invalid query id: {}", query_id))?;
+    let offset = parse_cursor(query_id, key)?;
+    let shown = ResultCache::open()?.shown(key, offset + rank - 1)?;
+
+    let entry = FeedbackEntry {
+        query_id: key.to_string(),
+        query: shown.query,
+        mode: shown.mode,
+        rank: offset + rank,
+        chunk_id: shown.chunk_id,
+        file_path: shown.file_path,
+        start_line: shown.start_line,
+        end_line: shown.end_line,
+        bm25_score: shown.bm25_score,
+        vector_score: shown.vector_score,
+        colbert_score: shown.colbert_score,
+        label,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path()?)
+        .context("Failed to open feedback log")?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(entry)
+}
+
+/// Logged feedback, keeping the latest rating of each result per query
+pub fn load_feedback() -> Result<Vec<FeedbackEntry>> {
+    let path = log_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)?;
+
+    let mut entries: Vec<FeedbackEntry> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: FeedbackEntry = serde_json::from_str(line)
+            .with_context(|| format!("Invalid feedback entry on line {}", i + 1))?;
+        match index.entry((entry.query_id.clone(), entry.chunk_id.clone())) {
+            Entry::Occupied(slot) => entries[*slot.get()] = entry,
+            Entry::Vacant(slot) => {
+                slot.insert(entries.len());
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Vector similarity of a rated result as a function of the hybrid alpha
+#[derive(Debug, Clone, Copy)]
+pub enum VectorScore {
+    /// Not a hybrid query (or its chunk is no longer indexed)
+    Fixed(f32),
+    /// Cosine of the BGE and CodeRankEmbed query embeddings with the chunk,
+    /// and of the two query embeddings with each other
+    Hybrid { bge: f32, code: f32, cross: f32 },
+}
+
+impl VectorScore {
+    /// Cosine of the chunk with the query fused at `alpha`
+    pub fn at(&self, alpha: f32) -> f32 {
+        match *self {
+            VectorScore::Fixed(score) => score,
+            VectorScore::Hybrid { bge, code, cross } => {
+                let beta = 1.0 - alpha;
+                let norm = (alpha * alpha + beta * beta + 2.0 * alpha * beta * cross).sqrt();
+                if norm > 0.0 {
+                    (alpha * bge + beta * code) / norm
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Score components of a rated result
+#[derive(Debug, Clone)]
+pub struct FeedbackSample {
+    pub query_id: String,
+    pub good: bool,
+    pub bm25_score: f32,
+    pub colbert_score: Option<f32>,
+    pub vector: VectorScore,
+}
+
+impl FeedbackSample {
+    pub fn new(entry: &FeedbackEntry, vector: VectorScore) -> Self {
+        Self {
+            query_id: entry.query_id.clone(),
+            good: entry.label == FeedbackLabel::Good,
+            bm25_score: entry.bm25_score,
+            colbert_score: entry.colbert_score,
+            vector,
+        }
+    }
+}
+
+/// Fusion weights and how many rated pairs they order correctly
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FusionFit {
+    pub bm25_weight: f32,
+    pub vector_weight: f32,
+    pub alpha: f32,
+    /// Fraction of (good, bad) pairs where the good result scores higher
+    pub accuracy: f32,
+}
+
+/// Result of `fit_fusion`
+#[derive(Debug, Clone, Serialize)]
+pub struct TuneReport {
+    pub pairs: usize,
+    /// Pairs whose scores depend on the hybrid alpha
+    pub hybrid_pairs: usize,
+    pub current: FusionFit,
+    pub fitted: FusionFit,
+}
+
+impl TuneReport {
+    pub fn improved(&self) -> bool {
+        self.fitted.accuracy > self.current.accuracy
+    }
+}
+
+/// Grid-search the BM25 weight (vector weight `1 - w`) and, when hybrid
+/// pairs are logged, the alpha. Ties go to the larger mean margin; the
+/// current weights are kept unless the fit orders more pairs correctly.
+pub fn fit_fusion(
+    samples: &[FeedbackSample],
+    (bm25_weight, vector_weight): (f32, f32),
+    alpha: f32,
+) -> Result<TuneReport> {
+    let pairs = preference_pairs(samples);
+    if pairs.is_empty() {
+        let good = samples.iter().filter(|s| s.good).count();
+        bail!(
+            "Tuning needs a good and a bad result of the same query ({} good, {} bad logged)",
+            good,
+            samples.len() - good
+        );
+    }
+    let hybrid_pairs = pairs
+        .iter()
+        .filter(|(good, bad)| {
+            matches!(good.vector, VectorScore::Hybrid { .. })
+                || matches!(bad.vector, VectorScore::Hybrid { .. })
+        })
+        .count();
+
+    let evaluate = |bm25_weight: f32, vector_weight: f32, alpha: f32| {
+        let searcher = HybridSearcher::new(bm25_weight, vector_weight);
+        let score = |s: &FeedbackSample| -> f32 {
+            searcher
+                .components(s.bm25_score, s.vector.at(alpha), s.colbert_score)
+                .iter()
+                .map(|c| c.contribution())
+                .sum()
+        };
+        let margins: Vec<f32> = pairs.iter().map(|(g, b)| score(g) - score(b)).collect();
+        let correct = margins.iter().filter(|&&m| m > 0.0).count();
+        let fit = FusionFit {
+            bm25_weight,
+            vector_weight,
+            alpha,
+            accuracy: correct as f32 / pairs.len() as f32,
+        };
+        (fit, margins.iter().sum::<f32>() / pairs.len() as f32)
+    };
+
+    let (current, _) = evaluate(bm25_weight, vector_weight, alpha);
+    let grid = |i: usize| i as f32 / GRID_STEPS as f32;
+    let alphas: Vec<f32> = if hybrid_pairs > 0 {
+        (0..=GRID_STEPS).map(grid).collect()
+    } else {
+        vec![alpha]
+    };
+
+    let mut best = (current, f32::NEG_INFINITY);
+    for &alpha in &alphas {
+        for w in (0..=GRID_STEPS).map(grid) {
+            let (fit, margin) = evaluate(w, 1.0 - w, alpha);
+            if fit.accuracy > best.0.accuracy
+                || (fit.accuracy == best.0.accuracy && margin > best.1)
+            {
+                best = (fit, margin);
+            }
+        }
+    }
+
+    Ok(TuneReport {
+        pairs: pairs.len(),
+        hybrid_pairs,
+        current,
+        fitted: if best.0.accuracy > current.accuracy {
+            best.0
+        } else {
+            current
+        },
+    })
+}
+
+/// Every (good, bad) pair of results rated for the same query
+fn preference_pairs(samples: &[FeedbackSample]) -> Vec<(&FeedbackSample, &FeedbackSample)> {
+    let mut by_query: HashMap<&str, Vec<&FeedbackSample>> = HashMap::new();
+    for sample in samples {
+        by_query.entry(&sample.query_id).or_default().push(sample);
+    }
+
+    let mut pairs = Vec::new();
+    for rated in by_query.values() {
+        for good in rated.iter().filter(|s| s.good) {
+            for bad in rated.iter().filter(|s| !s.good) {
+                pairs.push((*good, *bad));
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(query: &str, good: bool, bm25: f32, vector: f32) -> FeedbackSample {
+        FeedbackSample {
+            query_id: query.to_string(),
+            good,
+            bm25_score: bm25,
+            colbert_score: None,
+            vector: VectorScore::Fixed(vector),
+        }
+    }
+
+    #[test]
+    fn test_fit_fusion() {
+        // Good results match the query terms but embed slightly worse
+        let samples = vec![
+            sample("a", true, 30.0, 0.60),
+            sample("a", false, 0.0, 0.65),
+            sample("b", true, 25.0, 0.55),
+            sample("b", false, 2.0, 0.62),
+            sample("c", true, 10.0, 0.70),
+        ];
+
+        let report = fit_fusion(&samples, (0.0, 1.0), 0.4).unwrap();
+        assert_eq!(report.pairs, 2);
+        assert_eq!(report.hybrid_pairs, 0);
+        assert_eq!(report.current.accuracy, 0.0);
+        assert!(report.improved());
+        assert_eq!(report.fitted.accuracy, 1.0);
+        assert!(report.fitted.bm25_weight > 0.0);
+        assert_eq!(report.fitted.alpha, 0.4);
+
+        // Nothing to compare without a bad result for the same query
+        assert!(fit_fusion(&samples[4..], (0.3, 0.7), 0.4).is_err());
+    }
+
+    #[test]
+    fn test_hybrid_vector_score() {
+        let score = VectorScore::Hybrid {
+            bge: 0.8,
+            code: 0.2,
+            cross: 0.5,
+        };
+        assert!((score.at(1.0) - 0.8).abs() < 1e-6);
+        assert!((score.at(0.0) - 0.2).abs() < 1e-6);
+        // Equal weights: (0.4 + 0.1) / sqrt(0.25 + 0.25 + 0.25)
+        assert!((score.at(0.5) - 0.5 / 0.75f32.sqrt()).abs() < 1e-6);
+    }
+}
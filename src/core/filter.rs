@@ -0,0 +1,281 @@
+//! Search result scoping
+//!
+//! Restricts results to a subtree and to include/exclude globs, using the
+//! `ignore` crate's override (ripgrep `--glob`) semantics: a plain glob
+//! whitelists, a `!`-prefixed glob excludes, and once any whitelist glob is
+//! present, files matching none of them are dropped. Each `with_globs` call
+//! is its own set, so `--glob` and inline `path:` globs must both match.
+//! Chunk-level predicates (language, chunk type, symbol) come from the
+//! inline query language.
+
+use anyhow::{Context, Result};
+use ignore::overrides::{Override, OverrideBuilder};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::core::store::FileChunk;
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    /// Only files under this (canonical) path
+    root: Option<PathBuf>,
+    /// Compiled include/exclude glob sets; a file must pass every set
+    globs: Vec<Override>,
+    /// Lowercased file extensions
+    file_types: Vec<String>,
+    /// Only these files (e.g. modified since a git date)
+    files: Option<HashSet<PathBuf>>,
+    languages: ValueFilter,
+    kinds: ValueFilter,
+    symbols: ValueFilter,
+    parents: ValueFilter,
+}
+
+/// Include/exclude lists for one chunk attribute
+#[derive(Debug, Clone, Default)]
+struct ValueFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// Substring match instead of equality
+    contains: bool,
+}
+
+impl ValueFilter {
+    fn extend(&mut self, include: &[String], exclude: &[String]) {
+        self.include
+            .extend(include.iter().map(|v| v.to_lowercase()));
+        self.exclude
+            .extend(exclude.iter().map(|v| v.to_lowercase()));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    fn matches(&self, value: Option<&str>) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let value = value.map(|v| v.to_lowercase());
+        let hit = |pattern: &String| match value {
+            Some(ref v) if self.contains => v.contains(pattern.as_str()),
+            Some(ref v) => v == pattern,
+            None => false,
+        };
+        (self.include.is_empty() || self.include.iter().any(hit)) && !self.exclude.iter().any(hit)
+    }
+}
+
+impl SearchFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict results to files under `path` (a directory or a single file)
+    pub fn with_root(mut self, path: &str) -> Result<Self> {
+        let root = Path::new(path)
+            .canonicalize()
+            .with_context(|| format!("Invalid search path: {}", path))?;
+        self.root = Some(root);
+        Ok(self)
+    }
+
+    /// Add a set of include globs (`!glob` excludes) and exclude globs.
+    /// Includes within one call are alternatives; separate calls must all
+    /// match. Globs are relative to the search root, or the current directory.
+    pub fn with_globs(mut self, globs: &[String], excludes: &[String]) -> Result<Self> {
+        if globs.is_empty() && excludes.is_empty() {
+            return Ok(self);
+        }
+
+        let patterns = globs.iter().cloned().chain(excludes.iter().map(|e| {
+            if e.starts_with('!') {
+                e.clone()
+            } else {
+                format!("!{}", e)
+            }
+        }));
+
+        let base = match self.root {
+            Some(ref root) if root.is_dir() => root.clone(),
+            Some(ref root) => root.parent().map(Path::to_path_buf).unwrap_or_default(),
+            None => std::env::current_dir()?.canonicalize()?,
+        };
+
+        let mut builder = OverrideBuilder::new(&base);
+        for glob in patterns {
+            builder
+                .add(&glob)
+                .with_context(|| format!("Invalid glob: {}", glob))?;
+        }
+
+        self.globs
+            .push(builder.build().context("Failed to build glob filter")?);
+        Ok(self)
+    }
+
+    /// Restrict results to the given file extensions
+    pub fn with_file_types(mut self, file_types: &[String]) -> Self {
+        self.file_types
+            .extend(file_types.iter().map(|t| t.to_lowercase()));
+        self
+    }
+
+    /// Restrict results to an explicit set of absolute file paths
+    pub fn with_files(mut self, files: HashSet<PathBuf>) -> Self {
+        self.files = Some(match self.files.take() {
+            Some(existing) => existing.intersection(&files).cloned().collect(),
+            None => files,
+        });
+        self
+    }
+
+    /// Restrict chunk languages
+    pub fn with_languages(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.languages.extend(include, exclude);
+        self
+    }
+
+    /// Restrict chunk types (function, class, ...)
+    pub fn with_kinds(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.kinds.extend(include, exclude);
+        self
+    }
+
+    /// Restrict by symbol name substring
+    pub fn with_symbols(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.symbols.contains = true;
+        self.symbols.extend(include, exclude);
+        self
+    }
+
+    /// Restrict by parent symbol name substring
+    pub fn with_parents(mut self, include: &[String], exclude: &[String]) -> Self {
+        self.parents.contains = true;
+        self.parents.extend(include, exclude);
+        self
+    }
+
+    /// True when no restriction is configured
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+            && self.globs.is_empty()
+            && self.file_types.is_empty()
+            && self.files.is_none()
+            && self.languages.is_empty()
+            && self.kinds.is_empty()
+            && self.symbols.is_empty()
+            && self.parents.is_empty()
+    }
+
+    /// Check a chunk against file-level and chunk-level restrictions
+    pub fn matches_chunk(&self, chunk: &FileChunk) -> bool {
+        self.matches(&chunk.file_path)
+            && self.languages.matches(chunk.language.as_deref())
+            && self.kinds.matches(Some(&chunk.chunk_type))
+            && self.symbols.matches(chunk.symbol_name.as_deref())
+            && self.parents.matches(chunk.parent_name.as_deref())
+    }
+
+    /// Check an indexed (absolute) file path against the filter
+    pub fn matches(&self, file_path: &str) -> bool {
+        let path = Path::new(file_path);
+
+        if let Some(ref root) = self.root {
+            if !path.starts_with(root) {
+                return false;
+            }
+        }
+
+        if !self.file_types.is_empty() {
+            let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+            match ext {
+                Some(ext) if self.file_types.contains(&ext) => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(ref files) = self.files {
+            if !files.contains(path) {
+                return false;
+            }
+        }
+
+        if self
+            .globs
+            .iter()
+            .any(|globs| globs.matched(path, false).is_ignore())
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cwd() -> String {
+        std::env::current_dir()
+            .unwrap()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_root_scoping() {
+        let filter = SearchFilter::new().with_root("src").unwrap();
+        let root = cwd();
+
+        assert!(filter.matches(&format!("{}/src/main.rs", root)));
+        assert!(!filter.matches(&format!("{}/scripts/embedding_server.py", root)));
+        assert!(!filter.matches(&format!("{}/src2/main.rs", root)));
+    }
+
+    #[test]
+    fn test_globs_and_excludes() {
+        let filter = SearchFilter::new()
+            .with_globs(
+                &["*.rs".to_string(), "!**/tests/**".to_string()],
+                &["vendor/**".to_string()],
+            )
+            .unwrap();
+        let root = cwd();
+
+        assert!(filter.matches(&format!("{}/src/main.rs", root)));
+        assert!(!filter.matches(&format!("{}/src/app.ts", root)));
+        assert!(!filter.matches(&format!("{}/src/tests/it.rs", root)));
+        assert!(!filter.matches(&format!("{}/vendor/lib.rs", root)));
+    }
+
+    #[test]
+    fn test_chunk_predicates() {
+        let chunk = FileChunk {
+            chunk_type: "method".to_string(),
+            ..FileChunk::test("1", "/repo/src/client.rs", (1, 5))
+                .with_symbol("retry_request")
+                .with_parent("HttpClient")
+        };
+
+        let filter = SearchFilter::new()
+            .with_languages(&["rust".to_string()], &[])
+            .with_symbols(&["retry".to_string()], &[])
+            .with_parents(&["client".to_string()], &[]);
+        assert!(filter.matches_chunk(&chunk));
+
+        let filter = SearchFilter::new().with_kinds(&[], &["method".to_string()]);
+        assert!(!filter.matches_chunk(&chunk));
+    }
+
+    #[test]
+    fn test_file_types() {
+        let filter = SearchFilter::new().with_file_types(&["PY".to_string()]);
+        assert!(filter.matches("/repo/a.py"));
+        assert!(!filter.matches("/repo/a.rs"));
+        assert!(!filter.matches("/repo/Makefile"));
+    }
+}
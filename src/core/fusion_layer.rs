@@ -0,0 +1,419 @@
+//! Learned fusion for the hybrid embedder
+//!
+//! `FusionStrategy::Adaptive` learns a gate per dimension,
+//! `g = sigmoid(w)`, and fuses `g * bge + (1 - g) * code`.
+//! `FusionStrategy::Concatenate` learns a projection `W` of `[bge; code]`
+//! down to the embedding dimension. Both start out equal to the default
+//! weighted average and are fit with an in-batch contrastive loss on
+//! (query, positive, negative) triples (see `core::triples`). Only the query
+//! is fused: hybrid search compares it to the store's chunk embeddings, so
+//! positives and negatives are embedded with the model that built the store.
+//!
+//! Weights are kept in `~/.sgrep/fusion-<strategy>.safetensors` and loaded
+//! by `HybridEmbedder::with_strategy`; without a file the embedder falls
+//! back to its fixed heuristics.
+
+use anyhow::{bail, Context, Result};
+use candle_core::{DType, Device, Tensor, Var, D};
+use candle_nn::{AdamW, Optimizer, ParamsAdamW};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::core::config::Config;
+use crate::core::hybrid_embedder::{FusionStrategy, DEFAULT_ALPHA};
+
+/// Tensor name of the per-dimension gate logits
+const GATE: &str = "gate";
+/// Tensor name of the `[dim, 2 * dim]` projection
+const PROJECTION: &str = "projection";
+
+/// Trained fusion weights
+pub struct FusionLayer {
+    strategy: FusionStrategy,
+    /// Gate logits `[dim]` or projection `[dim, 2 * dim]`
+    weights: Tensor,
+}
+
+impl FusionLayer {
+    /// Weights at their initial value: the default weighted average
+    pub fn initial(strategy: FusionStrategy, dim: usize) -> Result<Self> {
+        let device = Device::Cpu;
+        let weights = match strategy {
+            FusionStrategy::Adaptive => {
+                let logit = (DEFAULT_ALPHA / (1.0 - DEFAULT_ALPHA)).ln();
+                Tensor::full(logit, dim, &device)?
+            }
+            FusionStrategy::Concatenate => {
+                let eye = Tensor::eye(dim, DType::F32, &device)?;
+                Tensor::cat(
+                    &[
+                        (&eye * DEFAULT_ALPHA as f64)?,
+                        (&eye * (1.0 - DEFAULT_ALPHA) as f64)?,
+                    ],
+                    1,
+                )?
+            }
+            other => bail!("{} fusion has no trainable weights", other.name()),
+        };
+        Ok(Self { strategy, weights })
+    }
+
+    pub fn path(strategy: FusionStrategy) -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join(format!("fusion-{}.safetensors", strategy.name())))
+    }
+
+    /// Trained weights for `strategy`, if `sgrep train-fusion` saved any
+    pub fn load(strategy: FusionStrategy, dim: usize) -> Result<Option<Self>> {
+        let path = Self::path(strategy)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::load_from(&path, strategy, dim).map(Some)
+    }
+
+    pub fn load_from(path: &Path, strategy: FusionStrategy, dim: usize) -> Result<Self> {
+        let mut tensors = candle_core::safetensors::load(path, &Device::Cpu)
+            .with_context(|| format!("Failed to read fusion weights: {}", path.display()))?;
+        let (name, shape) = match strategy {
+            FusionStrategy::Adaptive => (GATE, vec![dim]),
+            FusionStrategy::Concatenate => (PROJECTION, vec![dim, 2 * dim]),
+            other => bail!("{} fusion has no trainable weights", other.name()),
+        };
+        let weights = tensors
+            .remove(name)
+            .with_context(|| format!("No '{}' tensor in {}", name, path.display()))?;
+        if weights.dims() != shape.as_slice() {
+            bail!(
+                "Fusion weights in {} have shape {:?}, expected {:?}; retrain with: sgrep train-fusion",
+                path.display(),
+                weights.dims(),
+                shape
+            );
+        }
+        Ok(Self { strategy, weights })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let name = match self.strategy {
+            FusionStrategy::Adaptive => GATE,
+            _ => PROJECTION,
+        };
+        let tensors = HashMap::from([(name.to_string(), self.weights.clone())]);
+        candle_core::safetensors::save(&tensors, path)
+            .with_context(|| format!("Failed to write fusion weights: {}", path.display()))
+    }
+
+    /// Fuse one pair of L2-normalized embeddings
+    pub fn fuse(&self, bge: &[f32], code: &[f32]) -> Result<Vec<f32>> {
+        let bge = Tensor::from_slice(bge, (1, bge.len()), &Device::Cpu)?;
+        let code = Tensor::from_slice(code, (1, code.len()), &Device::Cpu)?;
+        let fused = forward(self.strategy, &self.weights, &bge, &code)?;
+        Ok(fused.squeeze(0)?.to_vec1()?)
+    }
+}
+
+/// Fused, row-normalized embeddings for `[n, dim]` inputs
+fn forward(
+    strategy: FusionStrategy,
+    weights: &Tensor,
+    bge: &Tensor,
+    code: &Tensor,
+) -> candle_core::Result<Tensor> {
+    let fused = match strategy {
+        FusionStrategy::Adaptive => {
+            // sigmoid, written out so it is differentiable
+            let gate = (weights.neg()?.exp()? + 1.0)?.recip()?;
+            let keep = (gate.neg()? + 1.0)?;
+            (bge.broadcast_mul(&gate)? + code.broadcast_mul(&keep)?)?
+        }
+        _ => Tensor::cat(&[bge, code], 1)?.matmul(&weights.t()?)?,
+    };
+    let norm = (fused.sqr()?.sum_keepdim(D::Minus1)?.sqrt()? + 1e-8)?;
+    fused.broadcast_div(&norm)
+}
+
+/// Embeddings of training triples, `[n, dim]` each: BGE and CodeRankEmbed
+/// halves of the query, and the documents as the store embeds its chunks
+pub struct TripleEmbeddings {
+    pub query_bge: Tensor,
+    pub query_code: Tensor,
+    pub positive: Tensor,
+    pub negative: Tensor,
+}
+
+impl TripleEmbeddings {
+    /// Stack rows of L2-normalized embeddings
+    pub fn new(
+        queries: &[(Vec<f32>, Vec<f32>)],
+        positives: &[Vec<f32>],
+        negatives: &[Vec<f32>],
+    ) -> Result<Self> {
+        let stack = |rows: Vec<&[f32]>| -> Result<Tensor> {
+            let rows: Vec<Tensor> = rows
+                .into_iter()
+                .map(|row| Tensor::new(row, &Device::Cpu))
+                .collect::<candle_core::Result<_>>()?;
+            Ok(Tensor::stack(&rows, 0)?)
+        };
+        let documents = |rows: &[Vec<f32>]| stack(rows.iter().map(Vec::as_slice).collect());
+        Ok(Self {
+            query_bge: stack(queries.iter().map(|(b, _)| b.as_slice()).collect())?,
+            query_code: stack(queries.iter().map(|(_, c)| c.as_slice()).collect())?,
+            positive: documents(positives)?,
+            negative: documents(negatives)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.query_bge.dims()[0]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn dim(&self) -> usize {
+        self.query_bge.dims()[1]
+    }
+
+    fn select(&self, rows: &[u32]) -> candle_core::Result<Self> {
+        let index = Tensor::new(rows, &Device::Cpu)?;
+        let pick = |t: &Tensor| t.index_select(&index, 0);
+        Ok(Self {
+            query_bge: pick(&self.query_bge)?,
+            query_code: pick(&self.query_code)?,
+            positive: pick(&self.positive)?,
+            negative: pick(&self.negative)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainOptions {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    /// Softmax temperature of the contrastive loss
+    pub temperature: f64,
+    /// Pull towards the initial weights, so few triples can't wreck them
+    pub regularization: f64,
+}
+
+impl TrainOptions {
+    /// Defaults for `strategy`: the projection has far more weights, so it
+    /// takes smaller steps
+    pub fn for_strategy(strategy: FusionStrategy) -> Self {
+        Self {
+            epochs: 30,
+            batch_size: 32,
+            learning_rate: match strategy {
+                FusionStrategy::Concatenate => 0.001,
+                _ => 0.05,
+            },
+            temperature: 0.05,
+            regularization: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrainReport {
+    pub train_triples: usize,
+    pub held_out_triples: usize,
+    /// Held-out triples where the positive is closer to the query than the
+    /// negative, with the initial (weighted average) and trained weights
+    pub initial_accuracy: f32,
+    pub trained_accuracy: f32,
+    pub final_loss: f32,
+}
+
+impl TrainReport {
+    pub fn improved(&self) -> bool {
+        self.trained_accuracy >= self.initial_accuracy
+    }
+}
+
+/// Fit `strategy` to `data`, holding out every fifth triple for the report
+pub fn train(
+    strategy: FusionStrategy,
+    data: &TripleEmbeddings,
+    options: &TrainOptions,
+) -> Result<(FusionLayer, TrainReport)> {
+    if data.len() < 10 {
+        bail!("Training needs at least 10 triples, found {}", data.len());
+    }
+    let initial = FusionLayer::initial(strategy, data.dim())?;
+    let (held_out, train_rows): (Vec<u32>, Vec<u32>) =
+        (0..data.len() as u32).partition(|i| i % 5 == 0);
+    let held_out_data = data.select(&held_out)?;
+
+    let weights = Var::from_tensor(&initial.weights)?;
+    let mut optimizer = AdamW::new(
+        vec![weights.clone()],
+        ParamsAdamW {
+            lr: options.learning_rate,
+            weight_decay: 0.0,
+            ..Default::default()
+        },
+    )?;
+
+    let mut order = train_rows.clone();
+    let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+    let mut final_loss = 0.0;
+    for _ in 0..options.epochs {
+        shuffle(&mut order, &mut seed);
+        for batch in order.chunks(options.batch_size.max(2)) {
+            let batch = data.select(batch)?;
+            let loss = (contrastive_loss(strategy, weights.as_tensor(), &batch, options)?
+                + ((weights.as_tensor() - &initial.weights)?
+                    .sqr()?
+                    .mean_all()?
+                    * options.regularization)?)?;
+            optimizer.backward_step(&loss)?;
+            final_loss = loss.to_scalar::<f32>()?;
+        }
+    }
+
+    let trained = FusionLayer {
+        strategy,
+        weights: weights.as_tensor().detach(),
+    };
+    let report = TrainReport {
+        train_triples: train_rows.len(),
+        held_out_triples: held_out.len(),
+        initial_accuracy: accuracy(&initial, &held_out_data)?,
+        trained_accuracy: accuracy(&trained, &held_out_data)?,
+        final_loss,
+    };
+    Ok((trained, report))
+}
+
+/// Cross-entropy of each fused query against every positive and negative in
+/// the batch, with its own positive as the target
+fn contrastive_loss(
+    strategy: FusionStrategy,
+    weights: &Tensor,
+    batch: &TripleEmbeddings,
+    options: &TrainOptions,
+) -> candle_core::Result<Tensor> {
+    let queries = forward(strategy, weights, &batch.query_bge, &batch.query_code)?;
+    let candidates = Tensor::cat(&[&batch.positive, &batch.negative], 0)?;
+    let logits = (queries.matmul(&candidates.t()?)? / options.temperature)?;
+    let targets = Tensor::arange(0u32, batch.len() as u32, &Device::Cpu)?;
+    candle_nn::loss::cross_entropy(&logits, &targets)
+}
+
+/// Fraction of triples whose positive is more similar to the query
+fn accuracy(layer: &FusionLayer, data: &TripleEmbeddings) -> Result<f32> {
+    if data.is_empty() {
+        return Ok(0.0);
+    }
+    let queries = forward(
+        layer.strategy,
+        &layer.weights,
+        &data.query_bge,
+        &data.query_code,
+    )?;
+    let positive = (&queries * &data.positive)?.sum(1)?;
+    let negative = (&queries * &data.negative)?.sum(1)?;
+    let correct = positive
+        .gt(&negative)?
+        .to_dtype(DType::F32)?
+        .sum_all()?
+        .to_scalar::<f32>()?;
+    Ok(correct / data.len() as f32)
+}
+
+/// Fisher-Yates with a xorshift generator, so runs are reproducible
+fn shuffle(items: &mut [u32], seed: &mut u64) {
+    for i in (1..items.len()).rev() {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        items.swap(i, (*seed % (i as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(v: Vec<f32>) -> Vec<f32> {
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        v.into_iter().map(|x| x / norm).collect()
+    }
+
+    /// Triples against a BGE store where only the BGE half of the query
+    /// tells positive from negative
+    fn bge_store_triples(n: usize, dim: usize) -> TripleEmbeddings {
+        let mut seed = 42u64;
+        let mut random = |len: usize| -> Vec<f32> {
+            (0..len)
+                .map(|_| {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    ((seed >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                })
+                .collect()
+        };
+        let (mut queries, mut positives, mut negatives) = (Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..n {
+            // The positive is a noisy copy of the query's BGE half; the
+            // CodeRankEmbed half, in another space, happens to match the
+            // negative, so the default weights pick the negative
+            let bge = random(dim);
+            let noisy: Vec<f32> = bge
+                .iter()
+                .zip(random(dim))
+                .map(|(b, r)| b + 2.0 * r)
+                .collect();
+            let negative = normalized(random(dim));
+            queries.push((normalized(bge), negative.clone()));
+            positives.push(normalized(noisy));
+            negatives.push(negative);
+        }
+        TripleEmbeddings::new(&queries, &positives, &negatives).unwrap()
+    }
+
+    #[test]
+    fn test_train_adaptive() {
+        let data = bge_store_triples(200, 32);
+        let options = TrainOptions::for_strategy(FusionStrategy::Adaptive);
+        let (layer, report) = train(FusionStrategy::Adaptive, &data, &options).unwrap();
+        assert_eq!(report.held_out_triples, 40);
+        assert!(report.trained_accuracy > report.initial_accuracy + 0.2);
+
+        // Round trip through safetensors
+        let path =
+            std::env::temp_dir().join(format!("sgrep-fusion-{}.safetensors", std::process::id()));
+        layer.save(&path).unwrap();
+        let loaded = FusionLayer::load_from(&path, FusionStrategy::Adaptive, 32).unwrap();
+        assert!(FusionLayer::load_from(&path, FusionStrategy::Adaptive, 16).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        let bge = normalized(vec![1.0; 32]);
+        let code = normalized((0..32).map(|i| i as f32).collect());
+        assert_eq!(
+            layer.fuse(&bge, &code).unwrap(),
+            loaded.fuse(&bge, &code).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_initial_matches_weighted_average() {
+        let bge = normalized(vec![1.0, 0.0, 0.0, 0.0]);
+        let code = normalized(vec![0.0, 1.0, 0.0, 0.0]);
+        let expected = normalized(vec![DEFAULT_ALPHA, 1.0 - DEFAULT_ALPHA, 0.0, 0.0]);
+        for strategy in [FusionStrategy::Adaptive, FusionStrategy::Concatenate] {
+            let fused = FusionLayer::initial(strategy, 4)
+                .unwrap()
+                .fuse(&bge, &code)
+                .unwrap();
+            for (a, b) in fused.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-5);
+            }
+        }
+    }
+}
@@ -237,6 +237,79 @@ impl GitRepo {
             .collect())
     }
 
+    /// Get files touched by commits since a date (any `git log --since` format),
+    /// plus uncommitted changes. Paths are relative to the repository root.
+    pub fn modified_since(&self, since: &str) -> Result<Vec<String>> {
+        let output = Command::new("git")
+            .args([
+                "log",
+                &format!("--since={}", since),
+                "--name-only",
+                "--pretty=format:",
+            ])
+            .current_dir(&self.root_path)
+            .output()
+            .context("Failed to run git log")?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "git log --since={} failed: {}",
+                since,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let uncommitted = Command::new("git")
+            .args(["diff", "--name-only", "HEAD"])
+            .current_dir(&self.root_path)
+            .output()?;
+
+        let mut files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(String::from_utf8_lossy(&uncommitted.stdout).lines())
+            .filter(|l| !l.is_empty())
+            .map(|s| s.to_string())
+            .collect();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Message and added lines of recent non-merge commits, newest first
+    pub fn commit_patches(&self, limit: usize) -> Result<Vec<CommitPatch>> {
+        let output = Command::new("git")
+            .args([
+                "log",
+                &format!("-{}", limit),
+                "--no-merges",
+                "--unified=0",
+                "--format=%x1e%H%x1f%B%x1f",
+                "-p",
+            ])
+            .current_dir(&self.root_path)
+            .output()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .split('\x1e')
+            .filter_map(|record| {
+                let mut parts = record.splitn(3, '\x1f');
+                let hash = parts.next()?.trim();
+                let message = parts.next()?.trim().to_string();
+                let added: Vec<&str> = parts
+                    .next()?
+                    .lines()
+                    .filter(|l| l.starts_with('+') && !l.starts_with("+++"))
+                    .map(|l| &l[1..])
+                    .collect();
+                (!hash.is_empty()).then(|| CommitPatch {
+                    message,
+                    added: added.join("\n"),
+                })
+            })
+            .collect())
+    }
+
     /// Get the current HEAD commit hash
     pub fn head_commit(&self) -> Result<String> {
         let output = Command::new("git")
@@ -305,6 +378,13 @@ impl GitRepo {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct CommitPatch {
+    pub message: String,
+    /// Lines the commit added, without the `+`
+    pub added: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct DiffEntry {
     pub path: String,
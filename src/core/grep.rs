@@ -0,0 +1,200 @@
+//! Exact and regex matching
+//!
+//! Line-level matching against indexed chunk content, or against files on
+//! disk for paths that are not indexed. The store's trigram index narrows
+//! the chunks to verify. Chunks overlap (a class and its methods), so
+//! matches are de-duplicated per file and line.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::core::filter::SearchFilter;
+use crate::core::scanner::FileScanner;
+use crate::core::store::VectorStore;
+use crate::core::trigram::TrigramQuery;
+
+/// A matching line
+#[derive(Debug, Clone)]
+pub struct LineMatch {
+    pub line_number: usize,
+    pub line: String,
+    /// Byte ranges of the matches within `line`
+    pub ranges: Vec<(usize, usize)>,
+}
+
+/// All matching lines of one file
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub file_path: String,
+    pub lines: Vec<LineMatch>,
+    /// Indexed chunks containing at least one match (empty for disk files)
+    pub chunk_ids: Vec<String>,
+}
+
+pub struct GrepMatcher {
+    regex: Regex,
+    trigrams: TrigramQuery,
+}
+
+impl GrepMatcher {
+    /// Match a regular expression
+    pub fn regex(pattern: &str, ignore_case: bool) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .with_context(|| format!("Invalid regex: {}", pattern))?;
+        let trigrams = TrigramQuery::from_regex(pattern, ignore_case);
+        Ok(Self { regex, trigrams })
+    }
+
+    /// Match a literal string
+    pub fn fixed(literal: &str, ignore_case: bool) -> Result<Self> {
+        Self::regex(&regex::escape(literal), ignore_case)
+    }
+
+    /// Match one line, returning the match ranges if any
+    pub fn match_line(&self, line: &str) -> Option<Vec<(usize, usize)>> {
+        let ranges: Vec<(usize, usize)> = self
+            .regex
+            .find_iter(line)
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        if ranges.is_empty() {
+            None
+        } else {
+            Some(ranges)
+        }
+    }
+
+    /// Search the stored content of every indexed chunk passing the filter
+    pub fn search_store(&self, store: &VectorStore, filter: &SearchFilter) -> Vec<FileMatches> {
+        let mut by_file: HashMap<&str, (BTreeMap<usize, LineMatch>, Vec<String>)> = HashMap::new();
+
+        let chunks = store
+            .trigram_candidates(&self.trigrams)
+            .unwrap_or_else(|| store.chunks.values().collect());
+
+        for chunk in chunks {
+            if !filter.matches_chunk(chunk) || !self.regex.is_match(&chunk.content) {
+                continue;
+            }
+
+            let (lines, chunk_ids) = by_file.entry(chunk.file_path.as_str()).or_default();
+            chunk_ids.push(chunk.id.clone());
+
+            for (offset, line) in chunk.content.lines().enumerate() {
+                let line_number = chunk.start_line + offset;
+                if lines.contains_key(&line_number) {
+                    continue;
+                }
+                if let Some(ranges) = self.match_line(line) {
+                    lines.insert(
+                        line_number,
+                        LineMatch {
+                            line_number,
+                            line: line.to_string(),
+                            ranges,
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut files: Vec<FileMatches> = by_file
+            .into_iter()
+            .map(|(path, (lines, chunk_ids))| FileMatches {
+                file_path: path.to_string(),
+                lines: lines.into_values().collect(),
+                chunk_ids,
+            })
+            .collect();
+        files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        files
+    }
+
+    /// Search files on disk under `root` that are not in the store
+    pub fn search_disk(
+        &self,
+        root: &str,
+        store: &VectorStore,
+        filter: &SearchFilter,
+    ) -> Result<Vec<FileMatches>> {
+        let mut files = Vec::new();
+
+        for file in FileScanner::new(root).scan()? {
+            let path = Path::new(&file.path)
+                .canonicalize()
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or(file.path);
+            if store.get_file(&path).is_some() || !filter.matches(&path) {
+                continue;
+            }
+
+            let lines: Vec<LineMatch> = file
+                .content
+                .lines()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    self.match_line(line).map(|ranges| LineMatch {
+                        line_number: i + 1,
+                        line: line.to_string(),
+                        ranges,
+                    })
+                })
+                .collect();
+
+            if !lines.is_empty() {
+                files.push(FileMatches {
+                    file_path: path,
+                    lines,
+                    chunk_ids: Vec::new(),
+                });
+            }
+        }
+
+        files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+
+    fn chunk(id: &str, start: usize, content: &str) -> FileChunk {
+        let end = start + content.lines().count() - 1;
+        FileChunk::test(id, "/repo/src/lib.rs", (start, end)).with_content(content)
+    }
+
+    #[test]
+    fn test_overlapping_chunks_dedup_lines() {
+        let store = VectorStore::with_test_chunks(vec![
+            chunk("class", 10, "impl A {\n    fn a() { x.unwrap() }\n}"),
+            chunk("method", 11, "    fn a() { x.unwrap() }"),
+        ]);
+
+        let matcher = GrepMatcher::regex(r"unwrap\(\)", false).unwrap();
+        let files = matcher.search_store(&store, &SearchFilter::new());
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].lines.len(), 1);
+        assert_eq!(files[0].lines[0].line_number, 11);
+        assert_eq!(files[0].chunk_ids.len(), 2);
+    }
+
+    #[test]
+    fn test_fixed_escapes_metacharacters() {
+        let matcher = GrepMatcher::fixed("a.b()", false).unwrap();
+        assert!(matcher.match_line("call a.b() here").is_some());
+        assert!(matcher.match_line("call axb() here").is_none());
+
+        let ranges = GrepMatcher::fixed("ab", true)
+            .unwrap()
+            .match_line("AB ab")
+            .unwrap();
+        assert_eq!(ranges, vec![(0, 2), (3, 5)]);
+    }
+}
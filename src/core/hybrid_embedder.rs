@@ -22,28 +22,64 @@
 
 use anyhow::{anyhow, Result};
 use candle_core::{DType, Device, Tensor};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
 
+use super::fusion_layer::FusionLayer;
 use super::local_embeddings::{LocalEmbedder, SpeedMode};
 
+/// BGE weight of the default weighted-average fusion
+pub const DEFAULT_ALPHA: f32 = 0.4;
+
 /// Fusion strategy for combining embeddings
 #[derive(Debug, Clone, Copy)]
 pub enum FusionStrategy {
     /// Simple weighted average: α * bge + (1-α) * code
     WeightedAverage { alpha: f32 },
-    /// Concatenate then project: W * [bge; code]
+    /// Concatenate then project: W * [bge; code], W trained by `sgrep train-fusion`
     Concatenate,
     /// Max pooling across dimensions
     MaxPool,
-    /// Adaptive: learn weights per dimension
+    /// Adaptive: learned per-dimension gate (see `core::fusion_layer`)
     Adaptive,
 }
 
+impl FromStr for FusionStrategy {
+    type Err = anyhow::Error;
+
+    /// Strategy by name; `weighted` uses the default alpha
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "weighted" => Ok(FusionStrategy::default()),
+            "concatenate" => Ok(FusionStrategy::Concatenate),
+            "maxpool" => Ok(FusionStrategy::MaxPool),
+            "adaptive" => Ok(FusionStrategy::Adaptive),
+            other => Err(anyhow!(
+                "Unknown fusion '{}' (expected weighted, concatenate, maxpool or adaptive)",
+                other
+            )),
+        }
+    }
+}
+
+impl FusionStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FusionStrategy::WeightedAverage { .. } => "weighted",
+            FusionStrategy::Concatenate => "concatenate",
+            FusionStrategy::MaxPool => "maxpool",
+            FusionStrategy::Adaptive => "adaptive",
+        }
+    }
+}
+
 impl Default for FusionStrategy {
     fn default() -> Self {
         // Default: 40% BGE (general understanding) + 60% CodeRankEmbed (code-specific)
-        FusionStrategy::WeightedAverage { alpha: 0.4 }
+        FusionStrategy::WeightedAverage {
+            alpha: DEFAULT_ALPHA,
+        }
     }
 }
 
@@ -58,6 +94,8 @@ pub struct HybridEmbedder {
     code_embedder: LocalEmbedder,
     /// Fusion strategy
     strategy: FusionStrategy,
+    /// Trained weights for Adaptive and Concatenate, from `sgrep train-fusion`
+    layer: Option<FusionLayer>,
     /// Embedding dimension (same as input models)
     embedding_dim: usize,
     /// Device
@@ -88,11 +126,20 @@ impl HybridEmbedder {
 
         HybridModelStatus::show_fusion_ready();
 
+        let embedding_dim = bge_embedder.embedding_dim(); // Both are 768-dim
+        let layer = match strategy {
+            FusionStrategy::Adaptive | FusionStrategy::Concatenate => {
+                FusionLayer::load(strategy, embedding_dim)?
+            }
+            _ => None,
+        };
+
         Ok(Self {
-            embedding_dim: bge_embedder.embedding_dim(), // Both are 768-dim
+            embedding_dim,
             bge_embedder,
             code_embedder,
             strategy,
+            layer,
             device: Device::Cpu,
         })
     }
@@ -120,11 +167,36 @@ impl HybridEmbedder {
 
     /// Embed a search query with fusion
     pub fn embed_query(&mut self, query: &str) -> Result<Vec<f32>> {
-        // For queries, we might want slightly different weighting
-        // Giving more weight to semantic understanding
+        let (bge_emb, code_emb) = self.embed_query_parts(query)?;
+        self.fuse(&bge_emb, &code_emb)
+    }
+
+    /// Embed several search queries with fusion, one batch per model
+    pub fn embed_queries(&mut self, queries: &[String]) -> Result<Vec<Vec<f32>>> {
+        let bge = self.bge_embedder.embed_queries(queries)?;
+        let code = self.code_embedder.embed_queries(queries)?;
+        bge.iter()
+            .zip(&code)
+            .map(|(b, c)| self.fuse(&self.l2_normalize(b), &self.l2_normalize(c)))
+            .collect()
+    }
+
+    /// Normalized BGE and CodeRankEmbed query embeddings, before fusion
+    pub fn embed_query_parts(&mut self, query: &str) -> Result<(Vec<f32>, Vec<f32>)> {
         let bge_emb = self.bge_embedder.embed_query(query)?;
         let code_emb = self.code_embedder.embed_query(query)?;
-        self.fuse(&bge_emb, &code_emb)
+        Ok((self.l2_normalize(&bge_emb), self.l2_normalize(&code_emb)))
+    }
+
+    /// Normalized BGE and CodeRankEmbed document embeddings, before fusion
+    pub fn embed_document_parts(&mut self, texts: &[String]) -> Result<Vec<(Vec<f32>, Vec<f32>)>> {
+        let bge = self.bge_embedder.embed(texts)?;
+        let code = self.code_embedder.embed(texts)?;
+        Ok(bge
+            .iter()
+            .zip(&code)
+            .map(|(b, c)| (self.l2_normalize(b), self.l2_normalize(c)))
+            .collect())
     }
 
     /// Embed a single text with fusion
@@ -141,6 +213,9 @@ impl HybridEmbedder {
 
     /// Fuse two embedding vectors according to the strategy
     fn fuse(&self, bge: &[f32], code: &[f32]) -> Result<Vec<f32>> {
+        if let Some(ref layer) = self.layer {
+            return layer.fuse(&self.l2_normalize(bge), &self.l2_normalize(code));
+        }
         match self.strategy {
             FusionStrategy::WeightedAverage { alpha } => {
                 self.fuse_weighted_average(bge, code, alpha)
@@ -199,7 +274,7 @@ impl HybridEmbedder {
         Ok(fused)
     }
 
-    /// Concatenate and project (simplified: average of dimensions)
+    /// Concatenate and project, until weights are trained (simplified: average of dimensions)
     fn fuse_concatenate(&self, bge: &[f32], code: &[f32]) -> Result<Vec<f32>> {
         // For now, just interleave and average pairs
         // A full implementation would use a learned projection matrix
@@ -223,7 +298,7 @@ impl HybridEmbedder {
         Ok(fused)
     }
 
-    /// Adaptive fusion: weight by magnitude
+    /// Adaptive fusion until weights are trained: weight by magnitude
     fn fuse_adaptive(&self, bge: &[f32], code: &[f32]) -> Result<Vec<f32>> {
         let bge_norm = self.l2_normalize(bge);
         let code_norm = self.l2_normalize(code);
@@ -290,6 +365,7 @@ mod tests {
             bge_embedder: LocalEmbedder::with_speed_mode(SpeedMode::Balanced).unwrap(),
             code_embedder: LocalEmbedder::with_speed_mode(SpeedMode::Code).unwrap(),
             strategy: FusionStrategy::default(),
+            layer: None,
             embedding_dim: 768,
             device: Device::Cpu,
         };
@@ -0,0 +1,710 @@
+//! Query intent routing
+//!
+//! Classifies a query as an identifier, a code snippet, a natural-language
+//! question or an error message and routes it: how BM25 splits it into
+//! terms, how much the lexical score counts and, when
+//! `config.route_embedder` is on, which embedder encodes it.
+//!
+//! The classifier is a softmax model over 14 surface features
+//! (symbol shapes, code punctuation, question words, error markers, file
+//! locations). Its default weights are hand-set rules; `sgrep intent
+//! --train` refits them to labeled queries and saves the result to
+//! `~/.sgrep/intent-model.json`, which routing then uses.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::core::config::Config;
+use crate::core::search::{HybridSearcher, QueryTokenizer};
+use crate::core::session::EmbedMode;
+use crate::core::store::VectorStore;
+
+/// Embedding size of the local BGE model that hybrid fusion builds on
+const BGE_DIM: usize = 768;
+const FEATURES: usize = 14;
+/// Every n-th labeled query is held out to check a fit
+const HOLD_OUT_EVERY: usize = 5;
+const MIN_EXAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryIntent {
+    /// A symbol name or path: `parseConfig`, `HybridSearcher::search`
+    Identifier,
+    /// Source code: `let x = foo(bar);`
+    Code,
+    /// Natural language: "how are stale chunks removed?"
+    Question,
+    /// An error message or stack trace
+    Error,
+}
+
+pub const INTENTS: [QueryIntent; 4] = [
+    QueryIntent::Identifier,
+    QueryIntent::Code,
+    QueryIntent::Question,
+    QueryIntent::Error,
+];
+
+impl FromStr for QueryIntent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "identifier" | "symbol" => Ok(QueryIntent::Identifier),
+            "code" | "snippet" => Ok(QueryIntent::Code),
+            "question" | "text" => Ok(QueryIntent::Question),
+            "error" | "trace" => Ok(QueryIntent::Error),
+            other => bail!(
+                "Unknown intent '{}' (expected identifier, code, question or error)",
+                other
+            ),
+        }
+    }
+}
+
+impl QueryIntent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryIntent::Identifier => "identifier",
+            QueryIntent::Code => "code",
+            QueryIntent::Question => "question",
+            QueryIntent::Error => "error",
+        }
+    }
+
+    fn index(self) -> usize {
+        INTENTS.iter().position(|&i| i == self).unwrap()
+    }
+
+    /// Lowest share of the BM25 score in the combined retrieval score;
+    /// exact tokens matter more for symbols and error text than for prose
+    fn min_lexical_share(self) -> f32 {
+        match self {
+            QueryIntent::Identifier => 0.6,
+            QueryIntent::Error => 0.5,
+            QueryIntent::Code => 0.4,
+            QueryIntent::Question => 0.0,
+        }
+    }
+
+    fn tokenizer(self) -> QueryTokenizer {
+        match self {
+            QueryIntent::Identifier => QueryTokenizer::Symbols,
+            QueryIntent::Error => QueryTokenizer::Error,
+            QueryIntent::Code | QueryIntent::Question => QueryTokenizer::Words,
+        }
+    }
+}
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "of", "to", "in", "for", "on", "with", "and", "or", "is", "are", "does",
+    "do", "how", "what", "where", "when", "why", "which", "that", "this", "it", "from", "by", "be",
+    "as",
+];
+const QUESTION_WORDS: &[&str] = &[
+    "how", "what", "where", "why", "when", "which", "who", "does", "do", "is", "are", "can",
+    "should", "find", "show", "list", "explain",
+];
+const CODE_KEYWORDS: &[&str] = &[
+    "fn", "let", "const", "var", "def", "class", "return", "import", "function", "pub", "struct",
+    "impl", "async", "await", "public", "static", "void", "func", "package", "#include", "=>",
+    "->", ":=",
+];
+const ERROR_WORDS: &[&str] = &[
+    "error",
+    "exception",
+    "panic",
+    "panicked",
+    "traceback",
+    "failed",
+    "fatal",
+    "undefined",
+    "cannot",
+    "unexpected",
+    "refused",
+    "denied",
+    "overflow",
+    "segmentation",
+];
+
+/// Surface features of a query, each roughly in [0, 1]. The first is a
+/// constant bias term.
+fn features(text: &str) -> [f32; FEATURES] {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let n = tokens.len().max(1) as f32;
+    let words: Vec<String> = tokens
+        .iter()
+        .map(|t| {
+            t.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .collect();
+    let fraction = |count: usize| count as f32 / n;
+    let flag = |b: bool| if b { 1.0 } else { 0.0 };
+
+    let symbols = tokens.iter().filter(|t| is_symbol(t)).count();
+    let stopwords = words
+        .iter()
+        .filter(|w| STOPWORDS.contains(&w.as_str()))
+        .count();
+    let question = text.trim_end().ends_with('?')
+        || words
+            .first()
+            .is_some_and(|w| QUESTION_WORDS.contains(&w.as_str()));
+    let chars = text.chars().count().max(1) as f32;
+    let punctuation = text.chars().filter(|c| "(){}[];=<>&|".contains(*c)).count() as f32;
+    let prose = tokens
+        .iter()
+        .filter(|t| {
+            let word = t.trim_end_matches(|c: char| ",.?!:;".contains(c));
+            word.len() >= 2 && word.chars().all(|c| c.is_ascii_lowercase())
+        })
+        .count();
+
+    [
+        1.0,
+        flag(tokens.len() == 1),
+        if tokens.len() <= 3 {
+            fraction(symbols)
+        } else {
+            0.0
+        },
+        fraction(stopwords),
+        flag(question),
+        (punctuation / chars * 8.0).min(1.0),
+        flag(tokens.iter().any(|t| CODE_KEYWORDS.contains(t))),
+        flag(text.trim().contains('\n')),
+        flag(words.iter().any(|w| ERROR_WORDS.contains(&w.as_str()))),
+        flag(has_error_shape(text, &tokens)),
+        flag(has_location(&tokens)),
+        flag(text.contains(['"', '\'', '`'])),
+        fraction(prose),
+        ((1.0 + n).ln() / 3.0).min(1.0),
+    ]
+}
+
+/// `parseConfig`, `parse_config`, `std::fs::read`, `store.chunks()`
+fn is_symbol(token: &str) -> bool {
+    let token = token.trim_end_matches("()");
+    if !token
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        || !token
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_:.$#".contains(c))
+    {
+        return false;
+    }
+    let bytes = token.as_bytes();
+    let camel = bytes
+        .windows(2)
+        .any(|w| w[0].is_ascii_lowercase() && w[1].is_ascii_uppercase());
+    let joined = bytes.windows(3).any(|w| {
+        w[0].is_ascii_alphanumeric() && b"_.#".contains(&w[1]) && w[2].is_ascii_alphanumeric()
+    });
+    camel || joined || token.contains("::")
+}
+
+fn has_error_shape(text: &str, tokens: &[&str]) -> bool {
+    const MARKERS: &[&str] = &[
+        "panicked at",
+        "Traceback (most recent call last)",
+        "error[E",
+        "Caused by:",
+        "goroutine ",
+        "Uncaught ",
+    ];
+    MARKERS.iter().any(|m| text.contains(m))
+        || text.trim_start().starts_with("error:")
+        || tokens.iter().any(|t| {
+            let t = t.trim_end_matches(':');
+            t.len() > 5
+                && t.starts_with(|c: char| c.is_ascii_uppercase())
+                && (t.ends_with("Error") || t.ends_with("Exception"))
+        })
+}
+
+/// `src/main.rs:12:5`, `(App.java:42)`, `File "x.py", line 3`
+fn has_location(tokens: &[&str]) -> bool {
+    tokens.iter().enumerate().any(|(i, token)| {
+        let file_line = token
+            .split(':')
+            .skip(1)
+            .any(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+            && token
+                .split(':')
+                .next()
+                .is_some_and(|file| file.contains('.'));
+        let line_word = *token == "line"
+            && tokens
+                .get(i + 1)
+                .is_some_and(|next| next.starts_with(|c: char| c.is_ascii_digit()));
+        file_line || line_word
+    })
+}
+
+/// Hand-set weights per intent, in `INTENTS` order, over the features:
+/// bias, single token, short symbol, stopwords, question cue, code
+/// punctuation, code keyword, multi-line, error word, error shape,
+/// location, quotes, prose, length
+const RULES: [[f32; FEATURES]; 4] = [
+    [
+        0.0, 3.0, 3.0, -2.0, -2.0, 0.0, -0.5, -2.0, -1.0, -2.0, -1.0, -0.5, -0.5, -1.5,
+    ],
+    [
+        -0.5, -1.0, 0.5, -1.5, -1.5, 5.0, 2.0, 2.0, -0.5, -1.5, -0.5, 0.3, -1.0, 0.5,
+    ],
+    [
+        1.0, -1.5, -1.0, 3.0, 2.5, -2.0, -0.5, -1.0, 0.0, -2.0, -1.0, 0.0, 2.0, 0.5,
+    ],
+    [
+        -1.0, -1.0, -0.5, 0.0, -1.5, 0.5, -0.5, 0.5, 2.0, 4.0, 3.0, 1.0, 0.0, 0.5,
+    ],
+];
+
+#[derive(Debug, Clone)]
+pub struct Classification {
+    pub intent: QueryIntent,
+    /// Softmax probability of `intent`
+    pub confidence: f32,
+}
+
+/// Linear intent classifier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntentModel {
+    /// One row of feature weights per intent, in `INTENTS` order
+    weights: Vec<Vec<f32>>,
+    /// Whether the weights were fit to labeled queries
+    #[serde(default)]
+    trained: bool,
+}
+
+impl Default for IntentModel {
+    fn default() -> Self {
+        Self {
+            weights: RULES.iter().map(|row| row.to_vec()).collect(),
+            trained: false,
+        }
+    }
+}
+
+impl IntentModel {
+    pub fn path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("intent-model.json"))
+    }
+
+    /// The trained model, or the rules when none was saved
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read intent model: {}", path.display()))?;
+        let model: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid intent model: {}", path.display()))?;
+        if model.weights.len() != INTENTS.len()
+            || model.weights.iter().any(|row| row.len() != FEATURES)
+        {
+            bail!(
+                "Intent model {} has the wrong shape; retrain it with sgrep intent --train",
+                path.display()
+            );
+        }
+        Ok(model)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn is_trained(&self) -> bool {
+        self.trained
+    }
+
+    pub fn classify(&self, text: &str) -> Classification {
+        let probabilities = self.probabilities(&features(text));
+        let (best, &confidence) = probabilities
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        Classification {
+            intent: INTENTS[best],
+            confidence,
+        }
+    }
+
+    fn probabilities(&self, x: &[f32; FEATURES]) -> [f32; 4] {
+        let mut scores = [0.0f32; 4];
+        for (score, row) in scores.iter_mut().zip(&self.weights) {
+            *score = row.iter().zip(x).map(|(w, v)| w * v).sum();
+        }
+        let max = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let mut total = 0.0;
+        for score in scores.iter_mut() {
+            *score = (*score - max).exp();
+            total += *score;
+        }
+        scores.map(|s| s / total)
+    }
+
+    fn accuracy(&self, examples: &[&(String, QueryIntent)]) -> f32 {
+        if examples.is_empty() {
+            return 0.0;
+        }
+        let correct = examples
+            .iter()
+            .filter(|(text, intent)| self.classify(text).intent == *intent)
+            .count();
+        correct as f32 / examples.len() as f32
+    }
+}
+
+/// Held-out accuracy of the rules and of the fitted model
+#[derive(Debug, Clone, Serialize)]
+pub struct IntentFit {
+    pub train_examples: usize,
+    pub held_out_examples: usize,
+    pub rules_accuracy: f32,
+    pub trained_accuracy: f32,
+}
+
+impl IntentFit {
+    pub fn improved(&self) -> bool {
+        self.trained_accuracy >= self.rules_accuracy
+    }
+}
+
+/// Fit the classifier to labeled queries with softmax regression, starting
+/// from (and regularized towards) the rules
+pub fn fit_intents(examples: &[(String, QueryIntent)]) -> Result<(IntentModel, IntentFit)> {
+    const EPOCHS: usize = 300;
+    const LEARNING_RATE: f32 = 0.5;
+    const REGULARIZATION: f32 = 0.01;
+
+    if examples.len() < MIN_EXAMPLES {
+        bail!(
+            "Need at least {} labeled queries to fit intents, got {}",
+            MIN_EXAMPLES,
+            examples.len()
+        );
+    }
+    let (held_out, train): (Vec<_>, Vec<_>) = examples
+        .iter()
+        .enumerate()
+        .partition(|(i, _)| i % HOLD_OUT_EVERY == HOLD_OUT_EVERY - 1);
+    let held_out: Vec<&(String, QueryIntent)> = held_out.into_iter().map(|(_, e)| e).collect();
+    let train: Vec<&(String, QueryIntent)> = train.into_iter().map(|(_, e)| e).collect();
+    let inputs: Vec<([f32; FEATURES], usize)> = train
+        .iter()
+        .map(|(text, intent)| (features(text), intent.index()))
+        .collect();
+
+    let rules = IntentModel::default();
+    let mut model = IntentModel {
+        trained: true,
+        ..rules.clone()
+    };
+    for _ in 0..EPOCHS {
+        let mut gradient = vec![vec![0.0f32; FEATURES]; INTENTS.len()];
+        for (x, label) in &inputs {
+            let probabilities = model.probabilities(x);
+            for (k, row) in gradient.iter_mut().enumerate() {
+                let error = probabilities[k] - if k == *label { 1.0 } else { 0.0 };
+                for (g, v) in row.iter_mut().zip(x) {
+                    *g += error * v;
+                }
+            }
+        }
+        for (k, row) in model.weights.iter_mut().enumerate() {
+            for (j, w) in row.iter_mut().enumerate() {
+                let pull = REGULARIZATION * (*w - RULES[k][j]);
+                *w -= LEARNING_RATE * (gradient[k][j] / inputs.len() as f32 + pull);
+            }
+        }
+    }
+
+    let fit = IntentFit {
+        train_examples: train.len(),
+        held_out_examples: held_out.len(),
+        rules_accuracy: rules.accuracy(&held_out),
+        trained_accuracy: model.accuracy(&held_out),
+    };
+    Ok((model, fit))
+}
+
+/// Labeled queries, one JSON object per line: `{"query": ..., "intent": ...}`
+pub fn load_examples(path: &Path) -> Result<Vec<(String, QueryIntent)>> {
+    #[derive(Deserialize)]
+    struct Example {
+        query: String,
+        intent: String,
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read labeled queries: {}", path.display()))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let example: Example = serde_json::from_str(line)
+                .with_context(|| format!("{}:{}: invalid example", path.display(), i + 1))?;
+            let intent = example
+                .intent
+                .parse()
+                .with_context(|| format!("{}:{}", path.display(), i + 1))?;
+            Ok((example.query, intent))
+        })
+        .collect()
+}
+
+/// How one query is searched
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub intent: QueryIntent,
+    pub confidence: f32,
+    /// Classified by a trained model rather than the rules
+    pub trained: bool,
+    pub mode: EmbedMode,
+    pub tokenizer: QueryTokenizer,
+    pub bm25_weight: f32,
+    pub vector_weight: f32,
+}
+
+impl Route {
+    /// One line for humans: `identifier (92%) → hybrid embedder, symbols terms, bm25 0.60`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ({:.0}%{}) → {} embedder, {} terms, bm25 {:.2}",
+            self.intent.as_str(),
+            self.confidence * 100.0,
+            if self.trained { ", trained" } else { "" },
+            self.mode.as_str(),
+            self.tokenizer.as_str(),
+            self.bm25_weight
+        )
+    }
+
+    /// Searcher with this route's weights and tokenizer
+    pub fn searcher(&self) -> HybridSearcher {
+        HybridSearcher::new(self.bm25_weight, self.vector_weight).with_tokenizer(self.tokenizer)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "intent": self.intent.as_str(),
+            "confidence": self.confidence,
+            "classifier": if self.trained { "trained" } else { "rules" },
+            "mode": self.mode.as_str(),
+            "tokenizer": self.tokenizer.as_str(),
+            "bm25_weight": self.bm25_weight,
+            "vector_weight": self.vector_weight,
+        })
+    }
+}
+
+/// Routes queries against one store
+#[derive(Debug, Clone)]
+pub struct Router {
+    model: IntentModel,
+    /// Configured BM25 and vector weights
+    weights: (f32, f32),
+    /// Hybrid BGE + CodeRankEmbed queries fit the store's embeddings
+    hybrid: bool,
+}
+
+impl Router {
+    /// Router with the saved intent model, falling back to the rules when
+    /// it can't be read. `local` means default queries are embedded with
+    /// the local BGE model, which hybrid fusion extends; the embedder is
+    /// only switched when `config.route_embedder` opts in.
+    pub fn new(config: &Config, store: &VectorStore, local: bool) -> Self {
+        let dim = store.chunks.values().next().map(|c| c.embedding.len());
+        Self::with_model(
+            IntentModel::load().unwrap_or_default(),
+            config,
+            config.get_route_embedder() && local && dim == Some(BGE_DIM),
+        )
+    }
+
+    pub fn with_model(model: IntentModel, config: &Config, hybrid: bool) -> Self {
+        Self {
+            model,
+            weights: config.get_fusion_weights(),
+            hybrid,
+        }
+    }
+
+    pub fn route(&self, text: &str) -> Route {
+        let Classification { intent, confidence } = self.model.classify(text);
+
+        // Code-shaped queries add CodeRankEmbed to the configured model's
+        // BGE embedding; swapping in CodeRankEmbed alone would leave the
+        // space the store was indexed in
+        let mode = if self.hybrid && intent != QueryIntent::Question {
+            EmbedMode::Hybrid
+        } else {
+            EmbedMode::Default
+        };
+
+        let (bm25, vector) = self.weights;
+        let total = bm25 + vector;
+        let share = if total > 0.0 { bm25 / total } else { 0.0 };
+        let bm25_weight = total * share.max(intent.min_lexical_share());
+
+        Route {
+            intent,
+            confidence,
+            trained: self.model.is_trained(),
+            mode,
+            tokenizer: intent.tokenizer(),
+            bm25_weight,
+            vector_weight: total - bm25_weight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_rules_classify() {
+        let model = IntentModel::default();
+        let cases = [
+            ("parseConfig", QueryIntent::Identifier),
+            ("HybridSearcher::search", QueryIntent::Identifier),
+            ("store.chunks.values()", QueryIntent::Identifier),
+            ("MAX_LINES", QueryIntent::Identifier),
+            ("let x = foo(bar);", QueryIntent::Code),
+            ("fn main() { println!(\"hi\"); }", QueryIntent::Code),
+            ("how does the cache get invalidated?", QueryIntent::Question),
+            ("error handling in the http client", QueryIntent::Question),
+            ("where are embeddings normalized", QueryIntent::Question),
+            (
+                "thread 'main' panicked at src/main.rs:3:5",
+                QueryIntent::Error,
+            ),
+            (
+                "TypeError: Cannot read properties of undefined (reading 'x')",
+                QueryIntent::Error,
+            ),
+            (
+                "error[E0382]: borrow of moved value: `config`",
+                QueryIntent::Error,
+            ),
+            (
+                "File \"app.py\", line 12, in load\nKeyError: 'path'",
+                QueryIntent::Error,
+            ),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(model.classify(query).intent, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let config = Config::default();
+        let router = Router::with_model(IntentModel::default(), &config, true);
+        let (bm25, vector) = config.get_fusion_weights();
+
+        let route = router.route("parse_config");
+        assert_eq!(route.intent, QueryIntent::Identifier);
+        assert_eq!(route.mode, EmbedMode::Hybrid);
+        assert_eq!(route.tokenizer, QueryTokenizer::Symbols);
+        assert!((route.bm25_weight - 0.6 * (bm25 + vector)).abs() < 1e-5);
+
+        // Questions keep the configured search
+        let route = router.route("how are stale chunks removed?");
+        assert_eq!(route.mode, EmbedMode::Default);
+        assert_eq!(route.tokenizer, QueryTokenizer::Words);
+        assert_eq!((route.bm25_weight, route.vector_weight), (bm25, vector));
+
+        // API or MiniLM embeddings never mix with the local models
+        let route =
+            Router::with_model(IntentModel::default(), &config, false).route("parse_config");
+        assert_eq!(route.mode, EmbedMode::Default);
+
+        // A local BGE store only switches embedders once configured to
+        let store = VectorStore::with_test_chunks(vec![
+            FileChunk::test("1", "/r/a.rs", (1, 5)).with_embedding(vec![0.0; BGE_DIM])
+        ]);
+        let route = Router::new(&config, &store, true).route("parse_config");
+        assert_eq!(route.mode, EmbedMode::Default);
+        let config = Config {
+            route_embedder: Some(true),
+            ..Config::default()
+        };
+        let route = Router::new(&config, &store, true).route("parse_config");
+        assert_eq!(route.mode, EmbedMode::Hybrid);
+    }
+
+    #[test]
+    fn test_tokenizers() {
+        let idf: HashMap<String, f32> = [
+            "self.parse_config(path)",
+            "parse_config_file(path)",
+            "fn",
+            "missing",
+        ]
+        .iter()
+        .map(|t| (t.to_string(), 1.0))
+        .collect();
+
+        let terms = QueryTokenizer::Symbols.terms("parse_config", &idf);
+        assert_eq!(terms, vec!["parse_config", "self.parse_config(path)"]);
+
+        let terms =
+            QueryTokenizer::Error.terms("error: 'missing' field at src/lib.rs:12:5 0x7ff3", &idf);
+        assert_eq!(
+            terms,
+            vec!["error:", "error", "'missing'", "missing", "field", "at"]
+        );
+    }
+
+    #[test]
+    fn test_fit_intents() {
+        // Short lowercase words labeled as questions, which the rules call
+        // identifiers
+        let mut examples = Vec::new();
+        for word in [
+            "retry",
+            "backoff",
+            "caching",
+            "logging",
+            "pagination",
+            "auth",
+            "indexing",
+            "parsing",
+            "config",
+            "tokens",
+        ] {
+            examples.push((word.to_string(), QueryIntent::Question));
+            examples.push((format!("{}Handler", word), QueryIntent::Identifier));
+        }
+        let (model, fit) = fit_intents(&examples).unwrap();
+        assert!(model.is_trained());
+        assert!(fit.improved());
+        assert!(fit.trained_accuracy > fit.rules_accuracy);
+        assert_eq!(model.classify("sharding").intent, QueryIntent::Question);
+        assert_eq!(
+            model.classify("shardingHandler").intent,
+            QueryIntent::Identifier
+        );
+    }
+}
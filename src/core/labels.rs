@@ -0,0 +1,147 @@
+//! Labeled queries for calibration and evaluation
+//!
+//! A label file is a JSON array of queries with the locations and symbols
+//! that answer them:
+//!
+//! ```json
+//! [{ "query": "persist the vector store", "relevant": ["src/core/store.rs:200-260"],
+//!    "symbols": ["VectorStore::save"] }]
+//! ```
+//!
+//! Locations are `path` or `path:start-end`; paths match any result path
+//! ending with them, so label files work from any checkout location.
+//! Symbols are `name` or `Parent::name`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::core::store::FileChunk;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabeledQuery {
+    pub query: String,
+    /// Relevant locations: `path` or `path:start-end`
+    #[serde(default)]
+    pub relevant: Vec<String>,
+    /// Relevant symbols: `name` or `Parent::name`
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+impl LabeledQuery {
+    /// Whether `chunk` is one of the relevant locations or symbols
+    pub fn is_relevant(&self, chunk: &FileChunk) -> bool {
+        self.relevant
+            .iter()
+            .any(|location| location_matches(location, chunk))
+            || self
+                .symbols
+                .iter()
+                .any(|symbol| symbol_matches(symbol, chunk))
+    }
+
+    /// Indexes of the relevant locations, then symbols, that `chunk` covers
+    pub fn targets_of(&self, chunk: &FileChunk) -> Vec<usize> {
+        let locations = self
+            .relevant
+            .iter()
+            .map(|location| location_matches(location, chunk));
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|symbol| symbol_matches(symbol, chunk));
+        locations
+            .chain(symbols)
+            .enumerate()
+            .filter_map(|(i, covered)| covered.then_some(i))
+            .collect()
+    }
+
+    /// Number of relevant locations and symbols
+    pub fn target_count(&self) -> usize {
+        self.relevant.len() + self.symbols.len()
+    }
+
+    /// Number of relevant locations and symbols covered by any of `chunks`
+    pub fn targets_found(&self, chunks: &[&FileChunk]) -> usize {
+        let locations = self
+            .relevant
+            .iter()
+            .filter(|location| chunks.iter().any(|c| location_matches(location, c)))
+            .count();
+        let symbols = self
+            .symbols
+            .iter()
+            .filter(|symbol| chunks.iter().any(|c| symbol_matches(symbol, c)))
+            .count();
+        locations + symbols
+    }
+}
+
+fn location_matches(location: &str, chunk: &FileChunk) -> bool {
+    let (path, range) = parse_location(location);
+    Path::new(&chunk.file_path).ends_with(path)
+        && range.is_none_or(|(start, end)| chunk.start_line <= end && start <= chunk.end_line)
+}
+
+fn symbol_matches(symbol: &str, chunk: &FileChunk) -> bool {
+    let (parent, name) = match symbol.rsplit_once("::") {
+        Some((parent, name)) => (Some(parent.rsplit("::").next().unwrap_or(parent)), name),
+        None => (None, symbol),
+    };
+    chunk.symbol_name.as_deref() == Some(name)
+        && parent.is_none_or(|parent| chunk.parent_name.as_deref() == Some(parent))
+}
+
+pub fn load_labels(path: &str) -> Result<Vec<LabeledQuery>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read labels: {}", path))?;
+    serde_json::from_str(&content).with_context(|| format!("Invalid label file: {}", path))
+}
+
+/// Split `path:start-end` (or `path:line`) into a path and line range
+fn parse_location(location: &str) -> (&str, Option<(usize, usize)>) {
+    if let Some((path, range)) = location.rsplit_once(':') {
+        let bounds = match range.split_once('-') {
+            Some((start, end)) => start.parse().ok().zip(end.parse().ok()),
+            None => range.parse().ok().map(|line| (line, line)),
+        };
+        if bounds.is_some() {
+            return (path, bounds);
+        }
+    }
+    (location, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevant_locations() {
+        let chunk = FileChunk::test("c", "/repo/src/core/store.rs", (200, 240));
+        let label = |relevant: &str| LabeledQuery {
+            query: "q".to_string(),
+            relevant: vec![relevant.to_string()],
+            symbols: Vec::new(),
+        };
+
+        assert!(label("src/core/store.rs").is_relevant(&chunk));
+        assert!(label("core/store.rs:230-300").is_relevant(&chunk));
+        assert!(label("store.rs:210").is_relevant(&chunk));
+        assert!(!label("store.rs:10-20").is_relevant(&chunk));
+        assert!(!label("re/store.rs").is_relevant(&chunk));
+
+        let chunk = chunk.with_symbol("save").with_parent("VectorStore");
+        let symbols = LabeledQuery {
+            query: "q".to_string(),
+            relevant: vec!["store.rs:10-20".to_string()],
+            symbols: vec!["VectorStore::save".to_string(), "load".to_string()],
+        };
+        assert!(symbols.is_relevant(&chunk));
+        assert_eq!(symbols.target_count(), 3);
+        assert_eq!(symbols.targets_found(&[&chunk]), 1);
+    }
+}
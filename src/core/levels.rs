@@ -0,0 +1,477 @@
+//! File- and directory-level vectors for hierarchical retrieval
+//!
+//! A file vector is the mean of its chunk embeddings, with chunks that
+//! define top-level symbols weighted up, mixed with an embedding of the
+//! file's path and top-level symbol names; a directory vector is the mean of
+//! the vectors of the files directly in it. File and directory ranking also
+//! adds a lexical match of the query terms against the names.
+//!
+//! Each file vector is stamped with the file's hash and index time. The
+//! indexer embeds the names of the files changed since (`stale_names`,
+//! `embed_names`) and `sync` re-pools only those.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::core::embeddings::{cosine_similarity, EmbeddingProvider};
+use crate::core::filter::SearchFilter;
+use crate::core::search::query_terms;
+use crate::core::store::{FileChunk, IndexedFile};
+
+/// Weight of chunks defining a top-level symbol in the file vector
+const TOP_LEVEL_WEIGHT: f32 = 2.0;
+/// Weight of the path/symbol name match in file and directory scores
+const NAME_WEIGHT: f32 = 0.2;
+/// Weight of the embedded path and symbol names against the pooled chunks
+const NAME_VECTOR_WEIGHT: f32 = 0.5;
+/// Name texts per embedding request
+const NAME_BATCH: usize = 64;
+
+/// Unit of retrieval for `sgrep search --level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchLevel {
+    #[default]
+    Chunk,
+    File,
+    Dir,
+}
+
+impl FromStr for SearchLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "chunk" => Ok(SearchLevel::Chunk),
+            "file" => Ok(SearchLevel::File),
+            "dir" | "directory" => Ok(SearchLevel::Dir),
+            other => bail!("Unknown level '{}' (expected chunk, file or dir)", other),
+        }
+    }
+}
+
+impl SearchLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchLevel::Chunk => "chunk",
+            SearchLevel::File => "file",
+            SearchLevel::Dir => "dir",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileVector {
+    /// `hash:indexed_at` of the file when it was pooled
+    stamp: String,
+    embedding: Vec<f32>,
+    /// Top-level symbol names
+    symbols: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelIndex {
+    files: HashMap<String, FileVector>,
+    dirs: HashMap<String, Vec<f32>>,
+}
+
+/// A ranked file or directory
+#[derive(Debug, Clone)]
+pub struct LevelHit {
+    pub path: String,
+    pub score: f32,
+    pub vector_score: f32,
+    /// Share of query terms found in the path or top-level symbol names
+    pub name_score: f32,
+    /// Top-level symbols of a file, or the files directly in a directory
+    pub names: Vec<String>,
+}
+
+impl LevelIndex {
+    /// Path and top-level symbol text of each file whose hash or index time
+    /// changed since it was pooled, for `embed_names`. Files without chunk
+    /// embeddings are skipped, as they get no vector.
+    pub fn stale_names(
+        &self,
+        files: &HashMap<String, IndexedFile>,
+        chunks: &HashMap<String, FileChunk>,
+    ) -> Vec<(String, String)> {
+        files
+            .iter()
+            .filter(|(path, file)| !self.is_current(path, file))
+            .filter_map(|(path, file)| {
+                let file_chunks = chunks_of(file, chunks);
+                if file_chunks.iter().all(|c| c.embedding.is_empty()) {
+                    return None;
+                }
+                let symbols = top_level_symbols(&file_chunks);
+                let text = if symbols.is_empty() {
+                    path.clone()
+                } else {
+                    format!("{}: {}", path, symbols.join(", "))
+                };
+                Some((path.clone(), text))
+            })
+            .collect()
+    }
+
+    /// Re-pool files whose hash or index time changed, mixing in their
+    /// embedded `names` (see `stale_names`), and drop removed files. A file
+    /// missing from `names` is pooled from its chunks alone. Returns whether
+    /// anything changed.
+    pub fn sync(
+        &mut self,
+        files: &HashMap<String, IndexedFile>,
+        chunks: &HashMap<String, FileChunk>,
+        names: &HashMap<String, Vec<f32>>,
+    ) -> bool {
+        let before = self.files.len();
+        self.files.retain(|path, _| files.contains_key(path));
+        let mut changed = self.files.len() != before;
+
+        for (path, file) in files {
+            if self.is_current(path, file) {
+                continue;
+            }
+            let stamp = stamp(file);
+            let file_chunks = chunks_of(file, chunks);
+            match pool_file(&file_chunks, names.get(path)) {
+                Some((embedding, symbols)) => {
+                    self.files.insert(
+                        path.clone(),
+                        FileVector {
+                            stamp,
+                            embedding,
+                            symbols,
+                        },
+                    );
+                }
+                None => {
+                    self.files.remove(path);
+                }
+            }
+            changed = true;
+        }
+
+        if changed || (self.dirs.is_empty() && !self.files.is_empty()) {
+            self.rebuild_dirs();
+        }
+        changed
+    }
+
+    fn is_current(&self, path: &str, file: &IndexedFile) -> bool {
+        self.files.get(path).is_some_and(|v| v.stamp == stamp(file))
+    }
+
+    fn rebuild_dirs(&mut self) {
+        let mut sums: HashMap<String, (Vec<f32>, usize)> = HashMap::new();
+        for (path, file) in &self.files {
+            let (sum, count) = sums
+                .entry(parent(path))
+                .or_insert_with(|| (vec![0.0; file.embedding.len()], 0));
+            if sum.len() == file.embedding.len() {
+                for (s, x) in sum.iter_mut().zip(&file.embedding) {
+                    *s += x;
+                }
+                *count += 1;
+            }
+        }
+        self.dirs = sums
+            .into_iter()
+            .filter(|(_, (_, count))| *count > 0)
+            .map(|(dir, (sum, _))| (dir, normalized(sum)))
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files ranked by similarity to the query
+    pub fn search_files(
+        &self,
+        query_embedding: &[f32],
+        query_text: &str,
+        limit: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<LevelHit> {
+        let terms = query_terms(query_text);
+        let mut hits: Vec<LevelHit> = self
+            .files
+            .iter()
+            .filter(|(path, _)| filter.is_none_or(|f| f.matches(path)))
+            .map(|(path, file)| {
+                let mut names = vec![path.as_str()];
+                names.extend(file.symbols.iter().map(String::as_str));
+                hit(
+                    path,
+                    cosine_similarity(query_embedding, &file.embedding),
+                    name_score(&terms, &names),
+                    file.symbols.clone(),
+                )
+            })
+            .collect();
+        rank(&mut hits, limit);
+        hits
+    }
+
+    /// Directories ranked by similarity to the query; only directories with
+    /// files that pass the filter are considered
+    pub fn search_dirs(
+        &self,
+        query_embedding: &[f32],
+        query_text: &str,
+        limit: usize,
+        filter: Option<&SearchFilter>,
+    ) -> Vec<LevelHit> {
+        let terms = query_terms(query_text);
+        let mut members: HashMap<String, Vec<String>> = HashMap::new();
+        for path in self.files.keys() {
+            if filter.is_none_or(|f| f.matches(path)) {
+                members.entry(parent(path)).or_default().push(path.clone());
+            }
+        }
+
+        let mut hits: Vec<LevelHit> = members
+            .into_iter()
+            .filter_map(|(dir, mut files)| {
+                let embedding = self.dirs.get(&dir)?;
+                files.sort();
+                Some(hit(
+                    &dir,
+                    cosine_similarity(query_embedding, embedding),
+                    name_score(&terms, &[dir.as_str()]),
+                    files,
+                ))
+            })
+            .collect();
+        rank(&mut hits, limit);
+        hits
+    }
+
+    /// Narrow a search directory → file: the best `files` files within the
+    /// best directories
+    pub fn narrow(
+        &self,
+        query_embedding: &[f32],
+        query_text: &str,
+        files: usize,
+        filter: Option<&SearchFilter>,
+    ) -> HashSet<PathBuf> {
+        let dirs: HashSet<String> = self
+            .search_dirs(query_embedding, query_text, (files / 2).max(3), filter)
+            .into_iter()
+            .map(|hit| hit.path)
+            .collect();
+        self.search_files(query_embedding, query_text, usize::MAX, filter)
+            .into_iter()
+            .filter(|hit| dirs.contains(&parent(&hit.path)))
+            .take(files)
+            .map(|hit| PathBuf::from(hit.path))
+            .collect()
+    }
+}
+
+/// Embed the `(path, text)` pairs of `LevelIndex::stale_names`. Files in a
+/// batch that fails to embed are left out and pooled from their chunks alone.
+pub async fn embed_names(
+    embeddings: &EmbeddingProvider,
+    names: Vec<(String, String)>,
+) -> HashMap<String, Vec<f32>> {
+    let mut embedded = HashMap::new();
+    for batch in names.chunks(NAME_BATCH) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        match embeddings.embed(&texts).await {
+            Ok(vectors) => {
+                embedded.extend(batch.iter().map(|(path, _)| path.clone()).zip(vectors));
+            }
+            Err(e) => eprintln!("Failed to embed file names: {}", e),
+        }
+    }
+    embedded
+}
+
+fn stamp(file: &IndexedFile) -> String {
+    format!("{}:{}", file.hash, file.indexed_at)
+}
+
+fn chunks_of<'a>(file: &IndexedFile, chunks: &'a HashMap<String, FileChunk>) -> Vec<&'a FileChunk> {
+    file.chunks.iter().filter_map(|id| chunks.get(id)).collect()
+}
+
+fn is_top_level(chunk: &FileChunk) -> bool {
+    chunk.parent_name.is_none() && chunk.symbol_name.is_some()
+}
+
+/// Sorted, deduplicated top-level symbol names of a file's chunks
+fn top_level_symbols(chunks: &[&FileChunk]) -> Vec<String> {
+    let mut symbols: Vec<String> = chunks
+        .iter()
+        .filter(|c| is_top_level(c))
+        .filter_map(|c| c.symbol_name.clone())
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    symbols
+}
+
+/// Pooled vector and top-level symbols of a file's chunks, mixed with the
+/// embedding of its names when it has the chunks' dimension
+fn pool_file(chunks: &[&FileChunk], names: Option<&Vec<f32>>) -> Option<(Vec<f32>, Vec<String>)> {
+    let dim = chunks.iter().map(|c| c.embedding.len()).find(|&n| n > 0)?;
+    let mut sum = vec![0.0f32; dim];
+
+    for chunk in chunks.iter().filter(|c| c.embedding.len() == dim) {
+        let weight = if is_top_level(chunk) {
+            TOP_LEVEL_WEIGHT
+        } else {
+            1.0
+        };
+        for (s, x) in sum.iter_mut().zip(&chunk.embedding) {
+            *s += weight * x;
+        }
+    }
+
+    let mut pooled = normalized(sum);
+    if let Some(names) = names.filter(|n| n.len() == dim) {
+        let names = normalized(names.clone());
+        for (p, x) in pooled.iter_mut().zip(&names) {
+            *p += NAME_VECTOR_WEIGHT * x;
+        }
+        pooled = normalized(pooled);
+    }
+    Some((pooled, top_level_symbols(chunks)))
+}
+
+fn hit(path: &str, vector_score: f32, name_score: f32, names: Vec<String>) -> LevelHit {
+    LevelHit {
+        path: path.to_string(),
+        score: vector_score + NAME_WEIGHT * name_score,
+        vector_score,
+        name_score,
+        names,
+    }
+}
+
+fn rank(hits: &mut Vec<LevelHit>, limit: usize) {
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(&b.path)));
+    hits.truncate(limit);
+}
+
+/// Share of query terms contained in any of `names`
+fn name_score(terms: &[String], names: &[&str]) -> f32 {
+    let terms: Vec<&String> = terms.iter().filter(|t| t.len() >= 3).collect();
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let names: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    let found = terms
+        .iter()
+        .filter(|t| names.iter().any(|n| n.contains(t.as_str())))
+        .count();
+    found as f32 / terms.len() as f32
+}
+
+fn parent(path: &str) -> String {
+    Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn normalized(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::VectorStore;
+
+    fn chunk(id: &str, file: &str, symbol: Option<&str>, embedding: Vec<f32>) -> FileChunk {
+        let chunk = FileChunk::test(id, file, (1, 10)).with_embedding(embedding);
+        match symbol {
+            Some(symbol) => chunk.with_symbol(symbol),
+            None => chunk,
+        }
+    }
+
+    #[test]
+    fn test_sync_and_rank() {
+        let store = VectorStore::with_test_chunks(vec![
+            chunk("1", "/r/db/pool.rs", Some("Pool"), vec![1.0, 0.0]),
+            chunk("2", "/r/db/pool.rs", None, vec![0.0, 1.0]),
+            chunk("3", "/r/db/query.rs", Some("execute"), vec![0.9, 0.1]),
+            chunk("4", "/r/ui/render.rs", Some("render"), vec![0.0, 1.0]),
+        ]);
+        let (mut files, chunks) = (store.files, store.chunks);
+
+        let mut levels = LevelIndex::default();
+        assert_eq!(levels.stale_names(&files, &chunks).len(), 3);
+        assert!(levels.sync(&files, &chunks, &HashMap::new()));
+        assert!(!levels.sync(&files, &chunks, &HashMap::new()));
+        assert!(levels.stale_names(&files, &chunks).is_empty());
+
+        // The top-level symbol outweighs the other chunk
+        let hits = levels.search_files(&[1.0, 0.0], "", 3, None);
+        assert_eq!(hits[0].path, "/r/db/query.rs");
+        assert_eq!(hits[1].path, "/r/db/pool.rs");
+        assert_eq!(hits[1].names, vec!["Pool"]);
+
+        // Path and symbol names lift the named file
+        let hits = levels.search_files(&[1.0, 0.0], "pool", 1, None);
+        assert_eq!(hits[0].path, "/r/db/pool.rs");
+
+        let dirs = levels.search_dirs(&[1.0, 0.0], "", 2, None);
+        assert_eq!(dirs[0].path, "/r/db");
+        assert_eq!(dirs[0].names.len(), 2);
+
+        let narrowed = levels.narrow(&[0.0, 1.0], "", 1, None);
+        assert_eq!(narrowed.len(), 1);
+        assert!(narrowed.contains(Path::new("/r/ui/render.rs")));
+
+        // A removed file drops out, and its directory with it
+        files.remove("/r/ui/render.rs");
+        assert!(levels.sync(&files, &chunks, &HashMap::new()));
+        assert_eq!(levels.search_dirs(&[0.0, 1.0], "", 5, None).len(), 1);
+    }
+
+    #[test]
+    fn test_names_are_embedded_into_file_vectors() {
+        let store = VectorStore::with_test_chunks(vec![
+            chunk("1", "/r/db/pool.rs", Some("Pool"), vec![1.0, 0.0]),
+            chunk("2", "/r/db/pool.rs", Some("connect"), vec![1.0, 0.0]),
+            chunk("3", "/r/db/query.rs", None, vec![1.0, 0.0]),
+            chunk("4", "/r/db/empty.rs", None, vec![]),
+        ]);
+        let (files, chunks) = (store.files, store.chunks);
+
+        let mut levels = LevelIndex::default();
+        let mut names = levels.stale_names(&files, &chunks);
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                (
+                    "/r/db/pool.rs".to_string(),
+                    "/r/db/pool.rs: Pool, connect".to_string()
+                ),
+                ("/r/db/query.rs".to_string(), "/r/db/query.rs".to_string()),
+            ]
+        );
+
+        // Identical chunks; only pool.rs's names point elsewhere
+        let embedded = HashMap::from([("/r/db/pool.rs".to_string(), vec![0.0, 1.0])]);
+        levels.sync(&files, &chunks, &embedded);
+        let hits = levels.search_files(&[0.0, 1.0], "", 2, None);
+        assert_eq!(hits[0].path, "/r/db/pool.rs");
+        assert!(hits[0].vector_score > 0.4);
+        assert!(hits[1].vector_score.abs() < 1e-6);
+    }
+}
@@ -22,6 +22,9 @@ pub enum SpeedMode {
     Code,
 }
 
+/// Queries embedded per forward pass by `embed_queries`
+const QUERY_BATCH: usize = 32;
+
 /// Model type enum to support different architectures
 enum ModelType {
     Bert(BertModel),
@@ -193,38 +196,69 @@ impl LocalEmbedder {
         self.embed_single(&prefixed)
     }
 
+    /// Embed several search queries, `QUERY_BATCH` per forward pass
+    pub fn embed_queries(&mut self, queries: &[String]) -> Result<Vec<Vec<f32>>> {
+        let prefixed: Vec<String> = queries
+            .iter()
+            .map(|q| format!("search_query: {}", q))
+            .collect();
+        let mut embeddings = Vec::with_capacity(queries.len());
+        for batch in prefixed.chunks(QUERY_BATCH) {
+            embeddings.extend(self.embed_batch(batch)?);
+        }
+        Ok(embeddings)
+    }
+
     /// Embed a single text and return embedding vector
     fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
-        let encoding = self
-            .tokenizer
-            .encode(text, true)
-            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+        self.embed_batch(&[text.to_string()])?
+            .pop()
+            .ok_or_else(|| anyhow!("Model returned no embedding"))
+    }
 
+    /// Embed texts in one forward pass: shorter inputs are padded to the
+    /// longest, and the attention mask keeps padding out of attention and
+    /// pooling, so each vector matches embedding that text alone
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
         // Max tokens depends on model (512 for BERT, 8192 for NomicBert)
         let max_len = match &self.model {
             ModelType::Bert(_) => 512,
             ModelType::NomicBert(_) => 8192,
         };
 
-        let input_ids: Vec<u32> = encoding.get_ids().iter().take(max_len).copied().collect();
-        let attention_mask: Vec<u32> = encoding
-            .get_attention_mask()
-            .iter()
-            .take(max_len)
-            .copied()
-            .collect();
-        let token_type_ids: Vec<u32> = encoding
-            .get_type_ids()
+        let mut encodings = Vec::with_capacity(texts.len());
+        for text in texts {
+            let encoding = self
+                .tokenizer
+                .encode(text.as_str(), true)
+                .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+            encodings.push(encoding);
+        }
+        let seq_len = encodings
             .iter()
-            .take(max_len)
-            .copied()
-            .collect();
-
-        let seq_len = input_ids.len();
+            .map(|e| e.get_ids().len().min(max_len))
+            .max()
+            .unwrap_or(0);
+
+        // Right-pad with id 0 and a zero mask
+        let padded = |values: &[u32], pad: u32| -> Vec<u32> {
+            let mut row: Vec<u32> = values.iter().take(seq_len).copied().collect();
+            row.resize(seq_len, pad);
+            row
+        };
+        let mut input_ids = Vec::with_capacity(texts.len() * seq_len);
+        let mut attention_mask = Vec::with_capacity(texts.len() * seq_len);
+        let mut token_type_ids = Vec::with_capacity(texts.len() * seq_len);
+        for encoding in &encodings {
+            input_ids.extend(padded(encoding.get_ids(), 0));
+            attention_mask.extend(padded(encoding.get_attention_mask(), 0));
+            token_type_ids.extend(padded(encoding.get_type_ids(), 0));
+        }
 
-        let input_ids = Tensor::new(input_ids.as_slice(), &self.device)?.unsqueeze(0)?;
-        let attention_mask = Tensor::new(attention_mask.as_slice(), &self.device)?.unsqueeze(0)?;
-        let token_type_ids = Tensor::new(token_type_ids.as_slice(), &self.device)?.unsqueeze(0)?;
+        let shape = (texts.len(), seq_len);
+        let input_ids = Tensor::from_vec(input_ids, shape, &self.device)?;
+        let attention_mask = Tensor::from_vec(attention_mask, shape, &self.device)?;
+        let token_type_ids = Tensor::from_vec(token_type_ids, shape, &self.device)?;
 
         // Run model based on type
         let embeddings = match &self.model {
@@ -236,18 +270,13 @@ impl LocalEmbedder {
             }
         };
 
-        // Mean pooling over sequence dimension
-        let sum = embeddings.sum(1)?;
-        let count = Tensor::new(&[seq_len as f32], &self.device)?.broadcast_as(sum.shape())?;
-        let mean = (sum / count)?;
+        let mean = masked_mean(&embeddings, &attention_mask)?;
 
         // L2 normalize
         let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?;
         let normalized = mean.broadcast_div(&norm)?;
 
-        // Convert to Vec<f32>
-        let result: Vec<f32> = normalized.squeeze(0)?.to_vec1()?;
-        Ok(result)
+        Ok(normalized.to_vec2()?)
     }
 
     /// Get token-level embeddings
@@ -257,6 +286,15 @@ impl LocalEmbedder {
     }
 }
 
+/// Mean over the tokens of each sequence, skipping padding.
+/// `embeddings` is [batch, seq, dim], `mask` is [batch, seq] with 1 for tokens.
+fn masked_mean(embeddings: &Tensor, mask: &Tensor) -> Result<Tensor> {
+    let mask = mask.to_dtype(embeddings.dtype())?.unsqueeze(2)?;
+    let sum = embeddings.broadcast_mul(&mask)?.sum(1)?;
+    let count = mask.sum(1)?;
+    Ok(sum.broadcast_div(&count)?)
+}
+
 /// Download model (handled automatically by hf-hub, but we keep the interface)
 pub async fn download_model() -> Result<()> {
     println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -286,3 +324,31 @@ pub async fn download_model() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masked_mean_ignores_padding() {
+        let device = Device::Cpu;
+        // Second row is the first row's two tokens plus one padding token
+        let embeddings = Tensor::new(
+            &[
+                [[1.0f32, 2.0], [3.0, 4.0], [0.0, 0.0]],
+                [[1.0, 2.0], [3.0, 4.0], [9.0, 9.0]],
+            ],
+            &device,
+        )
+        .unwrap();
+        let mask = Tensor::new(&[[1u32, 1, 0], [1, 1, 0]], &device).unwrap();
+
+        let mean: Vec<Vec<f32>> = masked_mean(&embeddings, &mask).unwrap().to_vec2().unwrap();
+        assert_eq!(mean, vec![vec![2.0, 3.0], vec![2.0, 3.0]]);
+
+        let full = Tensor::new(&[[1u32, 1, 1]], &device).unwrap();
+        let unpadded = embeddings.narrow(0, 1, 1).unwrap();
+        let mean: Vec<Vec<f32>> = masked_mean(&unpadded, &full).unwrap().to_vec2().unwrap();
+        assert_eq!(mean, vec![vec![13.0 / 3.0, 5.0]]);
+    }
+}
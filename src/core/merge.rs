@@ -0,0 +1,255 @@
+//! Result span merging
+//!
+//! Tree-sitter chunking emits a class alongside its methods, and
+//! `split_oversized_chunks` emits consecutive fragments, so one region of a
+//! file can come back as several overlapping or contiguous hits. This pass
+//! folds them into a single span per region, substitutes the enclosing
+//! symbol when several of its children match, and records which sub-ranges
+//! actually matched.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::core::search::SearchResult;
+use crate::core::store::VectorStore;
+
+/// Score bonus per additional hit folded into a span
+const MERGE_BONUS: f32 = 0.02;
+
+/// Merge overlapping/adjacent hits per file. Output is sorted by score.
+pub fn merge_results(store: &VectorStore, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut by_file: HashMap<String, Vec<SearchResult>> = HashMap::new();
+    let mut file_order: Vec<String> = Vec::new();
+    for result in results {
+        let path = result.chunk.file_path.clone();
+        if !by_file.contains_key(&path) {
+            file_order.push(path.clone());
+        }
+        by_file.entry(path).or_default().push(result);
+    }
+
+    let mut merged: Vec<SearchResult> = Vec::new();
+    for path in file_order {
+        let hits = by_file.remove(&path).unwrap_or_default();
+        let hits = substitute_parents(store, &path, hits);
+        merged.extend(merge_spans(hits));
+    }
+
+    merged.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    merged
+}
+
+/// Replace sibling hits sharing a parent symbol with the parent chunk itself
+fn substitute_parents(
+    store: &VectorStore,
+    file_path: &str,
+    hits: Vec<SearchResult>,
+) -> Vec<SearchResult> {
+    let mut by_parent: HashMap<String, usize> = HashMap::new();
+    for hit in &hits {
+        if let Some(ref parent) = hit.chunk.parent_name {
+            *by_parent.entry(parent.clone()).or_insert(0) += 1;
+        }
+    }
+    if by_parent.values().all(|&count| count < 2) {
+        return hits;
+    }
+
+    let file_chunks = store.chunks_for_file(file_path);
+    let mut remaining = hits;
+    let mut out = Vec::new();
+
+    for (parent, count) in by_parent {
+        if count < 2 {
+            continue;
+        }
+        let (children, rest): (Vec<SearchResult>, Vec<SearchResult>) = remaining
+            .into_iter()
+            .partition(|hit| hit.chunk.parent_name.as_deref() == Some(parent.as_str()));
+        remaining = rest;
+
+        let start = children
+            .iter()
+            .map(|c| c.chunk.start_line)
+            .min()
+            .unwrap_or(0);
+        let end = children.iter().map(|c| c.chunk.end_line).max().unwrap_or(0);
+        let enclosing = file_chunks
+            .iter()
+            .filter(|c| {
+                c.symbol_name.as_deref() == Some(parent.as_str())
+                    && c.start_line <= start
+                    && c.end_line >= end
+            })
+            .min_by_key(|c| c.end_line - c.start_line);
+
+        match enclosing {
+            Some(parent_chunk) => {
+                let mut result = fold(children);
+                result.chunk = (*parent_chunk).clone();
+                out.push(result);
+            }
+            None => out.extend(children),
+        }
+    }
+
+    out.extend(remaining);
+    out
+}
+
+/// Sweep hits by start line, merging any that overlap or touch
+fn merge_spans(mut hits: Vec<SearchResult>) -> Vec<SearchResult> {
+    hits.sort_by_key(|h| (h.chunk.start_line, std::cmp::Reverse(h.chunk.end_line)));
+
+    let mut spans: Vec<Vec<SearchResult>> = Vec::new();
+    let mut current_end = 0;
+    for hit in hits {
+        match spans.last_mut() {
+            Some(group) if hit.chunk.start_line <= current_end + 1 => {
+                current_end = current_end.max(hit.chunk.end_line);
+                group.push(hit);
+            }
+            _ => {
+                current_end = hit.chunk.end_line;
+                spans.push(vec![hit]);
+            }
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|group| {
+            if group.len() == 1 {
+                return group.into_iter().next().unwrap();
+            }
+            let content = stitch_content(&group);
+            let start = group.iter().map(|h| h.chunk.start_line).min().unwrap_or(0);
+            let end = group.iter().map(|h| h.chunk.end_line).max().unwrap_or(0);
+            let mut result = fold(group);
+            result.chunk.start_line = start;
+            result.chunk.end_line = end;
+            result.chunk.content = content;
+            result
+        })
+        .collect()
+}
+
+/// Combine hits into one result: the best hit's metadata, max scores plus a
+/// small bonus per extra hit, and the union of matched ranges.
+fn fold(mut hits: Vec<SearchResult>) -> SearchResult {
+    hits.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let extra = hits.len().saturating_sub(1) as f32;
+
+    let mut ranges: Vec<(usize, usize)> = hits
+        .iter()
+        .flat_map(|h| h.matched_ranges.iter().copied())
+        .collect();
+    let bm25_score = hits.iter().map(|h| h.bm25_score).fold(0.0, f32::max);
+    let vector_score = hits.iter().map(|h| h.vector_score).fold(0.0, f32::max);
+    let colbert_score = hits.iter().filter_map(|h| h.colbert_score).reduce(f32::max);
+
+    let mut best = hits.swap_remove(0);
+    best.score += MERGE_BONUS * extra;
+    best.bm25_score = bm25_score;
+    best.vector_score = vector_score;
+    best.colbert_score = colbert_score;
+
+    ranges.sort_unstable();
+    best.matched_ranges = coalesce(ranges);
+    best
+}
+
+/// Merge sorted, overlapping or touching line ranges
+fn coalesce(ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut out: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match out.last_mut() {
+            Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+            _ => out.push((start, end)),
+        }
+    }
+    out
+}
+
+/// Rebuild span content line by line from the stored chunk contents.
+/// Wider chunks win so that lines keep their full indentation.
+fn stitch_content(group: &[SearchResult]) -> String {
+    let mut ordered: Vec<&SearchResult> = group.iter().collect();
+    ordered.sort_by_key(|h| std::cmp::Reverse(h.chunk.end_line - h.chunk.start_line));
+
+    let mut lines: BTreeMap<usize, &str> = BTreeMap::new();
+    for hit in ordered {
+        for (offset, line) in hit.chunk.content.lines().enumerate() {
+            lines.entry(hit.chunk.start_line + offset).or_insert(line);
+        }
+    }
+
+    lines.into_values().collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+
+    fn hit(start: usize, end: usize, score: f32, parent: Option<&str>) -> SearchResult {
+        let content = (start..=end)
+            .map(|n| format!("line {}", n))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let chunk = FileChunk {
+            parent_name: parent.map(|p| p.to_string()),
+            ..FileChunk::test(&format!("{}-{}", start, end), "/src/lib.rs", (start, end))
+                .with_content(&content)
+        };
+        SearchResult::test(chunk, score)
+    }
+
+    #[test]
+    fn test_merge_adjacent_and_overlapping() {
+        let merged = merge_spans(vec![
+            hit(1, 10, 0.5, None),
+            hit(11, 20, 0.8, None),
+            hit(15, 25, 0.6, None),
+            hit(40, 50, 0.7, None),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        let span = merged.iter().find(|r| r.chunk.start_line == 1).unwrap();
+        assert_eq!(span.chunk.end_line, 25);
+        assert_eq!(span.matched_ranges, vec![(1, 25)]);
+        assert_eq!(span.chunk.content.lines().count(), 25);
+        assert!((span.score - (0.8 + 2.0 * MERGE_BONUS)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_coalesce_keeps_gaps() {
+        let ranges = coalesce(vec![(1, 5), (3, 8), (20, 30)]);
+        assert_eq!(ranges, vec![(1, 8), (20, 30)]);
+    }
+
+    #[test]
+    fn test_substitute_parent_symbol() {
+        let class = FileChunk::test("class", "/src/lib.rs", (1, 60)).with_symbol("Server");
+        let store = VectorStore::with_test_chunks(vec![class]);
+
+        let merged = merge_results(
+            &store,
+            vec![
+                hit(10, 20, 0.7, Some("Server")),
+                hit(40, 50, 0.9, Some("Server")),
+            ],
+        );
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].chunk.id, "class");
+        assert_eq!(merged[0].matched_ranges, vec![(10, 20), (40, 50)]);
+    }
+}
@@ -1,18 +1,41 @@
+pub mod calibration;
 pub mod chunker;
 pub mod codemap;
 pub mod config;
+pub mod cross_encoder;
 pub mod daemon;
+pub mod diversify;
+pub mod dupes;
 pub mod embeddings;
+pub mod eval;
+pub mod explain;
 pub mod fast_indexer;
+pub mod feedback;
+pub mod filter;
+pub mod fusion_layer;
 pub mod git;
 pub mod graph;
+pub mod grep;
 pub mod hybrid_embedder;
+pub mod intent;
+pub mod labels;
+pub mod levels;
 pub mod local_embeddings;
+pub mod merge;
 pub mod nomic_bert;
 pub mod parser;
+pub mod query;
 pub mod reranker;
+pub mod result_cache;
 pub mod scanner;
 pub mod search;
+pub mod session;
+pub mod similar;
+pub mod snippet;
+pub mod stacktrace;
 pub mod store;
+pub mod topics;
 pub mod treesitter_chunker;
+pub mod trigram;
+pub mod triples;
 pub mod vector_index;
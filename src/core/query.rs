@@ -0,0 +1,376 @@
+//! Inline query language
+//!
+//! Splits a query like `retry logic lang:rust path:src/core kind:function -path:tests`
+//! into free text (embedded and BM25-scored as before) and structured filters:
+//!
+//! - `lang:` / `language:`   chunk language (`rs`, `py`, ... are accepted)
+//! - `kind:` / `type:`       chunk type (`function`, `method`, `class`, ...)
+//! - `symbol:`               symbol name contains
+//! - `parent:`               parent symbol name contains
+//! - `path:`                 path prefix or glob
+//! - `ext:`                  file extension
+//! - `since:`                files modified in git since a date (`2w`, `2024-01-01`, `yesterday`)
+//!
+//! A leading `-` negates an operator. Values may be quoted (`symbol:"new client"`),
+//! and quoting a whole token keeps it as text (`"note:important"`). Tokens that
+//! look like paths (`std::io`) or URLs are never treated as operators, and
+//! neither are unknown keys or operators without a value, so pasted error
+//! text (`kind: NotFound`, `config:unexpected EOF`) stays searchable. Only a
+//! key one typo away from an operator (`lnag:rust`) is an error.
+
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::core::filter::SearchFilter;
+use crate::core::git::GitRepo;
+use crate::core::treesitter_chunker::detect_language;
+
+const OPERATORS: &[&str] = &[
+    "lang", "language", "kind", "type", "symbol", "parent", "path", "ext", "since",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedQuery {
+    /// Free text with all operators removed
+    pub text: String,
+    pub languages: Vec<String>,
+    pub exclude_languages: Vec<String>,
+    pub kinds: Vec<String>,
+    pub exclude_kinds: Vec<String>,
+    pub symbols: Vec<String>,
+    pub exclude_symbols: Vec<String>,
+    pub parents: Vec<String>,
+    pub exclude_parents: Vec<String>,
+    pub paths: Vec<String>,
+    pub exclude_paths: Vec<String>,
+    pub extensions: Vec<String>,
+    pub since: Option<String>,
+}
+
+impl ParsedQuery {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut query = ParsedQuery::default();
+        let mut text: Vec<String> = Vec::new();
+
+        for token in tokenize(input) {
+            if token.quoted {
+                text.push(token.text);
+                continue;
+            }
+
+            let Some((negated, key, value)) = split_operator(&token.text) else {
+                text.push(token.text);
+                continue;
+            };
+
+            if !OPERATORS.contains(&key) {
+                if let Some(operator) = OPERATORS.iter().find(|op| one_typo_apart(key, op)) {
+                    if !value.is_empty() {
+                        bail!(
+                            "Unknown search operator '{}:' (did you mean '{}:'?). Quote the term to search for it literally.",
+                            key,
+                            operator
+                        );
+                    }
+                }
+                text.push(token.text);
+                continue;
+            }
+            // A trailing colon in prose ("type: ...") is not an operator
+            if value.is_empty() {
+                text.push(token.text);
+                continue;
+            }
+
+            let value = value.to_string();
+            match (key, negated) {
+                ("lang" | "language", false) => query.languages.push(normalize_language(&value)),
+                ("lang" | "language", true) => {
+                    query.exclude_languages.push(normalize_language(&value))
+                }
+                ("kind" | "type", false) => query.kinds.push(normalize_kind(&value)),
+                ("kind" | "type", true) => query.exclude_kinds.push(normalize_kind(&value)),
+                ("symbol", false) => query.symbols.push(value),
+                ("symbol", true) => query.exclude_symbols.push(value),
+                ("parent", false) => query.parents.push(value),
+                ("parent", true) => query.exclude_parents.push(value),
+                ("path", false) => query.paths.push(value),
+                ("path", true) => query.exclude_paths.push(value),
+                ("ext", false) => query
+                    .extensions
+                    .push(value.trim_start_matches('.').to_lowercase()),
+                ("since", false) => query.since = Some(value),
+                (key, true) => bail!("Search operator '{}:' cannot be negated", key),
+                _ => unreachable!(),
+            }
+        }
+
+        query.text = text.join(" ");
+        Ok(query)
+    }
+
+    /// Add this query's filters to `filter`. `repo_path` locates the git
+    /// repository for `since:`.
+    pub fn apply(&self, filter: SearchFilter, repo_path: &str) -> Result<SearchFilter> {
+        let globs: Vec<String> = self.paths.iter().flat_map(|p| path_globs(p)).collect();
+        let excludes: Vec<String> = self
+            .exclude_paths
+            .iter()
+            .flat_map(|p| path_globs(p))
+            .collect();
+
+        // A glob set of its own, so `path:` narrows `--glob` instead of widening it
+        let mut filter = filter
+            .with_globs(&globs, &excludes)?
+            .with_file_types(&self.extensions)
+            .with_languages(&self.languages, &self.exclude_languages)
+            .with_kinds(&self.kinds, &self.exclude_kinds)
+            .with_symbols(&self.symbols, &self.exclude_symbols)
+            .with_parents(&self.parents, &self.exclude_parents);
+
+        if let Some(ref since) = self.since {
+            let repo = GitRepo::open(repo_path)?;
+            let root = PathBuf::from(repo.root_path());
+            let files: HashSet<PathBuf> = repo
+                .modified_since(&git_date(since))?
+                .into_iter()
+                .map(|f| root.join(f))
+                .collect();
+            filter = filter.with_files(files);
+        }
+
+        Ok(filter)
+    }
+}
+
+struct Token {
+    text: String,
+    /// The whole token was quoted, so it is always free text
+    quoted: bool,
+}
+
+/// Split on whitespace, keeping quoted sections together
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut starts_quoted = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                if current.is_empty() && !in_quotes {
+                    starts_quoted = true;
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(Token {
+                        text: std::mem::take(&mut current),
+                        quoted: starts_quoted,
+                    });
+                }
+                starts_quoted = false;
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(Token {
+            text: current,
+            quoted: starts_quoted,
+        });
+    }
+    tokens
+}
+
+/// Whether `a` turns into `b` with one insertion, deletion, substitution or
+/// swap of adjacent characters
+fn one_typo_apart(a: &str, b: &str) -> bool {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    match (a.len(), b.len()) {
+        (0, 0) => false,
+        (1, 0) | (0, 1) => true,
+        (m, n) if m == n => {
+            a[1..] == b[1..] || (m >= 2 && a[0] == b[1] && a[1] == b[0] && a[2..] == b[2..])
+        }
+        (m, n) if m == n + 1 => a[1..] == *b,
+        (m, n) if m + 1 == n => *a == b[1..],
+        _ => false,
+    }
+}
+
+/// `[-]key:value` with a lowercase identifier key, excluding `a::b` paths and URLs
+fn split_operator(token: &str) -> Option<(bool, &str, &str)> {
+    let (negated, rest) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let (key, value) = rest.split_once(':')?;
+
+    if key.is_empty()
+        || !key.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+        || value.starts_with(':')
+        || value.starts_with("//")
+    {
+        return None;
+    }
+    Some((negated, key, value))
+}
+
+/// Language name for a `lang:` value, e.g. `ts` -> `typescript`
+pub fn normalize_language(value: &str) -> String {
+    let value = value.to_lowercase();
+    detect_language(&format!("file.{}", value))
+        .map(|l| l.to_string())
+        .unwrap_or(value)
+}
+
+/// Chunk type for a `type:` value, e.g. `fn` -> `function`
+pub fn normalize_kind(value: &str) -> String {
+    match value.to_lowercase().as_str() {
+        "fn" | "func" | "fun" | "def" => "function".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Turn a `path:` value into override globs: globs pass through, plain
+/// paths match as a prefix (anchored if they contain a slash, anywhere otherwise)
+fn path_globs(value: &str) -> Vec<String> {
+    if value.contains(['*', '?', '[', '{']) {
+        return vec![value.to_string()];
+    }
+    let trimmed = value.trim_end_matches('/');
+    if trimmed.contains('/') {
+        vec![trimmed.to_string(), format!("{}/**", trimmed)]
+    } else {
+        vec![format!("**/{}", trimmed), format!("**/{}/**", trimmed)]
+    }
+}
+
+/// Expand shorthands like `2w` or `3d` into a date git understands
+fn git_date(since: &str) -> String {
+    let (num, unit) = since.split_at(since.len().saturating_sub(1));
+    if let Ok(n) = num.parse::<u32>() {
+        let unit = match unit {
+            "h" => Some("hours"),
+            "d" => Some("days"),
+            "w" => Some("weeks"),
+            "m" => Some("months"),
+            "y" => Some("years"),
+            _ => None,
+        };
+        if let Some(unit) = unit {
+            return format!("{} {} ago", n, unit);
+        }
+    }
+    since.to_string()
+}
+
+/// Directory used to locate the repository for `since:`
+pub fn repo_path_for(path: Option<&str>) -> String {
+    match path {
+        Some(p) if Path::new(p).is_file() => Path::new(p)
+            .parent()
+            .map(|d| d.to_string_lossy().to_string())
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| ".".to_string()),
+        Some(p) => p.to_string(),
+        None => ".".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators_and_text() {
+        let q =
+            ParsedQuery::parse("retry logic lang:rs path:src/core kind:fn -path:tests").unwrap();
+        assert_eq!(q.text, "retry logic");
+        assert_eq!(q.languages, vec!["rust"]);
+        assert_eq!(q.kinds, vec!["function"]);
+        assert_eq!(q.paths, vec!["src/core"]);
+        assert_eq!(q.exclude_paths, vec!["tests"]);
+    }
+
+    #[test]
+    fn test_paths_urls_and_quotes_are_text() {
+        let q =
+            ParsedQuery::parse(r#"std::io::Error https://example.com "note:this" todo:"#).unwrap();
+        assert_eq!(q.text, "std::io::Error https://example.com note:this todo:");
+        assert!(q.languages.is_empty() && q.paths.is_empty());
+
+        let q = ParsedQuery::parse(r#"client symbol:"new client""#).unwrap();
+        assert_eq!(q.symbols, vec!["new client"]);
+    }
+
+    #[test]
+    fn test_path_operator_narrows_cli_globs() {
+        let root = std::env::current_dir()
+            .unwrap()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let cli = SearchFilter::new()
+            .with_globs(&["*.rs".to_string()], &[])
+            .unwrap();
+        let filter = ParsedQuery::parse("store path:src/core")
+            .unwrap()
+            .apply(cli, &root)
+            .unwrap();
+
+        assert!(filter.matches(&format!("{}/src/core/store.rs", root)));
+        assert!(!filter.matches(&format!("{}/src/main.rs", root)));
+        assert!(!filter.matches(&format!("{}/src/core/notes.md", root)));
+    }
+
+    #[test]
+    fn test_operator_typos_error() {
+        let err = ParsedQuery::parse("retry lnag:rust")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unknown search operator 'lnag:' (did you mean 'lang:'?)"));
+        assert!(ParsedQuery::parse("retry symbl:Client").is_err());
+        assert!(ParsedQuery::parse("retry paths:src").is_err());
+        assert!(ParsedQuery::parse("retry -since:2w").is_err());
+    }
+
+    #[test]
+    fn test_pasted_error_text_is_text() {
+        for input in [
+            "Os { code: 2, kind: NotFound, .. }",
+            "failed to read config:unexpected EOF",
+            "retry lang:",
+            "error note:see-docs",
+        ] {
+            let q = ParsedQuery::parse(input).unwrap();
+            assert_eq!(q.text, input);
+            assert!(q.kinds.is_empty() && q.languages.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_one_typo_apart() {
+        assert!(one_typo_apart("lnag", "lang"));
+        assert!(one_typo_apart("kinds", "kind"));
+        assert!(one_typo_apart("pth", "path"));
+        assert!(one_typo_apart("typo", "type"));
+        assert!(!one_typo_apart("lang", "lang"));
+        assert!(!one_typo_apart("config", "kind"));
+        assert!(!one_typo_apart("code", "kind"));
+    }
+
+    #[test]
+    fn test_git_date_shorthand() {
+        assert_eq!(git_date("2w"), "2 weeks ago");
+        assert_eq!(git_date("2024-01-01"), "2024-01-01");
+        assert_eq!(git_date("yesterday"), "yesterday");
+    }
+}
@@ -1,50 +1,228 @@
 use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crate::core::config::Config;
+use crate::core::cross_encoder::CrossEncoder;
 use crate::core::search::SearchResult;
 
-#[derive(Debug, Serialize)]
-struct RerankerRequest {
-    model: String,
-    query: String,
-    documents: Vec<String>,
-    top_n: usize,
+/// Consecutive failures before an endpoint is skipped
+const BREAKER_THRESHOLD: u32 = 3;
+
+/// How long a tripped endpoint is skipped before it is retried
+const BREAKER_COOLDOWN_SECS: i64 = 60;
+
+/// Request/response shape spoken by a rerank endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RerankFormat {
+    /// `{model, query, documents, top_n}` -> `{results: [{index, relevance_score}]}`
+    #[default]
+    Jina,
+    /// Same shape as Jina, served by api.cohere.ai
+    Cohere,
+    /// text-embeddings-inference: `{query, texts}` -> `[{index, score}]`
+    Tei,
 }
 
-#[derive(Debug, Deserialize)]
-struct RerankerResponse {
-    results: Vec<RerankerResult>,
+impl RerankFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "jina" => Some(Self::Jina),
+            "cohere" => Some(Self::Cohere),
+            "tei" => Some(Self::Tei),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct RerankerResult {
-    index: usize,
-    relevance_score: f32,
+/// A remote (or local HTTP) rerank endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankerEndpoint {
+    pub url: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub format: RerankFormat,
+    /// Literal API key
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Header carrying the key; `Authorization` values get a `Bearer ` prefix
+    #[serde(default = "default_auth_header")]
+    pub auth_header: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
-#[derive(Debug, Serialize)]
-struct JinaRerankerRequest {
-    model: String,
-    query: String,
-    documents: Vec<String>,
-    top_n: usize,
+fn default_auth_header() -> String {
+    "Authorization".to_string()
 }
 
-#[derive(Debug, Deserialize)]
-struct JinaRerankerResponse {
-    results: Vec<JinaRerankerResult>,
+fn default_timeout_secs() -> u64 {
+    10
 }
 
-#[derive(Debug, Deserialize)]
-struct JinaRerankerResult {
-    index: usize,
-    relevance_score: f32,
+impl RerankerEndpoint {
+    pub fn new(url: impl Into<String>, format: RerankFormat) -> Self {
+        Self {
+            url: url.into(),
+            model: None,
+            format,
+            api_key: None,
+            api_key_env: None,
+            auth_header: default_auth_header(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    pub fn with_api_key_env(mut self, var: impl Into<String>) -> Self {
+        self.api_key_env = Some(var.into());
+        self
+    }
+
+    /// Jina preset, enabled when `JINA_API_KEY` is set
+    pub fn jina() -> Self {
+        Self::new("https://api.jina.ai/v1/rerank", RerankFormat::Jina)
+            .with_model("jina-reranker-v2-base-multilingual")
+            .with_api_key_env("JINA_API_KEY")
+    }
+
+    /// Cohere preset, enabled when `COHERE_API_KEY` is set
+    pub fn cohere() -> Self {
+        Self::new("https://api.cohere.ai/v1/rerank", RerankFormat::Cohere)
+            .with_model("rerank-english-v3.0")
+            .with_api_key_env("COHERE_API_KEY")
+    }
+
+    pub fn get_api_key(&self) -> Option<String> {
+        self.api_key.clone().or_else(|| {
+            self.api_key_env
+                .as_ref()
+                .and_then(|var| std::env::var(var).ok())
+        })
+    }
+
+    fn request_body(&self, query: &str, documents: Vec<String>, top_n: usize) -> serde_json::Value {
+        match self.format {
+            RerankFormat::Jina | RerankFormat::Cohere => {
+                let mut body = serde_json::json!({
+                    "query": query,
+                    "documents": documents,
+                    "top_n": top_n,
+                });
+                if let Some(ref model) = self.model {
+                    body["model"] = serde_json::json!(model);
+                }
+                body
+            }
+            RerankFormat::Tei => serde_json::json!({
+                "query": query,
+                "texts": documents,
+                "truncate": true,
+            }),
+        }
+    }
+}
+
+/// Extract (index, score) pairs from any of the supported response shapes:
+/// a bare array, or an object with `results` / `data`, scored by
+/// `relevance_score` or `score`.
+fn parse_rerank_response(value: &serde_json::Value) -> Result<Vec<(usize, f32)>> {
+    let items = value
+        .as_array()
+        .or_else(|| value["results"].as_array())
+        .or_else(|| value["data"].as_array())
+        .ok_or_else(|| anyhow!("Unrecognized rerank response"))?;
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let index = item["index"].as_u64()? as usize;
+            let score = item["relevance_score"]
+                .as_f64()
+                .or_else(|| item["score"].as_f64())?;
+            Some((index, score as f32))
+        })
+        .collect())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BreakerEntry {
+    failures: u32,
+    open_until: Option<i64>,
+}
+
+/// Per-endpoint circuit breaker, persisted so short-lived CLI runs share it
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CircuitBreaker {
+    endpoints: HashMap<String, BreakerEntry>,
+}
+
+impl CircuitBreaker {
+    fn path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("reranker_breaker.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        if let (Ok(path), Ok(content)) = (Self::path(), serde_json::to_string(self)) {
+            let _ = fs::write(path, content);
+        }
+    }
+
+    fn is_open(&self, url: &str) -> bool {
+        self.endpoints
+            .get(url)
+            .and_then(|entry| entry.open_until)
+            .map(|until| chrono::Utc::now().timestamp() < until)
+            .unwrap_or(false)
+    }
+
+    fn record_success(&mut self, url: &str) {
+        if self.endpoints.remove(url).is_some() {
+            self.save();
+        }
+    }
+
+    fn record_failure(&mut self, url: &str) {
+        let entry = self.endpoints.entry(url.to_string()).or_default();
+        entry.failures += 1;
+        if entry.failures >= BREAKER_THRESHOLD {
+            entry.open_until = Some(chrono::Utc::now().timestamp() + BREAKER_COOLDOWN_SECS);
+            entry.failures = 0;
+        }
+        self.save();
+    }
 }
 
 pub struct Reranker {
     config: Config,
     client: reqwest::Client,
+    /// Local cross-encoder by model id, loaded on first use and kept for
+    /// later queries; `None` once loading it failed, so it isn't retried
+    local: Mutex<Option<(String, Option<Arc<CrossEncoder>>)>>,
+    /// Set once the "nothing to rerank with" note has been printed
+    noted_skip: AtomicBool,
 }
 
 impl Reranker {
@@ -52,7 +230,23 @@ impl Reranker {
         Self {
             config,
             client: reqwest::Client::new(),
+            local: Mutex::new(None),
+            noted_skip: AtomicBool::new(false),
+        }
+    }
+
+    /// Endpoints to try in order: the configured one, then env-based presets
+    fn endpoints(&self) -> Vec<RerankerEndpoint> {
+        let mut endpoints = Vec::new();
+        if let Some(ref endpoint) = self.config.reranker {
+            endpoints.push(endpoint.clone());
         }
+        for preset in [RerankerEndpoint::jina(), RerankerEndpoint::cohere()] {
+            if preset.get_api_key().is_some() {
+                endpoints.push(preset);
+            }
+        }
+        endpoints
     }
 
     pub async fn rerank(
@@ -65,69 +259,91 @@ impl Reranker {
             return Ok(results);
         }
 
-        // Try Jina reranker first (free tier available)
-        match self.rerank_jina(query, &results, top_n).await {
-            Ok(reranked) => return Ok(reranked),
-            Err(_) => {
-                // Fall back to Cohere if available
-                if let Some(api_key) = std::env::var("COHERE_API_KEY").ok() {
-                    match self.rerank_cohere(query, &results, top_n, &api_key).await {
-                        Ok(reranked) => return Ok(reranked),
-                        Err(_) => {}
-                    }
+        let endpoints = self.endpoints();
+        let mut breaker = CircuitBreaker::load();
+        for endpoint in &endpoints {
+            if breaker.is_open(&endpoint.url) {
+                continue;
+            }
+            match self.rerank_remote(endpoint, query, &results, top_n).await {
+                Ok(reranked) => {
+                    breaker.record_success(&endpoint.url);
+                    return Ok(reranked);
+                }
+                Err(_) => breaker.record_failure(&endpoint.url),
+            }
+        }
+
+        // Fall back to the local cross-encoder (no network once cached)
+        if let Some(model_id) = self.config.get_rerank_model() {
+            if let Some(encoder) = self.local_encoder(&model_id).await {
+                match self.rerank_local(encoder, query, &results, top_n).await {
+                    Ok(reranked) => return Ok(reranked),
+                    Err(e) => eprintln!("Local reranking failed: {}", e),
                 }
             }
+        } else if endpoints.is_empty()
+            && self.config.rerank_model.is_none()
+            && !self.noted_skip.swap(true, Ordering::Relaxed)
+        {
+            // Unset rather than turned off: say why results come back as is
+            eprintln!(
+                "Reranking skipped: no reranker configured. Enable it with \
+                 `sgrep config --rerank-model default` (or silence this with `--rerank-model none`)"
+            );
         }
 
         // If no reranker available, return original results
         Ok(results.into_iter().take(top_n).collect())
     }
 
-    async fn rerank_jina(
+    async fn rerank_remote(
         &self,
+        endpoint: &RerankerEndpoint,
         query: &str,
         results: &[SearchResult],
         top_n: usize,
     ) -> Result<Vec<SearchResult>> {
-        let api_key = std::env::var("JINA_API_KEY").context("JINA_API_KEY not set")?;
-
         let documents: Vec<String> = results.iter().map(|r| r.chunk.content.clone()).collect();
 
-        let request = JinaRerankerRequest {
-            model: "jina-reranker-v2-base-multilingual".to_string(),
-            query: query.to_string(),
-            documents,
-            top_n,
-        };
-
-        let response = self
+        let mut request = self
             .client
-            .post("https://api.jina.ai/v1/rerank")
-            .header("Authorization", format!("Bearer {}", api_key))
+            .post(&endpoint.url)
+            .timeout(Duration::from_secs(endpoint.timeout_secs))
             .header("Content-Type", "application/json")
-            .json(&request)
+            .json(&endpoint.request_body(query, documents, top_n));
+
+        if let Some(api_key) = endpoint.get_api_key() {
+            let value = if endpoint.auth_header.eq_ignore_ascii_case("authorization") {
+                format!("Bearer {}", api_key)
+            } else {
+                api_key
+            };
+            request = request.header(endpoint.auth_header.as_str(), value);
+        }
+
+        let response = request
             .send()
             .await
-            .context("Failed to send rerank request")?;
+            .with_context(|| format!("Failed to send rerank request to {}", endpoint.url))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Jina API error {}: {}", status, text));
+            return Err(anyhow!("Rerank API error {}: {}", status, text));
         }
 
-        let result: JinaRerankerResponse = response
+        let value: serde_json::Value = response
             .json()
             .await
             .context("Failed to parse rerank response")?;
 
-        let mut reranked: Vec<SearchResult> = result
-            .results
+        let mut reranked: Vec<SearchResult> = parse_rerank_response(&value)?
             .into_iter()
-            .filter_map(|r| {
-                results.get(r.index).map(|original| {
+            .filter_map(|(index, score)| {
+                results.get(index).map(|original| {
                     let mut new_result = original.clone();
-                    new_result.score = r.relevance_score;
+                    new_result.score = score;
                     new_result
                 })
             })
@@ -138,72 +354,83 @@ impl Reranker {
                 .partial_cmp(&a.score)
                 .unwrap_or(std::cmp::Ordering::Equal)
         });
+        reranked.truncate(top_n);
         Ok(reranked)
     }
 
-    async fn rerank_cohere(
+    /// The cached cross-encoder for `model_id`, loading it off the async
+    /// runtime on first use. A load failure is reported once and remembered.
+    async fn local_encoder(&self, model_id: &str) -> Option<Arc<CrossEncoder>> {
+        if let Ok(local) = self.local.lock() {
+            if let Some((id, encoder)) = local.as_ref() {
+                if id == model_id {
+                    return encoder.clone();
+                }
+            }
+        }
+
+        let id = model_id.to_string();
+        let loaded = tokio::task::spawn_blocking(move || CrossEncoder::load(&id))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
+        let encoder = match loaded {
+            Ok(encoder) => Some(Arc::new(encoder)),
+            Err(e) => {
+                eprintln!("Local reranker {} unavailable: {}", model_id, e);
+                None
+            }
+        };
+        if let Ok(mut local) = self.local.lock() {
+            *local = Some((model_id.to_string(), encoder.clone()));
+        }
+        encoder
+    }
+
+    async fn rerank_local(
         &self,
+        encoder: Arc<CrossEncoder>,
         query: &str,
         results: &[SearchResult],
         top_n: usize,
-        api_key: &str,
     ) -> Result<Vec<SearchResult>> {
-        let documents: Vec<String> = results.iter().map(|r| r.chunk.content.clone()).collect();
-
-        let request = serde_json::json!({
-            "model": "rerank-english-v3.0",
-            "query": query,
-            "documents": documents,
-            "top_n": top_n,
-        });
-
-        let response = self
-            .client
-            .post("https://api.cohere.ai/v1/rerank")
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send Cohere rerank request")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Cohere API error {}: {}", status, text));
-        }
-
-        let result: serde_json::Value = response
-            .json()
-            .await
-            .context("Failed to parse Cohere response")?;
-
-        let reranked_results = result["results"]
-            .as_array()
-            .ok_or_else(|| anyhow!("Invalid Cohere response"))?;
-
-        let mut reranked: Vec<SearchResult> = reranked_results
+        // Only the head of the candidate list is scored; the tail keeps its order
+        let head = self.config.get_rerank_top_n().max(top_n).min(results.len());
+        let documents: Vec<String> = results[..head]
             .iter()
-            .filter_map(|r| {
-                let index = r["index"].as_u64()? as usize;
-                let score = r["relevance_score"].as_f64()? as f32;
-                results.get(index).map(|original| {
-                    let mut new_result = original.clone();
-                    new_result.score = score;
-                    new_result
-                })
-            })
+            .map(|r| r.chunk.content.clone())
             .collect();
-
-        reranked.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        Ok(reranked)
+        let query = query.to_string();
+        let scores =
+            tokio::task::spawn_blocking(move || encoder.score(&query, &documents)).await??;
+        Ok(apply_scores(results, scores, top_n))
     }
 }
 
+/// Order the first `scores.len()` results by their cross-encoder score,
+/// keep the rest in retrieval order after them and cut to `top_n`
+fn apply_scores(results: &[SearchResult], scores: Vec<f32>, top_n: usize) -> Vec<SearchResult> {
+    let head = scores.len().min(results.len());
+    let mut reranked: Vec<SearchResult> = results[..head]
+        .iter()
+        .zip(scores)
+        .map(|(original, score)| {
+            let mut new_result = original.clone();
+            new_result.score = score;
+            new_result
+        })
+        .collect();
+
+    reranked.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    reranked.extend(results[head..].iter().cloned());
+    reranked.truncate(top_n);
+    reranked
+}
+
 // Simple local reranking using keyword overlap
 pub fn simple_rerank(query: &str, mut results: Vec<SearchResult>) -> Vec<SearchResult> {
     let query_terms: std::collections::HashSet<String> = query
@@ -232,3 +459,94 @@ pub fn simple_rerank(query: &str, mut results: Vec<SearchResult>) -> Vec<SearchR
     });
     results
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn result(content: &str) -> SearchResult {
+        let chunk = FileChunk::test(content, "/test/lib.rs", (1, 1)).with_content(content);
+        SearchResult::test(chunk, 0.5)
+    }
+
+    /// Serve a single HTTP response with the given JSON body
+    async fn mock_server(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 16384];
+            let _ = socket.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{}/rerank", addr)
+    }
+
+    #[test]
+    fn test_parse_rerank_response_shapes() {
+        let jina = serde_json::json!({"results": [{"index": 1, "relevance_score": 0.9}]});
+        let tei = serde_json::json!([{"index": 0, "score": 0.4}]);
+        let data = serde_json::json!({"data": [{"index": 2, "score": 0.7}]});
+
+        assert_eq!(parse_rerank_response(&jina).unwrap(), vec![(1, 0.9)]);
+        assert_eq!(parse_rerank_response(&tei).unwrap(), vec![(0, 0.4)]);
+        assert_eq!(parse_rerank_response(&data).unwrap(), vec![(2, 0.7)]);
+        assert!(parse_rerank_response(&serde_json::json!({"error": "x"})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rerank_remote_mock_server() {
+        let url = mock_server(
+            r#"{"results":[{"index":2,"relevance_score":0.95},{"index":0,"relevance_score":0.10}]}"#,
+        )
+        .await;
+        let endpoint = RerankerEndpoint::new(url, RerankFormat::Jina);
+        let reranker = Reranker::new(Config::default());
+
+        let results = vec![result("a"), result("b"), result("c")];
+        let reranked = reranker
+            .rerank_remote(&endpoint, "query", &results, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(reranked.len(), 2);
+        assert_eq!(reranked[0].chunk.content, "c");
+        assert_eq!(reranked[1].chunk.content, "a");
+    }
+
+    #[test]
+    fn test_apply_scores_orders_head_and_keeps_tail() {
+        let results = vec![result("a"), result("b"), result("c"), result("d")];
+
+        // Only a and b were scored; c and d follow in retrieval order
+        let reranked = apply_scores(&results, vec![0.2, 0.9], 3);
+        let order: Vec<&str> = reranked.iter().map(|r| r.chunk.content.as_str()).collect();
+        assert_eq!(order, vec!["b", "a", "c"]);
+        assert!((reranked[0].score - 0.9).abs() < 1e-6);
+        assert!((reranked[2].score - 0.5).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_remote_tei_format() {
+        let url = mock_server(r#"[{"index":1,"score":0.8},{"index":0,"score":0.2}]"#).await;
+        let endpoint = RerankerEndpoint::new(url, RerankFormat::Tei);
+        let reranker = Reranker::new(Config::default());
+
+        let results = vec![result("a"), result("b")];
+        let reranked = reranker
+            .rerank_remote(&endpoint, "query", &results, 10)
+            .await
+            .unwrap();
+
+        assert_eq!(reranked[0].chunk.content, "b");
+        assert!((reranked[0].score - 0.8).abs() < 1e-6);
+    }
+}
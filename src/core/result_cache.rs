@@ -0,0 +1,443 @@
+//! Ranked result lists cached for pagination
+//!
+//! Each entry holds the full ranked list for one query and set of search
+//! options (the cache key) at one store generation, so later pages are a
+//! slice instead of a new search. When a page reaches past the cached list
+//! the search is re-run with a larger pool and the entry replaced.
+//!
+//! A cursor is `<key>:<offset>`. It is only valid for the query that issued
+//! it: callers send the same query along with the cursor. The cursor of a
+//! page also serves as its query id for relevance feedback: the printed
+//! page is recorded with `record_shown`, kept far longer than the ranked
+//! lists, and read back by `shown`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::core::config::Config;
+use crate::core::search::{CandidateSource, SearchResult};
+use crate::core::session::SearchTrace;
+use crate::core::store::VectorStore;
+
+/// Entries older than this are removed when a new entry is written
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+/// Most entries kept on disk
+const MAX_ENTRIES: usize = 64;
+/// Shown pages stay rateable this long
+const SHOWN_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Most shown queries kept on disk
+const SHOWN_MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedHit {
+    chunk_id: String,
+    file_path: String,
+    /// Lines and content as shown, which for a merged span (see
+    /// `core::merge`) differ from the stored chunk
+    start_line: usize,
+    end_line: usize,
+    content: String,
+    score: f32,
+    bm25_score: f32,
+    vector_score: f32,
+    colbert_score: Option<f32>,
+    matched_ranges: Vec<(usize, usize)>,
+    /// Rank and score after retrieval, from the search trace
+    retrieval: Option<(usize, f32)>,
+    /// Rank and score after reranking, from the search trace
+    reranked: Option<(usize, f32)>,
+    source: CandidateSource,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    generation: u64,
+    /// Query text and embedding mode the list was ranked for
+    query: String,
+    mode: String,
+    /// The search returned fewer results than requested: there are no more
+    exhausted: bool,
+    hits: Vec<CachedHit>,
+}
+
+/// Ranked results for the current key, as far as they are known
+pub struct Cached {
+    pub results: Vec<SearchResult>,
+    pub exhausted: bool,
+    /// Trace of the search that produced the results, for the cached chunks
+    pub trace: SearchTrace,
+}
+
+/// Query text and embedding mode a cached list was ranked for
+pub struct RankedQuery<'a> {
+    pub text: &'a str,
+    pub mode: &'a str,
+}
+
+/// A result as it was shown for a query, for relevance feedback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShownResult {
+    pub query: String,
+    pub mode: String,
+    pub chunk_id: String,
+    pub file_path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub bm25_score: f32,
+    pub vector_score: f32,
+    pub colbert_score: Option<f32>,
+}
+
+/// Cache key for a query and everything that affects its ranking
+pub fn cache_key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(&hasher.finalize()[..8])
+}
+
+pub fn cursor(key: &str, offset: usize) -> String {
+    format!("{}:{}", key, offset)
+}
+
+/// Offset encoded in `cursor`, which must have been issued for `key`
+pub fn parse_cursor(cursor: &str, key: &str) -> Result<usize> {
+    let (cursor_key, offset) = cursor
+        .rsplit_once(':')
+        .with_context(|| format!("Invalid cursor: {}", cursor))?;
+    if cursor_key != key {
+        bail!("Cursor was issued for a different query or search options");
+    }
+    offset
+        .parse()
+        .with_context(|| format!("Invalid cursor: {}", cursor))
+}
+
+/// Results shown for one query id, by 0-based rank
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ShownEntry {
+    results: BTreeMap<usize, ShownResult>,
+}
+
+pub struct ResultCache {
+    dir: PathBuf,
+    /// Pages shown to the user, for feedback
+    shown_dir: PathBuf,
+}
+
+impl ResultCache {
+    pub fn open() -> Result<Self> {
+        Self::in_dir(&Config::config_dir()?)
+    }
+
+    fn in_dir(root: &Path) -> Result<Self> {
+        let dir = root.join("results");
+        let shown_dir = root.join("shown");
+        fs::create_dir_all(&dir)?;
+        fs::create_dir_all(&shown_dir)?;
+        Ok(Self { dir, shown_dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", key))
+    }
+
+    /// Cached results for `key` if they were ranked at `generation` and
+    /// cover `needed` results (or all there are)
+    pub fn get(
+        &self,
+        key: &str,
+        generation: u64,
+        store: &VectorStore,
+        needed: usize,
+    ) -> Option<Cached> {
+        let data = fs::read(self.path(key)).ok()?;
+        let entry: Entry = bincode::deserialize(&data).ok()?;
+        if entry.generation != generation || (entry.hits.len() < needed && !entry.exhausted) {
+            return None;
+        }
+
+        let mut trace = SearchTrace::default();
+        let results = entry
+            .hits
+            .into_iter()
+            .filter_map(|hit| {
+                if let Some(retrieval) = hit.retrieval {
+                    trace.retrieval.insert(hit.chunk_id.clone(), retrieval);
+                }
+                if let Some(reranked) = hit.reranked {
+                    trace.reranked.insert(hit.chunk_id.clone(), reranked);
+                }
+                let mut chunk = store.chunks.get(&hit.chunk_id)?.clone();
+                chunk.start_line = hit.start_line;
+                chunk.end_line = hit.end_line;
+                chunk.content = hit.content;
+                Some(SearchResult {
+                    chunk,
+                    score: hit.score,
+                    bm25_score: hit.bm25_score,
+                    vector_score: hit.vector_score,
+                    colbert_score: hit.colbert_score,
+                    matched_ranges: hit.matched_ranges,
+                    source: hit.source,
+                })
+            })
+            .collect();
+        Some(Cached {
+            results,
+            exhausted: entry.exhausted,
+            trace,
+        })
+    }
+
+    /// The result shown at 0-based `index` for the query id `key`
+    pub fn shown(&self, key: &str, index: usize) -> Result<ShownResult> {
+        let mut entry: ShownEntry = fs::read(self.shown_path(key))
+            .ok()
+            .and_then(|data| bincode::deserialize(&data).ok())
+            .with_context(|| format!("Unknown or expired query id: {}", key))?;
+        let count = entry.results.len();
+        entry.results.remove(&index).with_context(|| {
+            format!(
+                "Rank {} was not shown for this query ({} results were)",
+                index + 1,
+                count
+            )
+        })
+    }
+
+    /// Remember `results` as shown for `key` from 0-based rank `offset`, so
+    /// they can be rated after the ranked list has left the cache
+    pub fn record_shown(
+        &self,
+        key: &str,
+        query: RankedQuery,
+        offset: usize,
+        results: &[SearchResult],
+    ) -> Result<()> {
+        let path = self.shown_path(key);
+        let mut entry: ShownEntry = fs::read(&path)
+            .ok()
+            .and_then(|data| bincode::deserialize(&data).ok())
+            .unwrap_or_default();
+        for (i, r) in results.iter().enumerate() {
+            entry.results.insert(
+                offset + i,
+                ShownResult {
+                    query: query.text.to_string(),
+                    mode: query.mode.to_string(),
+                    chunk_id: r.chunk.id.clone(),
+                    file_path: r.chunk.file_path.clone(),
+                    start_line: r.chunk.start_line,
+                    end_line: r.chunk.end_line,
+                    bm25_score: r.bm25_score,
+                    vector_score: r.vector_score,
+                    colbert_score: r.colbert_score,
+                },
+            );
+        }
+        fs::write(&path, bincode::serialize(&entry)?).context("Failed to record shown results")?;
+        prune(&self.shown_dir, SHOWN_MAX_AGE, SHOWN_MAX_ENTRIES);
+        Ok(())
+    }
+
+    fn shown_path(&self, key: &str) -> PathBuf {
+        self.shown_dir.join(format!("{}.bin", key))
+    }
+
+    /// Store the results of a search that asked for `requested` results
+    pub fn put(
+        &self,
+        key: &str,
+        generation: u64,
+        query: RankedQuery,
+        results: &[SearchResult],
+        trace: &SearchTrace,
+        requested: usize,
+    ) -> Result<()> {
+        let entry = Entry {
+            generation,
+            query: query.text.to_string(),
+            mode: query.mode.to_string(),
+            exhausted: results.len() < requested,
+            hits: results
+                .iter()
+                .map(|r| CachedHit {
+                    chunk_id: r.chunk.id.clone(),
+                    file_path: r.chunk.file_path.clone(),
+                    start_line: r.chunk.start_line,
+                    end_line: r.chunk.end_line,
+                    content: r.chunk.content.clone(),
+                    score: r.score,
+                    bm25_score: r.bm25_score,
+                    vector_score: r.vector_score,
+                    colbert_score: r.colbert_score,
+                    matched_ranges: r.matched_ranges.clone(),
+                    retrieval: trace.retrieval.get(&r.chunk.id).copied(),
+                    reranked: trace.reranked.get(&r.chunk.id).copied(),
+                    source: r.source,
+                })
+                .collect(),
+        };
+        fs::write(self.path(key), bincode::serialize(&entry)?)?;
+        prune(&self.dir, MAX_AGE, MAX_ENTRIES);
+        Ok(())
+    }
+}
+
+/// Drop files in `dir` older than `max_age` and the oldest beyond `max_entries`
+fn prune(dir: &Path, max_age: Duration, max_entries: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .collect();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    for (i, (modified, path)) in files.iter().enumerate() {
+        let expired = now.duration_since(*modified).unwrap_or_default() > max_age;
+        if expired || i >= max_entries {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// One page of a ranked list
+pub struct Page {
+    pub results: Vec<SearchResult>,
+    pub offset: usize,
+    /// Cursor for the following page, if there may be more results
+    pub next_cursor: Option<String>,
+}
+
+impl Page {
+    pub fn new(key: &str, cached: &Cached, offset: usize, limit: usize) -> Self {
+        let total = cached.results.len();
+        let end = (offset + limit).min(total);
+        let more = end < total || !cached.exhausted;
+        Self {
+            results: cached
+                .results
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+            offset,
+            next_cursor: (more && end > offset).then(|| cursor(key, end)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::FileChunk;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let key = cache_key(&["cli", "retry logic", "default"]);
+        assert_eq!(key.len(), 16);
+        assert_ne!(key, cache_key(&["cli", "retry", "logic default"]));
+
+        let next = cursor(&key, 20);
+        assert_eq!(parse_cursor(&next, &key).unwrap(), 20);
+        assert!(parse_cursor(&next, "0000000000000000").is_err());
+        assert!(parse_cursor("garbage", &key).is_err());
+    }
+
+    #[test]
+    fn test_page_cursor() {
+        let page = |total: usize, exhausted: bool, offset: usize| {
+            let cached = Cached {
+                results: (0..total)
+                    .map(|i| {
+                        let chunk = FileChunk::test(&i.to_string(), "a.rs", (i, i));
+                        SearchResult::test(chunk, 1.0)
+                    })
+                    .collect(),
+                exhausted,
+                trace: SearchTrace::default(),
+            };
+            Page::new("k", &cached, offset, 10)
+        };
+
+        let first = page(25, true, 0);
+        assert_eq!(first.results.len(), 10);
+        assert_eq!(first.next_cursor.as_deref(), Some("k:10"));
+
+        let last = page(25, true, 20);
+        assert_eq!(last.results.len(), 5);
+        assert!(last.next_cursor.is_none());
+
+        // Not exhausted: the next page triggers a larger search
+        assert_eq!(page(20, false, 10).next_cursor.as_deref(), Some("k:20"));
+    }
+
+    #[test]
+    fn test_merged_span_round_trip() {
+        let root = std::env::temp_dir().join(format!("sgrep-merged-{}", std::process::id()));
+        let cache = ResultCache::in_dir(&root).unwrap();
+        let store = VectorStore::with_test_chunks(vec![
+            FileChunk::test("a", "a.rs", (1, 5)).with_content("fn a() {}"),
+            FileChunk::test("b", "a.rs", (6, 10)).with_content("fn b() {}"),
+        ]);
+
+        // The best hit's id over the stitched span of both chunks
+        let mut merged = SearchResult::test(
+            FileChunk::test("a", "a.rs", (1, 10)).with_content("fn a() {}\nfn b() {}"),
+            0.9,
+        );
+        merged.matched_ranges = vec![(8, 10)];
+        let query = RankedQuery {
+            text: "a b",
+            mode: "default",
+        };
+        cache
+            .put("k", 1, query, &[merged], &SearchTrace::default(), 10)
+            .unwrap();
+
+        let cached = cache.get("k", 1, &store, 1).unwrap();
+        let chunk = &cached.results[0].chunk;
+        assert_eq!(chunk.id, "a");
+        assert_eq!((chunk.start_line, chunk.end_line), (1, 10));
+        assert_eq!(chunk.content, "fn a() {}\nfn b() {}");
+        assert_eq!(cached.results[0].matched_ranges, vec![(8, 10)]);
+        assert!(cache.get("k", 2, &store, 1).is_none());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_shown_outlives_ranked_list() {
+        let root = std::env::temp_dir().join(format!("sgrep-shown-{}", std::process::id()));
+        let cache = ResultCache::in_dir(&root).unwrap();
+        let query = || RankedQuery {
+            text: "retry logic",
+            mode: "default",
+        };
+        let results: Vec<SearchResult> = (0..3)
+            .map(|i| SearchResult::test(FileChunk::test(&i.to_string(), "a.rs", (i, i)), 1.0))
+            .collect();
+
+        // A second page keeps the first one rateable
+        cache.record_shown("k", query(), 0, &results[..2]).unwrap();
+        cache.record_shown("k", query(), 2, &results[2..]).unwrap();
+        let _ = fs::remove_dir_all(root.join("results"));
+
+        assert_eq!(cache.shown("k", 0).unwrap().chunk_id, "0");
+        assert_eq!(cache.shown("k", 2).unwrap().chunk_id, "2");
+        assert!(cache.shown("k", 3).is_err());
+        assert!(cache.shown("other", 0).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
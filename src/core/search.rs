@@ -1,7 +1,11 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
+use crate::core::config::Config;
 use crate::core::embeddings::{colbert_max_sim, cosine_similarity};
+use crate::core::filter::SearchFilter;
 use crate::core::store::{FileChunk, VectorStore};
+use crate::core::trigram::TrigramQuery;
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -10,22 +14,206 @@ pub struct SearchResult {
     pub bm25_score: f32,
     pub vector_score: f32,
     pub colbert_score: Option<f32>,
+    /// Line ranges that matched (more than one once hits are merged)
+    pub matched_ranges: Vec<(usize, usize)>,
+    /// Candidate set the result was retrieved from
+    pub source: CandidateSource,
 }
 
+#[cfg(test)]
+impl SearchResult {
+    /// Result for `chunk` with a pure vector `score`, matching its whole span
+    pub fn test(chunk: FileChunk, score: f32) -> Self {
+        Self {
+            matched_ranges: vec![(chunk.start_line, chunk.end_line)],
+            chunk,
+            score,
+            bm25_score: 0.0,
+            vector_score: score,
+            colbert_score: None,
+            source: CandidateSource::Exhaustive,
+        }
+    }
+}
+
+/// Where a result's candidate came from. Unfiltered searches on a store
+/// with an ANN index score the ANN neighbours plus the chunks holding a
+/// query term; everything else scores every (filtered) chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CandidateSource {
+    /// Nearest neighbours from the ANN index
+    Ann,
+    /// Holds a query term (trigram index lookup)
+    Lexical,
+    /// Both an ANN neighbour and a lexical candidate
+    Both,
+    /// Every (filtered) chunk was scored
+    #[default]
+    Exhaustive,
+    /// Not retrieved: added as a dependency of a retrieved file
+    Graph,
+}
+
+impl CandidateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CandidateSource::Ann => "ann",
+            CandidateSource::Lexical => "lexical",
+            CandidateSource::Both => "ann+lexical",
+            CandidateSource::Exhaustive => "exhaustive",
+            CandidateSource::Graph => "graph",
+        }
+    }
+}
+
+/// One term of the combined retrieval score
+#[derive(Debug, Clone)]
+pub struct ScoreComponent {
+    pub name: &'static str,
+    /// Score as computed (BM25 is unbounded)
+    pub raw: f32,
+    /// Score mapped to [0, 1]
+    pub normalized: f32,
+    pub weight: f32,
+}
+
+impl ScoreComponent {
+    pub fn contribution(&self) -> f32 {
+        self.weight * self.normalized
+    }
+}
+
+/// Lowercased whitespace-separated terms, as scored by BM25
+pub fn query_terms(query_text: &str) -> Vec<String> {
+    query_text
+        .to_lowercase()
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// How query text is split into BM25 terms. Chunks are always indexed as
+/// lowercased whitespace-separated words; the query side picks which of
+/// those words to look up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueryTokenizer {
+    /// Whitespace-separated words, as indexed
+    #[default]
+    Words,
+    /// Each word plus the indexed words that contain it as a whole
+    /// identifier (`parse_config` also matches `self.parse_config(path)`)
+    Symbols,
+    /// Words without line numbers, addresses and file locations, plus each
+    /// word stripped of quotes and brackets
+    Error,
+}
+
+/// Index spellings a symbol term expands to, at most
+const MAX_SYMBOL_VARIANTS: usize = 8;
+
+impl QueryTokenizer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryTokenizer::Words => "words",
+            QueryTokenizer::Symbols => "symbols",
+            QueryTokenizer::Error => "error",
+        }
+    }
+
+    /// BM25 terms of `query_text`; `idf` holds the indexed vocabulary
+    pub fn terms(&self, query_text: &str, idf: &HashMap<String, f32>) -> Vec<String> {
+        let words = query_terms(query_text);
+        let mut terms: Vec<String> = Vec::new();
+        let mut push = |term: &str| {
+            if !term.is_empty() && !terms.iter().any(|t| t == term) {
+                terms.push(term.to_string());
+            }
+        };
+
+        match self {
+            QueryTokenizer::Words => words.iter().for_each(|w| push(w)),
+            QueryTokenizer::Symbols => {
+                for word in &words {
+                    push(word);
+                    let symbol = word.trim_matches(|c: char| !is_symbol_char(c));
+                    push(symbol);
+                    if symbol.is_empty() {
+                        continue;
+                    }
+                    let mut variants: Vec<&String> = idf
+                        .keys()
+                        .filter(|term| *term != word && contains_symbol(term, symbol))
+                        .collect();
+                    variants.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+                    for variant in variants.into_iter().take(MAX_SYMBOL_VARIANTS) {
+                        push(variant);
+                    }
+                }
+            }
+            QueryTokenizer::Error => {
+                for word in words.iter().filter(|w| !is_error_noise(w)) {
+                    push(word);
+                    push(word.trim_matches(|c: char| !c.is_alphanumeric() && c != '_'));
+                }
+            }
+        }
+        terms
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Whether `symbol` occurs in `term` not preceded or followed by another
+/// identifier character
+fn contains_symbol(term: &str, symbol: &str) -> bool {
+    term.match_indices(symbol).any(|(start, _)| {
+        let before = term[..start].chars().next_back();
+        let after = term[start + symbol.len()..].chars().next();
+        !before.is_some_and(is_symbol_char) && !after.is_some_and(is_symbol_char)
+    })
+}
+
+/// Line numbers, addresses and `file:line` locations vary between runs
+fn is_error_noise(word: &str) -> bool {
+    if !word.chars().any(|c| c.is_alphabetic()) || word.starts_with("0x") {
+        return true;
+    }
+    // src/main.rs:12:5, (app.js:3:14), main.go:27
+    word.split(':')
+        .skip(1)
+        .any(|part| part.starts_with(|c: char| c.is_ascii_digit()))
+        && word.contains('.')
+}
+
+/// Weight of the BM25 score in the combined retrieval score
+pub const DEFAULT_BM25_WEIGHT: f32 = 0.3;
+/// Weight of the vector similarity in the combined retrieval score
+pub const DEFAULT_VECTOR_WEIGHT: f32 = 0.7;
+
+/// Terms found in more than this share of the chunks add no lexical
+/// candidates to an ANN search
+const MAX_LEXICAL_TERM_SHARE: f32 = 0.05;
+/// Most lexical candidates BM25-scored per result kept
+const LEXICAL_SCORED_PER_RESULT: usize = 10;
+
 pub struct HybridSearcher {
     bm25_weight: f32,
     vector_weight: f32,
     k1: f32,
     b: f32,
+    tokenizer: QueryTokenizer,
 }
 
 impl Default for HybridSearcher {
     fn default() -> Self {
         Self {
-            bm25_weight: 0.3,
-            vector_weight: 0.7,
+            bm25_weight: DEFAULT_BM25_WEIGHT,
+            vector_weight: DEFAULT_VECTOR_WEIGHT,
             k1: 1.2,
             b: 0.75,
+            tokenizer: QueryTokenizer::Words,
         }
     }
 }
@@ -37,24 +225,38 @@ impl HybridSearcher {
             vector_weight,
             k1: 1.2,
             b: 0.75,
+            tokenizer: QueryTokenizer::Words,
         }
     }
 
+    /// Searcher with the configured fusion weights (see `sgrep tune`)
+    pub fn from_config(config: &Config) -> Self {
+        let (bm25_weight, vector_weight) = config.get_fusion_weights();
+        Self::new(bm25_weight, vector_weight)
+    }
+
+    /// Split queries into BM25 terms with `tokenizer` instead of words
+    pub fn with_tokenizer(mut self, tokenizer: QueryTokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// BM25 terms of `query_text` against the indexed vocabulary `idf`
+    pub fn terms(&self, query_text: &str, idf: &HashMap<String, f32>) -> Vec<String> {
+        self.tokenizer.terms(query_text, idf)
+    }
+
     pub fn search(
         &self,
         store: &VectorStore,
         query_embedding: &[f32],
         query_text: &str,
         limit: usize,
-        file_types: Option<&[String]>,
+        filter: Option<&SearchFilter>,
         use_colbert: bool,
         query_token_embeddings: Option<&[Vec<f32>]>,
     ) -> Vec<SearchResult> {
-        let query_terms: Vec<String> = query_text
-            .to_lowercase()
-            .split_whitespace()
-            .map(|s| s.to_string())
-            .collect();
+        let query_terms = self.terms(query_text, &store.bm25_idf);
 
         let avg_doc_len = if store.doc_count > 0 {
             store
@@ -68,39 +270,57 @@ impl HybridSearcher {
         };
 
         // Try ANN fast path first (for large indexes)
-        // Fetch more candidates than limit to allow for filtering and reranking
-        let ann_candidates = store.ann_search(query_embedding, limit * 3);
+        // Fetch more candidates than limit to allow for filtering and reranking.
+        // A scoped search may have no matches among the ANN neighbours, so
+        // filters always score exhaustively.
+        let filter = filter.filter(|f| !f.is_empty());
+        let ann_candidates = if filter.is_none() {
+            store.ann_search(query_embedding, limit * 3)
+        } else {
+            None
+        };
 
-        let chunks_iter: Box<dyn Iterator<Item = &FileChunk>> =
+        let chunks_iter: Box<dyn Iterator<Item = (&FileChunk, CandidateSource)>> =
             if let Some(ref candidates) = ann_candidates {
-                // Fast path: only score ANN candidates
-                Box::new(
-                    candidates
-                        .iter()
-                        .filter_map(|(chunk_id, _)| store.chunks.get(chunk_id)),
-                )
+                // Fast path: only score ANN candidates, plus the chunks the
+                // embedding missed that hold a query term
+                let lexical = self.lexical_candidates(store, &query_terms, avg_doc_len, limit * 3);
+                let lexical_ids: HashSet<&str> = lexical.iter().map(|c| c.id.as_str()).collect();
+                let mut chunks: Vec<(&FileChunk, CandidateSource)> = candidates
+                    .iter()
+                    .filter_map(|(chunk_id, _)| store.chunks.get(chunk_id))
+                    .map(|c| {
+                        if lexical_ids.contains(c.id.as_str()) {
+                            (c, CandidateSource::Both)
+                        } else {
+                            (c, CandidateSource::Ann)
+                        }
+                    })
+                    .collect();
+                let ann_ids: HashSet<&str> = chunks.iter().map(|(c, _)| c.id.as_str()).collect();
+                let lexical_only: Vec<&FileChunk> = lexical
+                    .into_iter()
+                    .filter(|c| !ann_ids.contains(c.id.as_str()))
+                    .collect();
+                chunks.extend(
+                    lexical_only
+                        .into_iter()
+                        .map(|c| (c, CandidateSource::Lexical)),
+                );
+                Box::new(chunks.into_iter())
             } else {
                 // Slow path: brute force all chunks
-                Box::new(store.chunks.values())
+                Box::new(
+                    store
+                        .chunks
+                        .values()
+                        .map(|c| (c, CandidateSource::Exhaustive)),
+                )
             };
 
         let mut results: Vec<SearchResult> = chunks_iter
-            .filter(|chunk| {
-                // Filter by file type if specified
-                if let Some(types) = file_types {
-                    let file_ext = std::path::Path::new(&chunk.file_path)
-                        .extension()
-                        .map(|e| e.to_string_lossy().to_lowercase());
-
-                    match file_ext {
-                        Some(ext) => types.iter().any(|t| t.to_lowercase() == ext),
-                        None => false,
-                    }
-                } else {
-                    true
-                }
-            })
-            .map(|chunk| {
+            .filter(|(chunk, _)| filter.map(|f| f.matches_chunk(chunk)).unwrap_or(true))
+            .map(|(chunk, source)| {
                 // Vector similarity (recompute for exact score, ANN gives approximate)
                 let vector_score = cosine_similarity(query_embedding, &chunk.embedding);
 
@@ -120,16 +340,11 @@ impl HybridSearcher {
                     None
                 };
 
-                // Combined score
-                let combined_score = if let Some(col_score) = colbert_score {
-                    // When using ColBERT, blend all three
-                    self.vector_weight * 0.5 * vector_score
-                        + self.vector_weight * 0.5 * col_score
-                        + self.bm25_weight * Self::normalize_bm25(bm25_score)
-                } else {
-                    self.vector_weight * vector_score
-                        + self.bm25_weight * Self::normalize_bm25(bm25_score)
-                };
+                let combined_score = self
+                    .components(bm25_score, vector_score, colbert_score)
+                    .iter()
+                    .map(ScoreComponent::contribution)
+                    .sum();
 
                 SearchResult {
                     chunk: chunk.clone(),
@@ -137,6 +352,8 @@ impl HybridSearcher {
                     bm25_score,
                     vector_score,
                     colbert_score,
+                    matched_ranges: vec![(chunk.start_line, chunk.end_line)],
+                    source,
                 }
             })
             .collect();
@@ -153,6 +370,97 @@ impl HybridSearcher {
         results
     }
 
+    /// Weighted terms of the combined score. With ColBERT the vector weight
+    /// is split evenly between the pooled and token-level similarities.
+    pub fn components(
+        &self,
+        bm25_score: f32,
+        vector_score: f32,
+        colbert_score: Option<f32>,
+    ) -> Vec<ScoreComponent> {
+        let vector_weight = if colbert_score.is_some() {
+            self.vector_weight * 0.5
+        } else {
+            self.vector_weight
+        };
+
+        let mut components = vec![ScoreComponent {
+            name: "vector",
+            raw: vector_score,
+            normalized: vector_score,
+            weight: vector_weight,
+        }];
+        if let Some(colbert_score) = colbert_score {
+            components.push(ScoreComponent {
+                name: "colbert",
+                raw: colbert_score,
+                normalized: colbert_score,
+                weight: vector_weight,
+            });
+        }
+        components.push(ScoreComponent {
+            name: "bm25",
+            raw: bm25_score,
+            normalized: Self::normalize_bm25(bm25_score),
+            weight: self.bm25_weight,
+        });
+        components
+    }
+
+    /// Chunks holding at least one query term, found through the store's
+    /// trigram index and cut to the `limit` best by BM25. Terms are looked
+    /// up rarest first; terms shorter than a trigram or found in too many
+    /// chunks are skipped, and at most `limit * LEXICAL_SCORED_PER_RESULT`
+    /// chunks are scored, so common words can't turn this into a full scan.
+    fn lexical_candidates<'a>(
+        &self,
+        store: &'a VectorStore,
+        query_terms: &[String],
+        avg_doc_len: f32,
+        limit: usize,
+    ) -> Vec<&'a FileChunk> {
+        let max_postings =
+            ((store.chunk_count() as f32 * MAX_LEXICAL_TERM_SHARE) as usize).max(limit);
+        let max_scored = limit * LEXICAL_SCORED_PER_RESULT;
+
+        // Terms missing from the IDF table appear in no chunk as a whole word
+        let idf = |term: &String| store.bm25_idf.get(term).copied().unwrap_or(f32::MAX);
+        let mut terms: Vec<&String> = query_terms.iter().filter(|t| t.len() >= 3).collect();
+        terms.sort_by(|a, b| idf(b).total_cmp(&idf(a)));
+
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut scored: Vec<(&FileChunk, f32)> = Vec::new();
+        for term in terms {
+            if seen.len() >= max_scored {
+                break;
+            }
+            let query = TrigramQuery::from_regex(&regex::escape(term), true);
+            let Some(chunks) = store.trigram_candidates(&query) else {
+                continue;
+            };
+            if chunks.len() > max_postings {
+                continue;
+            }
+            for chunk in chunks {
+                if seen.len() >= max_scored {
+                    break;
+                }
+                if !seen.insert(chunk.id.as_str()) {
+                    continue;
+                }
+                let score =
+                    self.compute_bm25(&chunk.content, query_terms, &store.bm25_idf, avg_doc_len);
+                if score > 0.0 {
+                    scored.push((chunk, score));
+                }
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        scored.into_iter().map(|(chunk, _)| chunk).collect()
+    }
+
     fn compute_bm25(
         &self,
         content: &str,
@@ -195,10 +503,15 @@ pub fn vector_search(
     store: &VectorStore,
     query_embedding: &[f32],
     limit: usize,
-    file_types: Option<&[String]>,
+    filter: Option<&SearchFilter>,
 ) -> Vec<SearchResult> {
-    // Try ANN fast path
-    let ann_candidates = store.ann_search(query_embedding, limit * 3);
+    // Try ANN fast path (exhaustive when filtered)
+    let filter = filter.filter(|f| !f.is_empty());
+    let ann_candidates = if filter.is_none() {
+        store.ann_search(query_embedding, limit * 3)
+    } else {
+        None
+    };
 
     let chunks_iter: Box<dyn Iterator<Item = &FileChunk>> =
         if let Some(ref candidates) = ann_candidates {
@@ -211,21 +524,13 @@ pub fn vector_search(
             Box::new(store.chunks.values())
         };
 
+    let source = if ann_candidates.is_some() {
+        CandidateSource::Ann
+    } else {
+        CandidateSource::Exhaustive
+    };
     let mut results: Vec<SearchResult> = chunks_iter
-        .filter(|chunk| {
-            if let Some(types) = file_types {
-                let file_ext = std::path::Path::new(&chunk.file_path)
-                    .extension()
-                    .map(|e| e.to_string_lossy().to_lowercase());
-
-                match file_ext {
-                    Some(ext) => types.iter().any(|t| t.to_lowercase() == ext),
-                    None => false,
-                }
-            } else {
-                true
-            }
-        })
+        .filter(|chunk| filter.map(|f| f.matches_chunk(chunk)).unwrap_or(true))
         .map(|chunk| {
             let score = cosine_similarity(query_embedding, &chunk.embedding);
             SearchResult {
@@ -234,6 +539,8 @@ pub fn vector_search(
                 bm25_score: 0.0,
                 vector_score: score,
                 colbert_score: None,
+                matched_ranges: vec![(chunk.start_line, chunk.end_line)],
+                source,
             }
         })
         .collect();
@@ -246,3 +553,75 @@ pub fn vector_search(
     results.truncate(limit);
     results
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ann_search_adds_lexical_candidates() {
+        // Four chunks just ahead of the term-holding one in embedding
+        // similarity fill the ANN candidates for a one-result search
+        let near = vec![0.1, (1.0f32 - 0.01).sqrt(), 0.0, 0.0];
+        let mut chunks: Vec<FileChunk> = (0..4)
+            .map(|i| {
+                FileChunk::test(&format!("near{}", i), "/r/a.rs", (i * 10, i * 10 + 5))
+                    .with_content("fn other() {}")
+                    .with_embedding(near.clone())
+            })
+            .collect();
+        chunks.push(
+            FileChunk::test("budget", "/r/b.rs", (1, 5))
+                .with_content(&["retry_budget"; 5].join(" "))
+                .with_embedding(vec![0.09, 0.0, (1.0f32 - 0.0081).sqrt(), 0.0]),
+        );
+        let mut store = VectorStore::with_test_chunks(chunks);
+        store.update_bm25_stats();
+        store.build_ann_index().unwrap();
+
+        let query = [1.0, 0.0, 0.0, 0.0];
+        let results =
+            HybridSearcher::default().search(&store, &query, "retry_budget", 1, None, false, None);
+        assert_eq!(results[0].chunk.id, "budget");
+        assert_eq!(results[0].source, CandidateSource::Lexical);
+
+        // Filtered searches score every chunk
+        let filter = SearchFilter::new().with_file_types(&["rs".to_string()]);
+        let results = HybridSearcher::default().search(
+            &store,
+            &query,
+            "retry_budget",
+            1,
+            Some(&filter),
+            false,
+            None,
+        );
+        assert_eq!(results[0].source, CandidateSource::Exhaustive);
+    }
+
+    #[test]
+    fn test_common_terms_add_no_lexical_candidates() {
+        let mut chunks: Vec<FileChunk> = (0..40)
+            .map(|i| {
+                FileChunk::test(&format!("c{}", i), "/r/a.rs", (i * 10, i * 10 + 5))
+                    .with_content("how are the other parts wired")
+            })
+            .collect();
+        chunks.push(
+            FileChunk::test("budget", "/r/b.rs", (1, 5))
+                .with_content("how the retry_budget is spent"),
+        );
+        let mut store = VectorStore::with_test_chunks(chunks);
+        store.update_bm25_stats();
+
+        let searcher = HybridSearcher::default();
+        let terms =
+            |query: &str| -> Vec<String> { query.split_whitespace().map(str::to_string).collect() };
+        assert!(searcher
+            .lexical_candidates(&store, &terms("how are the"), 30.0, 3)
+            .is_empty());
+        let found = searcher.lexical_candidates(&store, &terms("how the retry_budget"), 30.0, 3);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "budget");
+    }
+}
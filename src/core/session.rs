@@ -0,0 +1,397 @@
+//! Search session
+//!
+//! Holds the store, query embedder and reranker for successive queries, so
+//! models load once per process instead of once per search. `sgrep search`
+//! runs a single query through it; the interactive browser keeps it warm.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+use crate::core::config::Config;
+use crate::core::diversify::{diversify, DiversifyOptions};
+use crate::core::embeddings::EmbeddingProvider;
+use crate::core::filter::SearchFilter;
+use crate::core::hybrid_embedder::HybridEmbedder;
+use crate::core::intent::{Route, Router};
+use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
+use crate::core::merge::merge_results;
+use crate::core::query::ParsedQuery;
+use crate::core::reranker::{simple_rerank, Reranker};
+use crate::core::search::{HybridSearcher, SearchResult};
+use crate::core::store::VectorStore;
+
+/// How queries are embedded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbedMode {
+    /// The configured embedding provider
+    #[default]
+    Default,
+    /// Local CodeRankEmbed
+    Code,
+    /// BGE + CodeRankEmbed fusion
+    Hybrid,
+    /// Provider embedding plus ColBERT token embeddings
+    Colbert,
+}
+
+impl EmbedMode {
+    pub fn from_flags(code: bool, hybrid: bool, colbert: bool) -> Self {
+        if hybrid {
+            EmbedMode::Hybrid
+        } else if code {
+            EmbedMode::Code
+        } else if colbert {
+            EmbedMode::Colbert
+        } else {
+            EmbedMode::Default
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "default" => Some(EmbedMode::Default),
+            "code" => Some(EmbedMode::Code),
+            "hybrid" => Some(EmbedMode::Hybrid),
+            "colbert" => Some(EmbedMode::Colbert),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbedMode::Default => "default",
+            EmbedMode::Code => "code",
+            EmbedMode::Hybrid => "hybrid",
+            EmbedMode::Colbert => "colbert",
+        }
+    }
+}
+
+/// Pooled query embedding and (for ColBERT) token embeddings
+pub type QueryEmbedding = (Vec<f32>, Vec<Vec<f32>>);
+
+/// Query embedder that loads each model on first use and keeps it
+pub struct QueryEmbedder {
+    config: Config,
+    mode: EmbedMode,
+    provider: Option<EmbeddingProvider>,
+    code: Option<LocalEmbedder>,
+    hybrid: Option<HybridEmbedder>,
+}
+
+impl QueryEmbedder {
+    pub fn new(config: Config, mode: EmbedMode) -> Self {
+        Self {
+            config,
+            mode,
+            provider: None,
+            code: None,
+            hybrid: None,
+        }
+    }
+
+    pub fn mode(&self) -> EmbedMode {
+        self.mode
+    }
+
+    /// Switch modes; models loaded for other modes stay cached
+    pub fn set_mode(&mut self, mode: EmbedMode) {
+        self.mode = mode;
+    }
+
+    /// Embed `text`, returning the pooled embedding and (for ColBERT) token embeddings
+    pub async fn embed(&mut self, text: &str) -> Result<QueryEmbedding> {
+        match self.mode {
+            EmbedMode::Hybrid => Ok((self.hybrid()?.embed_query(text)?, vec![])),
+            EmbedMode::Code => Ok((self.code()?.embed_query(text)?, vec![])),
+            EmbedMode::Colbert => self.provider().embed_with_tokens(text).await,
+            EmbedMode::Default => Ok((self.provider().embed_single(text).await?, vec![])),
+        }
+    }
+
+    /// Embed several queries: one request for API providers, one batch per
+    /// local model
+    pub async fn embed_batch(&mut self, texts: &[String]) -> Result<Vec<QueryEmbedding>> {
+        let embeddings = match self.mode {
+            EmbedMode::Default => self.provider().embed(texts).await?,
+            EmbedMode::Code => self.code()?.embed_queries(texts)?,
+            EmbedMode::Hybrid => self.hybrid()?.embed_queries(texts)?,
+            // Token embeddings are computed one query at a time
+            EmbedMode::Colbert => {
+                let mut embeddings = Vec::with_capacity(texts.len());
+                for text in texts {
+                    embeddings.push(self.embed(text).await?);
+                }
+                return Ok(embeddings);
+            }
+        };
+        if embeddings.len() != texts.len() {
+            bail!(
+                "Expected {} embeddings, got {}",
+                texts.len(),
+                embeddings.len()
+            );
+        }
+        Ok(embeddings.into_iter().map(|e| (e, vec![])).collect())
+    }
+
+    fn code(&mut self) -> Result<&mut LocalEmbedder> {
+        if self.code.is_none() {
+            self.code = Some(LocalEmbedder::with_speed_mode(SpeedMode::Code)?);
+        }
+        Ok(self.code.as_mut().unwrap())
+    }
+
+    fn hybrid(&mut self) -> Result<&mut HybridEmbedder> {
+        if self.hybrid.is_none() {
+            self.hybrid = Some(HybridEmbedder::with_strategy(
+                self.config.hybrid_strategy(),
+            )?);
+        }
+        Ok(self.hybrid.as_mut().unwrap())
+    }
+
+    fn provider(&mut self) -> &EmbeddingProvider {
+        let config = &self.config;
+        self.provider
+            .get_or_insert_with(|| EmbeddingProvider::new(config.clone()))
+    }
+}
+
+/// Ranking stages applied after retrieval
+#[derive(Debug, Clone)]
+pub struct PipelineOptions {
+    pub max_count: usize,
+    pub rerank: bool,
+    /// Merge overlapping/adjacent hits from the same file into one span
+    pub merge: bool,
+    /// Diversify results with maximal marginal relevance
+    pub diverse: bool,
+    /// MMR trade-off between relevance (1.0) and diversity (0.0)
+    pub lambda: f32,
+    pub max_per_file: Option<usize>,
+    /// Only retrieve from the chunks of this many files, picked by
+    /// directory and then file vectors
+    pub narrow: Option<usize>,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        Self {
+            max_count: 10,
+            rerank: true,
+            merge: true,
+            diverse: false,
+            lambda: crate::core::diversify::DEFAULT_LAMBDA,
+            max_per_file: None,
+            narrow: None,
+        }
+    }
+}
+
+/// Intermediate rankings of the last search, for `--explain`
+#[derive(Debug, Clone, Default)]
+pub struct SearchTrace {
+    /// Rank (0-based) and combined score per chunk id after retrieval
+    pub retrieval: HashMap<String, (usize, f32)>,
+    /// Rank and score per chunk id after reranking; empty when not reranked
+    pub reranked: HashMap<String, (usize, f32)>,
+}
+
+impl SearchTrace {
+    /// Trace of a search that ends with `results` straight from retrieval
+    pub fn retrieved(results: &[SearchResult]) -> Self {
+        Self {
+            retrieval: ranks(results),
+            ..Self::default()
+        }
+    }
+
+    /// Combined retrieval score of a final result, before reranking and merge
+    /// bonuses; results that were not retrieved directly keep their score
+    pub fn retrieval_score(&self, result: &SearchResult) -> f32 {
+        self.retrieval
+            .get(&result.chunk.id)
+            .map_or(result.score, |&(_, score)| score)
+    }
+}
+
+fn ranks(results: &[SearchResult]) -> HashMap<String, (usize, f32)> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.chunk.id.clone(), (i, r.score)))
+        .collect()
+}
+
+pub struct SearchSession {
+    pub store: VectorStore,
+    pub embedder: QueryEmbedder,
+    pub options: PipelineOptions,
+    /// Retrieval scoring with the configured fusion weights
+    pub searcher: HybridSearcher,
+    /// Rankings from the most recent `search`
+    pub trace: SearchTrace,
+    /// Route of the most recent query, when routing is on
+    pub route: Option<Route>,
+    router: Option<Router>,
+    reranker: Reranker,
+}
+
+impl SearchSession {
+    pub fn new(
+        store: VectorStore,
+        config: Config,
+        mode: EmbedMode,
+        options: PipelineOptions,
+    ) -> Self {
+        Self {
+            store,
+            embedder: QueryEmbedder::new(config.clone(), mode),
+            options,
+            searcher: HybridSearcher::from_config(&config),
+            trace: SearchTrace::default(),
+            route: None,
+            router: None,
+            reranker: Reranker::new(config),
+        }
+    }
+
+    /// Pick the embedder, BM25 tokenizer and fusion weights of each query
+    /// from its intent (see `core::intent`)
+    pub fn with_routing(mut self, config: &Config) -> Self {
+        self.router = Some(Router::new(config, &self.store, config.provider == "local"));
+        self
+    }
+
+    /// Stop routing: later queries keep the current embedder and the
+    /// configured fusion weights (e.g. once a mode was picked by hand)
+    pub fn stop_routing(&mut self) {
+        if self.router.take().is_some() {
+            self.searcher = HybridSearcher::from_config(&self.embedder.config);
+            self.route = None;
+        }
+    }
+
+    pub fn is_routed(&self) -> bool {
+        self.router.is_some()
+    }
+
+    /// Route `text` and switch the embedder and searcher to it. Without
+    /// routing this returns `None` and changes nothing.
+    pub fn route(&mut self, text: &str) -> Option<&Route> {
+        let route = self.router.as_ref()?.route(text);
+        self.embedder.set_mode(route.mode);
+        self.searcher = route.searcher();
+        self.route = Some(route);
+        self.route.as_ref()
+    }
+
+    /// Route, retrieve, rerank, merge and diversify. `progress` receives
+    /// stage names.
+    pub async fn search(
+        &mut self,
+        query: &ParsedQuery,
+        filter: &SearchFilter,
+        progress: &dyn Fn(&str),
+    ) -> Result<Vec<SearchResult>> {
+        self.route(&query.text);
+        progress("Generating embeddings...");
+        let embedding = self.embedder.embed(&query.text).await?;
+        self.search_embedded(query, filter, embedding, progress)
+            .await
+    }
+
+    /// `search` with the query already routed and embedded (e.g. by
+    /// `embed_batch`)
+    pub async fn search_embedded(
+        &mut self,
+        query: &ParsedQuery,
+        filter: &SearchFilter,
+        (query_embedding, query_tokens): QueryEmbedding,
+        progress: &dyn Fn(&str),
+    ) -> Result<Vec<SearchResult>> {
+        let colbert = self.embedder.mode() == EmbedMode::Colbert;
+
+        let narrowed;
+        let filter = match self.options.narrow {
+            // Stores indexed before file vectors existed search everything
+            Some(files) if !self.store.level_index().is_empty() => {
+                progress("Narrowing to files...");
+                let files = self.store.level_index().narrow(
+                    &query_embedding,
+                    &query.text,
+                    files,
+                    Some(filter),
+                );
+                narrowed = filter.clone().with_files(files);
+                &narrowed
+            }
+            _ => filter,
+        };
+
+        progress("Searching index...");
+        let options = &self.options;
+        let mut results = self.searcher.search(
+            &self.store,
+            &query_embedding,
+            &query.text,
+            options.max_count * 3, // Get more for reranking
+            Some(filter),
+            colbert,
+            if colbert { Some(&query_tokens) } else { None },
+        );
+        self.trace = SearchTrace {
+            retrieval: ranks(&results),
+            reranked: HashMap::new(),
+        };
+        if results.is_empty() {
+            return Ok(results);
+        }
+
+        // Merging and diversification select from the whole candidate pool,
+        // so keep it through reranking
+        let diversify_options = if options.diverse || options.max_per_file.is_some() {
+            let lambda = if options.diverse { options.lambda } else { 1.0 };
+            Some(DiversifyOptions::new(lambda).with_max_per_file(options.max_per_file))
+        } else {
+            None
+        };
+        let keep = if options.merge || diversify_options.is_some() {
+            results.len()
+        } else {
+            options.max_count
+        };
+
+        // Rerank if enabled
+        if options.rerank && results.len() > 1 {
+            progress("Reranking results...");
+            let results_clone = results.clone();
+            results = match self.reranker.rerank(&query.text, results_clone, keep).await {
+                Ok(reranked) => reranked,
+                Err(_) => {
+                    // Fall back to simple reranking
+                    simple_rerank(&query.text, results)
+                        .into_iter()
+                        .take(keep)
+                        .collect()
+                }
+            };
+            self.trace.reranked = ranks(&results);
+        } else {
+            results.truncate(keep);
+        }
+
+        if options.merge {
+            results = merge_results(&self.store, results);
+        }
+
+        if let Some(ref diversify_options) = diversify_options {
+            results = diversify(results, options.max_count, diversify_options);
+        } else {
+            results.truncate(options.max_count);
+        }
+
+        Ok(results)
+    }
+}
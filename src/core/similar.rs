@@ -0,0 +1,203 @@
+//! "More like this" search
+//!
+//! Finds the indexed chunk at a file location or symbol and ranks other
+//! chunks by similarity to its stored embedding, so no model is loaded and
+//! the query is exactly as specific as the chunk.
+
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use crate::core::filter::SearchFilter;
+use crate::core::search::{vector_search, SearchResult};
+use crate::core::store::{FileChunk, VectorStore};
+
+/// Split `path:line` into its parts
+pub fn parse_location(location: &str) -> Result<(&str, usize)> {
+    match location.rsplit_once(':') {
+        Some((path, line)) if !path.is_empty() => match line.parse() {
+            Ok(line) if line > 0 => Ok((path, line)),
+            _ => bail!("Invalid line number in location: {}", location),
+        },
+        _ => bail!("Expected a location as path:line, got: {}", location),
+    }
+}
+
+/// The smallest indexed chunk of `path` that contains `line`
+pub fn chunk_at<'a>(store: &'a VectorStore, path: &str, line: usize) -> Result<&'a FileChunk> {
+    let indexed_path = indexed_path(store, path)?;
+
+    store
+        .chunks_for_file(&indexed_path)
+        .into_iter()
+        .filter(|c| c.start_line <= line && line <= c.end_line)
+        .min_by_key(|c| c.end_line - c.start_line)
+        .ok_or_else(|| anyhow::anyhow!("No indexed chunk contains {}:{}", path, line))
+}
+
+/// The chunk defining `symbol`, given as `name` or `Parent::name`
+pub fn chunk_for_symbol<'a>(store: &'a VectorStore, symbol: &str) -> Result<&'a FileChunk> {
+    let (parent, name) = match symbol.rsplit_once("::") {
+        Some((parent, name)) => (Some(parent.rsplit("::").next().unwrap_or(parent)), name),
+        None => (None, symbol),
+    };
+
+    let mut matches: Vec<&FileChunk> = store
+        .chunks
+        .values()
+        .filter(|c| c.symbol_name.as_deref() == Some(name))
+        .filter(|c| parent.is_none() || c.parent_name.as_deref() == parent)
+        .collect();
+    matches.sort_by(|a, b| {
+        a.file_path
+            .cmp(&b.file_path)
+            .then(a.start_line.cmp(&b.start_line))
+    });
+
+    match matches.as_slice() {
+        [] => bail!("No indexed symbol named {}", symbol),
+        [chunk] => Ok(chunk),
+        _ => {
+            let locations: Vec<String> = matches
+                .iter()
+                .take(10)
+                .map(|c| format!("  {}:{}", c.file_path, c.start_line))
+                .collect();
+            bail!(
+                "{} symbols named {}; pick one by location:\n{}",
+                matches.len(),
+                symbol,
+                locations.join("\n")
+            )
+        }
+    }
+}
+
+/// Chunks most similar to `chunk`, excluding it and chunks overlapping it
+pub fn similar_to(
+    store: &VectorStore,
+    chunk: &FileChunk,
+    limit: usize,
+    filter: Option<&SearchFilter>,
+) -> Vec<SearchResult> {
+    // Overlapping chunks (enclosing class, split parts) would crowd the top
+    let overlapping = store
+        .chunks_for_file(&chunk.file_path)
+        .iter()
+        .filter(|c| c.start_line <= chunk.end_line && chunk.start_line <= c.end_line)
+        .count();
+
+    vector_search(store, &chunk.embedding, limit + overlapping, filter)
+        .into_iter()
+        .filter(|r| {
+            r.chunk.file_path != chunk.file_path
+                || r.chunk.end_line < chunk.start_line
+                || chunk.end_line < r.chunk.start_line
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Key of `path` in the store: the canonical path, or the one indexed path
+/// ending with it
+fn indexed_path(store: &VectorStore, path: &str) -> Result<String> {
+    let canonical = Path::new(path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    if store.files.contains_key(&canonical) {
+        return Ok(canonical);
+    }
+
+    let mut matches: Vec<&String> = store
+        .files
+        .keys()
+        .filter(|indexed| Path::new(indexed).ends_with(path))
+        .collect();
+    matches.sort();
+
+    match matches.as_slice() {
+        [] => bail!("File is not indexed: {}", path),
+        [indexed] => Ok(indexed.to_string()),
+        _ => {
+            let paths: Vec<String> = matches
+                .iter()
+                .take(10)
+                .map(|p| format!("  {}", p))
+                .collect();
+            bail!(
+                "{} indexed files end with {}; give more of the path:\n{}",
+                matches.len(),
+                path,
+                paths.join("\n")
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(
+        id: &str,
+        file: &str,
+        lines: (usize, usize),
+        symbol: &str,
+        embedding: Vec<f32>,
+    ) -> FileChunk {
+        FileChunk::test(id, file, lines)
+            .with_symbol(symbol)
+            .with_parent("Store")
+            .with_embedding(embedding)
+    }
+
+    fn store() -> VectorStore {
+        VectorStore::with_test_chunks(vec![
+            chunk("impl", "/r/src/store.rs", (1, 100), "Store", vec![1.0, 0.1]),
+            chunk("save", "/r/src/store.rs", (10, 30), "save", vec![1.0, 0.0]),
+            chunk("load", "/r/src/store.rs", (40, 60), "load", vec![0.9, 0.2]),
+            chunk("write", "/r/src/io.rs", (1, 20), "write", vec![0.95, 0.05]),
+            chunk("other", "/r/src/io.rs", (30, 40), "other", vec![0.0, 1.0]),
+        ])
+    }
+
+    #[test]
+    fn test_locate_chunk() {
+        let store = store();
+        assert_eq!(
+            parse_location("src/store.rs:12").unwrap(),
+            ("src/store.rs", 12)
+        );
+        assert!(parse_location("src/store.rs").is_err());
+
+        assert_eq!(chunk_at(&store, "src/store.rs", 12).unwrap().id, "save");
+        assert_eq!(chunk_at(&store, "src/store.rs", 35).unwrap().id, "impl");
+        assert_eq!(chunk_for_symbol(&store, "Store::load").unwrap().id, "load");
+
+        // A bare file name that several indexed files end with is ambiguous
+        let mut store = store;
+        store.add_file(crate::core::store::IndexedFile {
+            path: "/r/tests/store.rs".to_string(),
+            hash: String::new(),
+            chunks: Vec::new(),
+            indexed_at: String::new(),
+        });
+        let err = chunk_at(&store, "store.rs", 12).unwrap_err().to_string();
+        assert!(
+            err.starts_with("2 indexed files end with store.rs"),
+            "{}",
+            err
+        );
+        assert_eq!(chunk_at(&store, "src/store.rs", 12).unwrap().id, "save");
+        assert!(chunk_for_symbol(&store, "Other::load").is_err());
+    }
+
+    #[test]
+    fn test_similar_excludes_self_and_overlaps() {
+        let store = store();
+        let save = chunk_at(&store, "src/store.rs", 12).unwrap();
+        let results = similar_to(&store, save, 2, None);
+        let ids: Vec<&str> = results.iter().map(|r| r.chunk.id.as_str()).collect();
+        assert_eq!(ids, vec!["write", "load"]);
+    }
+}
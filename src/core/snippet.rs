@@ -0,0 +1,192 @@
+//! Result snippets from the current file on disk
+//!
+//! Stored chunk content is a snapshot from indexing time. When showing a
+//! result, the file is re-read so context lines can be added and edits since
+//! indexing are detected: if the file hash changed, the chunk is located
+//! again by content (nearest occurrence to its indexed position).
+
+use std::fs;
+
+use crate::core::store::{compute_file_hash, FileChunk, VectorStore};
+
+/// How the on-disk file relates to the indexed chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// File unchanged since indexing
+    Fresh,
+    /// File changed, but the chunk is still at its indexed lines
+    Changed,
+    /// File changed and the chunk moved; holds the indexed start line
+    Moved(usize),
+    /// Chunk content no longer exists in the file
+    Stale,
+    /// File could not be read
+    Missing,
+}
+
+impl Freshness {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Freshness::Fresh => "fresh",
+            Freshness::Changed => "changed",
+            Freshness::Moved(_) => "moved",
+            Freshness::Stale => "stale",
+            Freshness::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    /// Chunk location in the current file (indexed location if stale/missing)
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Context lines before the chunk, with line numbers
+    pub before: Vec<(usize, String)>,
+    /// Chunk lines, with line numbers
+    pub lines: Vec<(usize, String)>,
+    /// Context lines after the chunk, with line numbers
+    pub after: Vec<(usize, String)>,
+    pub freshness: Freshness,
+}
+
+/// Build a snippet for `chunk` with `before`/`after` lines of context
+pub fn load_snippet(
+    store: &VectorStore,
+    chunk: &FileChunk,
+    before: usize,
+    after: usize,
+) -> Snippet {
+    let Ok(content) = fs::read_to_string(&chunk.file_path) else {
+        return stored_snippet(chunk, Freshness::Missing);
+    };
+
+    let indexed_hash = store.get_file(&chunk.file_path).map(|f| f.hash.as_str());
+    let file_lines: Vec<&str> = content.lines().collect();
+
+    let (start, freshness) = if indexed_hash == Some(compute_file_hash(&content).as_str()) {
+        (chunk.start_line, Freshness::Fresh)
+    } else {
+        match locate(&file_lines, chunk) {
+            Some(start) if start == chunk.start_line => (start, Freshness::Changed),
+            Some(start) => (start, Freshness::Moved(chunk.start_line)),
+            None => return stored_snippet(chunk, Freshness::Stale),
+        }
+    };
+
+    let len = chunk.end_line.saturating_sub(chunk.start_line) + 1;
+    let end = (start + len - 1).min(file_lines.len());
+    let numbered = |from: usize, to: usize| -> Vec<(usize, String)> {
+        (from..=to)
+            .filter(|&n| n >= 1 && n <= file_lines.len())
+            .map(|n| (n, file_lines[n - 1].to_string()))
+            .collect()
+    };
+
+    Snippet {
+        start_line: start,
+        end_line: end,
+        before: if before > 0 && start > 1 {
+            numbered(start.saturating_sub(before).max(1), start - 1)
+        } else {
+            Vec::new()
+        },
+        lines: numbered(start, end),
+        after: if after > 0 {
+            numbered(end + 1, end + after)
+        } else {
+            Vec::new()
+        },
+        freshness,
+    }
+}
+
+/// Snippet from the stored chunk content, without context
+fn stored_snippet(chunk: &FileChunk, freshness: Freshness) -> Snippet {
+    Snippet {
+        start_line: chunk.start_line,
+        end_line: chunk.end_line,
+        before: Vec::new(),
+        lines: chunk
+            .content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (chunk.start_line + i, line.to_string()))
+            .collect(),
+        after: Vec::new(),
+        freshness,
+    }
+}
+
+/// Find the 1-based start line of the chunk's content in `file_lines`,
+/// preferring the occurrence nearest to the indexed start line
+fn locate(file_lines: &[&str], chunk: &FileChunk) -> Option<usize> {
+    let chunk_lines: Vec<&str> = chunk.content.lines().collect();
+    if chunk_lines.is_empty() || chunk_lines.len() > file_lines.len() {
+        return None;
+    }
+
+    (0..=file_lines.len() - chunk_lines.len())
+        .filter(|&i| file_lines[i..i + chunk_lines.len()] == chunk_lines[..])
+        .map(|i| i + 1)
+        .min_by_key(|&start| start.abs_diff(chunk.start_line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::IndexedFile;
+
+    fn setup(
+        name: &str,
+        indexed: &str,
+        current: &str,
+        start: usize,
+        end: usize,
+    ) -> (VectorStore, FileChunk) {
+        let dir =
+            std::env::temp_dir().join(format!("sgrep-snippet-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.rs").to_string_lossy().to_string();
+        fs::write(&path, current).unwrap();
+
+        let lines: Vec<&str> = indexed.lines().collect();
+        let chunk = FileChunk::test("c", &path, (start, end))
+            .with_content(&lines[start - 1..end].join("\n"));
+        let mut store = VectorStore::default();
+        store.add_file(IndexedFile {
+            path,
+            hash: compute_file_hash(indexed),
+            chunks: vec!["c".to_string()],
+            indexed_at: String::new(),
+        });
+        (store, chunk)
+    }
+
+    #[test]
+    fn test_fresh_with_context() {
+        let text = "a\nb\nfn x() {\n}\nc\nd";
+        let (store, chunk) = setup("fresh", text, text, 3, 4);
+        let snippet = load_snippet(&store, &chunk, 1, 5);
+
+        assert_eq!(snippet.freshness, Freshness::Fresh);
+        assert_eq!(snippet.before, vec![(2, "b".to_string())]);
+        assert_eq!(snippet.lines.len(), 2);
+        assert_eq!(snippet.after.len(), 2);
+    }
+
+    #[test]
+    fn test_moved_and_stale() {
+        let indexed = "a\nfn x() {\n}\nb";
+        let (store, chunk) = setup("moved", indexed, "new\nlines\na\nfn x() {\n}\nb", 2, 3);
+        let snippet = load_snippet(&store, &chunk, 0, 0);
+        assert_eq!(snippet.freshness, Freshness::Moved(2));
+        assert_eq!(snippet.start_line, 4);
+        assert_eq!(snippet.lines[0], (4, "fn x() {".to_string()));
+
+        let (store, chunk) = setup("stale", indexed, "a\nfn y() {\n}\nb", 2, 3);
+        let snippet = load_snippet(&store, &chunk, 2, 2);
+        assert_eq!(snippet.freshness, Freshness::Stale);
+        assert!(snippet.before.is_empty());
+    }
+}
@@ -0,0 +1,673 @@
+//! Stack trace and error message lookup
+//!
+//! Parses panics, tracebacks and exception stacks from Rust, Python,
+//! JavaScript/TypeScript, Go and Java into frames, maps each frame onto the
+//! indexed chunk at its file and line, and finds the string literals an
+//! error message was formatted from.
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::core::store::{FileChunk, VectorStore};
+
+/// Fixed characters a literal must share with a message to count as its
+/// raise site, unless it covers the whole message
+const MIN_FIXED_CHARS: usize = 8;
+
+/// Share of the message a literal's fixed text must cover
+const MIN_COVERAGE: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceFormat {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+    Java,
+}
+
+impl TraceFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceFormat::Rust => "rust",
+            TraceFormat::Python => "python",
+            TraceFormat::JavaScript => "javascript",
+            TraceFormat::Go => "go",
+            TraceFormat::Java => "java",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Frame {
+    pub file: String,
+    pub line: Option<usize>,
+    pub function: Option<String>,
+}
+
+/// A parsed trace. Frames run innermost (where the error happened) first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StackTrace {
+    pub format: Option<TraceFormat>,
+    /// Exception class (`KeyError`, `java.lang.IllegalStateException`)
+    pub error_type: Option<String>,
+    pub message: Option<String>,
+    pub frames: Vec<Frame>,
+}
+
+impl StackTrace {
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty() && self.message.is_none()
+    }
+}
+
+pub struct TraceParser {
+    rust_panic: Regex,
+    rust_backtrace_fn: Regex,
+    rust_diagnostic: Regex,
+    rust_error: Regex,
+    at_location: Regex,
+    python_frame: Regex,
+    js_frame: Regex,
+    java_frame: Regex,
+    go_location: Regex,
+    go_panic: Regex,
+    exception: Regex,
+}
+
+impl TraceParser {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            rust_panic: Regex::new(r"panicked at (?:'(.*)', )?(\S+?):(\d+):\d+:?\s*$")?,
+            rust_backtrace_fn: Regex::new(r"^\s*\d+:\s+(?:0x[0-9a-fA-F]+ - )?(\S.*)$")?,
+            rust_diagnostic: Regex::new(r"^\s*--> (\S+?):(\d+):\d+")?,
+            rust_error: Regex::new(r"^error(?:\[\w+\])?: (.+)$")?,
+            at_location: Regex::new(r"^\s*at (\S+?):(\d+)(?::\d+)?$")?,
+            python_frame: Regex::new(r#"^\s*File "([^"]+)", line (\d+)(?:, in (\S+))?"#)?,
+            js_frame: Regex::new(r"^\s*at (?:async )?(.+?) \((\S+?):(\d+):\d+\)$")?,
+            java_frame: Regex::new(r"^\s*at ([\w$.<>/]+)\(([^():\s]+\.\w+)(?::(\d+))?\)$")?,
+            go_location: Regex::new(r"^\s+(\S+\.go):(\d+)(?: \+0x[0-9a-fA-F]+)?$")?,
+            go_panic: Regex::new(r"^(?:panic|fatal error): (.+?)(?: \[recovered\])?$")?,
+            exception: Regex::new(
+                r#"^(?:Uncaught |Caused by: |Exception in thread "[^"]*" )?((?:[A-Za-z_$][\w$]*\.)*[A-Z][\w$]*(?:Error|Exception|Exit|Interrupt|Warning)):?\s?(.*)$"#,
+            )?,
+        })
+    }
+
+    pub fn parse(&self, text: &str) -> StackTrace {
+        let mut trace = StackTrace::default();
+        let lines: Vec<&str> = text.lines().collect();
+        // Rust backtrace function waiting for its `at file:line`
+        let mut pending_fn: Option<String> = None;
+        // `at file:line` lines with no function, which prose can produce too
+        let mut bare_locations = 0;
+        let mut panic_message_next = false;
+
+        for (i, raw) in lines.iter().enumerate() {
+            let line = raw.trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if panic_message_next {
+                panic_message_next = false;
+                if !line.starts_with("note:") && !line.starts_with("stack backtrace") {
+                    trace.message = Some(line.trim().to_string());
+                    continue;
+                }
+            }
+
+            if let Some(caps) = self.rust_panic.captures(line) {
+                trace.format = Some(TraceFormat::Rust);
+                match caps.get(1) {
+                    Some(message) => trace.message = Some(message.as_str().to_string()),
+                    None => panic_message_next = true,
+                }
+                push(&mut trace, &caps[2], caps[3].parse().ok(), None);
+            } else if let Some(caps) = self.python_frame.captures(line) {
+                set_format(&mut trace, TraceFormat::Python);
+                let function = caps
+                    .get(3)
+                    .map(|m| m.as_str().to_string())
+                    .filter(|f| f != "<module>");
+                push(&mut trace, &caps[1], caps[2].parse().ok(), function);
+            } else if let Some(caps) = self.java_frame.captures(line) {
+                set_format(&mut trace, TraceFormat::Java);
+                let file = java_path(&caps[1], &caps[2]);
+                let line = caps.get(3).and_then(|m| m.as_str().parse().ok());
+                push(&mut trace, &file, line, Some(caps[1].to_string()));
+            } else if let Some(caps) = self.js_frame.captures(line) {
+                set_format(&mut trace, TraceFormat::JavaScript);
+                push(
+                    &mut trace,
+                    &caps[2],
+                    caps[3].parse().ok(),
+                    Some(caps[1].to_string()),
+                );
+            } else if let Some(caps) = self.at_location.captures(line) {
+                // Rust backtrace location, or a JS frame without a function
+                let function = pending_fn.take();
+                if function.is_none() {
+                    bare_locations += 1;
+                }
+                if trace.format.is_none() {
+                    trace.format = Some(if function.is_some() {
+                        TraceFormat::Rust
+                    } else {
+                        TraceFormat::JavaScript
+                    });
+                }
+                push(&mut trace, &caps[1], caps[2].parse().ok(), function);
+            } else if let Some(caps) = self.go_location.captures(line) {
+                set_format(&mut trace, TraceFormat::Go);
+                let function = i.checked_sub(1).and_then(|prev| go_function(lines[prev]));
+                push(&mut trace, &caps[1], caps[2].parse().ok(), function);
+            } else if let Some(caps) = self.rust_diagnostic.captures(line) {
+                set_format(&mut trace, TraceFormat::Rust);
+                push(&mut trace, &caps[1], caps[2].parse().ok(), None);
+            } else if let Some(caps) = self.rust_backtrace_fn.captures(line) {
+                pending_fn = Some(caps[1].trim().to_string());
+            } else if let Some(caps) = self.go_panic.captures(line) {
+                set_format(&mut trace, TraceFormat::Go);
+                trace.message.get_or_insert_with(|| caps[1].to_string());
+            } else if let Some(caps) = self.rust_error.captures(line) {
+                set_format(&mut trace, TraceFormat::Rust);
+                trace.message.get_or_insert_with(|| caps[1].to_string());
+            } else if let Some(caps) = self.exception.captures(line.trim()) {
+                // The last exception line is the one raised (Python) or the
+                // root cause (Java `Caused by:`)
+                trace.error_type = Some(caps[1].to_string());
+                let message = caps[2].trim();
+                if !message.is_empty() {
+                    trace.message = Some(message.to_string());
+                }
+            }
+        }
+
+        // One bare `at src/x.rs:10` is a location, not a stack
+        if bare_locations == 1 && trace.frames.len() == 1 {
+            trace.frames.clear();
+            if trace.message.is_none() && trace.error_type.is_none() {
+                trace.format = None;
+            }
+        }
+        if trace.format == Some(TraceFormat::Python) {
+            trace.frames.reverse();
+        }
+        trace
+    }
+}
+
+fn set_format(trace: &mut StackTrace, format: TraceFormat) {
+    trace.format.get_or_insert(format);
+}
+
+fn push(trace: &mut StackTrace, file: &str, line: Option<usize>, function: Option<String>) {
+    let file = normalize_path(file);
+    // The panic location repeats as the first backtrace frame
+    if let Some(existing) = trace
+        .frames
+        .iter_mut()
+        .find(|f| f.file == file && f.line == line)
+    {
+        if existing.function.is_none() {
+            existing.function = function;
+        }
+        return;
+    }
+    trace.frames.push(Frame {
+        file,
+        line,
+        function,
+    });
+}
+
+/// `com.example.Foo$Inner.run` + `Foo.java` → `com/example/Foo.java`
+fn java_path(method: &str, file: &str) -> String {
+    let parts: Vec<&str> = method.split('.').collect();
+    match parts.len() {
+        0..=2 => file.to_string(),
+        n => format!("{}/{}", parts[..n - 2].join("/"), file),
+    }
+}
+
+/// Function on the line above a Go frame location
+fn go_function(line: &str) -> Option<String> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let line = line.trim();
+    let line = line.strip_prefix("created by ").unwrap_or(line);
+    let line = line.split(" in goroutine").next().unwrap_or(line);
+    let name = match line.rfind('(') {
+        Some(i) if line.ends_with(')') && !line[i..].starts_with("(*") => &line[..i],
+        _ => line,
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Strip URL schemes, `./` and backslashes from a trace path
+fn normalize_path(path: &str) -> String {
+    let path = path.trim();
+    let path = ["webpack:///", "webpack://", "file://"]
+        .iter()
+        .find_map(|scheme| path.strip_prefix(scheme))
+        .unwrap_or(path);
+    let path = path.split('?').next().unwrap_or(path);
+    let path = path.replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect()
+}
+
+/// Indexed file a trace path refers to: the one sharing the longest run of
+/// trailing path components with it. `None` when no file has the same name
+/// or the best candidates tie.
+pub fn indexed_file<'a>(store: &'a VectorStore, path: &str) -> Option<&'a str> {
+    let wanted = components(path);
+    let mut best: Option<&str> = None;
+    let mut best_len = 0;
+    let mut tied = false;
+
+    for indexed in store.files.keys() {
+        let shared = components(indexed)
+            .iter()
+            .rev()
+            .zip(wanted.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if shared == 0 || shared < best_len {
+            continue;
+        }
+        tied = shared == best_len;
+        best_len = shared;
+        best = Some(indexed);
+    }
+
+    best.filter(|_| !tied)
+}
+
+/// Last name in a qualified function (`app::load::{{closure}}::h1f2e` → `load`)
+fn short_name(function: &str) -> Option<&str> {
+    let is_hash = |part: &str| {
+        part.len() == 17
+            && part.starts_with('h')
+            && part[1..].chars().all(|c| c.is_ascii_hexdigit())
+    };
+    function
+        .split([':', '.', '/'])
+        .map(|part| part.trim_matches(['(', ')', '*']))
+        .rfind(|part| !part.is_empty() && !part.starts_with("{{") && !is_hash(part))
+}
+
+/// A frame and the indexed code it points at
+#[derive(Debug, Clone)]
+pub struct FrameMatch {
+    pub frame: Frame,
+    /// Indexed file the frame's path resolved to
+    pub file: Option<String>,
+    /// Smallest chunk containing the line, or defining the function
+    pub chunk: Option<FileChunk>,
+}
+
+pub fn map_frames(store: &VectorStore, frames: &[Frame]) -> Vec<FrameMatch> {
+    frames
+        .iter()
+        .map(|frame| {
+            let file = indexed_file(store, &frame.file);
+            let chunks = file.map(|f| store.chunks_for_file(f)).unwrap_or_default();
+            let chunk = match frame.line {
+                Some(line) => chunks
+                    .into_iter()
+                    .filter(|c| c.start_line <= line && line <= c.end_line)
+                    .min_by_key(|c| c.end_line - c.start_line),
+                None => {
+                    let name = frame.function.as_deref().and_then(short_name);
+                    chunks
+                        .into_iter()
+                        .find(|c| name.is_some() && c.symbol_name.as_deref() == name)
+                }
+            };
+            FrameMatch {
+                frame: frame.clone(),
+                file: file.map(str::to_string),
+                chunk: chunk.cloned(),
+            }
+        })
+        .collect()
+}
+
+/// A string literal an error message was formatted from
+#[derive(Debug, Clone)]
+pub struct RaiseSite {
+    pub chunk: FileChunk,
+    pub line: usize,
+    pub literal: String,
+    /// Share of the message covered by the literal's fixed text
+    pub coverage: f32,
+}
+
+pub struct MessageMatcher {
+    literal: Regex,
+    placeholder: Regex,
+}
+
+impl MessageMatcher {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            literal: Regex::new(
+                r#""((?:[^"\\]|\\.)*)"|`((?:[^`\\]|\\.)*)`|'((?:[^'\\]|\\.){2,})'"#,
+            )?,
+            // `${expr}`, `{}` / `{name}` / `{0:>4}`, printf `%s` / `%-5d` / `%v`
+            placeholder: Regex::new(r"\$\{[^}]*\}|\{[^{}]*\}|%[-+ #0]*\d*(?:\.\d+)?[a-zA-Z]")?,
+        })
+    }
+
+    /// Share of `message` covered by `literal` when the literal's fixed
+    /// fragments appear in it in order
+    pub fn coverage(&self, literal: &str, message: &str) -> Option<f32> {
+        let message = message.to_lowercase();
+        let literal = unescape(literal).to_lowercase();
+        let mut pos = 0;
+        let mut fixed = 0;
+        for fragment in self.placeholder.split(&literal) {
+            if fragment.trim().is_empty() {
+                continue;
+            }
+            let found = message[pos..].find(fragment)?;
+            pos += found + fragment.len();
+            fixed += fragment.trim().chars().count();
+        }
+
+        let total = message.trim().chars().count();
+        if fixed == 0 || (fixed < MIN_FIXED_CHARS && fixed < total) {
+            return None;
+        }
+        let coverage = fixed as f32 / total as f32;
+        (coverage >= MIN_COVERAGE).then_some(coverage.min(1.0))
+    }
+
+    /// Literals in indexed code that `message` was formatted from, best
+    /// coverage first, one per source line
+    pub fn raise_sites(&self, store: &VectorStore, message: &str, limit: usize) -> Vec<RaiseSite> {
+        let message = message.lines().next().unwrap_or("").trim();
+        if message.is_empty() {
+            return Vec::new();
+        }
+
+        let mut best: HashMap<(&str, usize), RaiseSite> = HashMap::new();
+        for chunk in store.all_chunks() {
+            for (offset, text) in chunk.content.lines().enumerate() {
+                let Some((literal, coverage)) = self
+                    .literal
+                    .captures_iter(text)
+                    .filter_map(|caps| {
+                        let literal = caps.get(1).or(caps.get(2)).or(caps.get(3))?.as_str();
+                        Some((literal, self.coverage(literal, message)?))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                else {
+                    continue;
+                };
+
+                let line = chunk.start_line + offset;
+                let site = RaiseSite {
+                    chunk: chunk.clone(),
+                    line,
+                    literal: literal.to_string(),
+                    coverage,
+                };
+                // Nested chunks share lines; keep the smallest
+                best.entry((&chunk.file_path, line))
+                    .and_modify(|existing| {
+                        if chunk.end_line - chunk.start_line
+                            < existing.chunk.end_line - existing.chunk.start_line
+                        {
+                            *existing = site.clone();
+                        }
+                    })
+                    .or_insert(site);
+            }
+        }
+
+        let mut sites: Vec<RaiseSite> = best.into_values().collect();
+        sites.sort_by(|a, b| {
+            b.coverage
+                .total_cmp(&a.coverage)
+                .then(a.chunk.file_path.cmp(&b.chunk.file_path))
+                .then(a.line.cmp(&b.line))
+        });
+        sites.truncate(limit);
+        sites
+    }
+}
+
+fn unescape(literal: &str) -> String {
+    literal
+        .replace("\\n", " ")
+        .replace("\\t", " ")
+        .replace("\\\"", "\"")
+        .replace("\\'", "'")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(file: &str, line: usize, function: Option<&str>) -> Frame {
+        Frame {
+            file: file.to_string(),
+            line: Some(line),
+            function: function.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse_rust_panic() {
+        let parser = TraceParser::new().unwrap();
+        let trace = parser.parse(
+            "thread 'main' panicked at src/core/store.rs:42:9:\n\
+             index out of bounds: the len is 3 but the index is 5\n\
+             stack backtrace:\n   \
+               0: searchgrep::core::store::VectorStore::load\n             \
+                  at ./src/core/store.rs:42:9\n   \
+               1: searchgrep::main\n             \
+                  at ./src/main.rs:10:5\n",
+        );
+        assert_eq!(trace.format, Some(TraceFormat::Rust));
+        assert_eq!(
+            trace.message.as_deref(),
+            Some("index out of bounds: the len is 3 but the index is 5")
+        );
+        assert_eq!(
+            trace.frames,
+            vec![
+                frame(
+                    "src/core/store.rs",
+                    42,
+                    Some("searchgrep::core::store::VectorStore::load")
+                ),
+                frame("src/main.rs", 10, Some("searchgrep::main")),
+            ]
+        );
+
+        let old = parser.parse("thread 'main' panicked at 'no store', src/lib.rs:3:5");
+        assert_eq!(old.message.as_deref(), Some("no store"));
+        assert_eq!(old.frames, vec![frame("src/lib.rs", 3, None)]);
+    }
+
+    #[test]
+    fn test_parse_python_traceback() {
+        let trace = TraceParser::new().unwrap().parse(
+            "Traceback (most recent call last):\n  \
+               File \"/srv/app/main.py\", line 8, in <module>\n    \
+                 run()\n  \
+               File \"/srv/app/users.py\", line 21, in load_user\n    \
+                 raise KeyError(f\"unknown user {uid}\")\n\
+             KeyError: 'unknown user 7'",
+        );
+        assert_eq!(trace.format, Some(TraceFormat::Python));
+        assert_eq!(trace.error_type.as_deref(), Some("KeyError"));
+        assert_eq!(trace.message.as_deref(), Some("'unknown user 7'"));
+        // Innermost first
+        assert_eq!(
+            trace.frames,
+            vec![
+                frame("/srv/app/users.py", 21, Some("load_user")),
+                frame("/srv/app/main.py", 8, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_js_go_java() {
+        let parser = TraceParser::new().unwrap();
+
+        let js = parser.parse(
+            "TypeError: Cannot read properties of undefined (reading 'id')\n    \
+               at getUser (webpack:///src/api/users.ts:14:22)\n    \
+               at async Server.handle (/app/dist/server.js:88:5)\n    \
+               at /app/dist/index.js:3:1",
+        );
+        assert_eq!(js.format, Some(TraceFormat::JavaScript));
+        assert_eq!(js.error_type.as_deref(), Some("TypeError"));
+        assert_eq!(js.frames[0], frame("src/api/users.ts", 14, Some("getUser")));
+        assert_eq!(js.frames[1].function.as_deref(), Some("Server.handle"));
+        assert_eq!(js.frames[2], frame("/app/dist/index.js", 3, None));
+
+        let go = parser.parse(
+            "panic: runtime error: index out of range [5] with length 3\n\n\
+             goroutine 1 [running]:\n\
+             main.(*Server).handle(0xc000010000, {0x0, 0x0})\n\
+             \t/home/me/app/server.go:27 +0x1d\n\
+             main.main()\n\
+             \t/home/me/app/main.go:9 +0x25",
+        );
+        assert_eq!(go.format, Some(TraceFormat::Go));
+        assert_eq!(
+            go.message.as_deref(),
+            Some("runtime error: index out of range [5] with length 3")
+        );
+        assert_eq!(
+            go.frames,
+            vec![
+                frame("/home/me/app/server.go", 27, Some("main.(*Server).handle")),
+                frame("/home/me/app/main.go", 9, Some("main.main")),
+            ]
+        );
+
+        let java = parser.parse(
+            "Exception in thread \"main\" java.lang.IllegalStateException: wrapper\n\
+             \tat com.example.Repo.save(Repo.java:42)\n\
+             \tat java.base/java.lang.Thread.run(Native Method)\n\
+             Caused by: java.io.IOException: disk full on /data\n\
+             \tat com.example.io.Disk$Writer.write(Disk.java:7)",
+        );
+        assert_eq!(java.format, Some(TraceFormat::Java));
+        assert_eq!(java.error_type.as_deref(), Some("java.io.IOException"));
+        assert_eq!(java.message.as_deref(), Some("disk full on /data"));
+        assert_eq!(
+            java.frames,
+            vec![
+                frame("com/example/Repo.java", 42, Some("com.example.Repo.save")),
+                frame(
+                    "com/example/io/Disk.java",
+                    7,
+                    Some("com.example.io.Disk$Writer.write")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_query_is_not_a_trace() {
+        let parser = TraceParser::new().unwrap();
+        assert!(parser.parse("where are embeddings cached").is_empty());
+        assert!(parser.parse("fn:load lang:rust").frames.is_empty());
+        assert!(parser.parse("at src/store.rs:250").is_empty());
+        assert_eq!(
+            parser
+                .parse("    at src/store.rs:250:3\n    at src/main.rs:9:1")
+                .frames
+                .len(),
+            2
+        );
+    }
+
+    fn store() -> VectorStore {
+        let load = "fn load() {\n    bail!(\"Store {} is corrupt: {}\", name, err);\n}";
+        VectorStore::with_test_chunks(vec![
+            FileChunk::test("impl", "/r/src/core/store.rs", (1, 80)).with_symbol("VectorStore"),
+            FileChunk::test("load", "/r/src/core/store.rs", (40, 50))
+                .with_symbol("load")
+                .with_content(load),
+            FileChunk::test("main", "/r/src/main.rs", (1, 20)).with_symbol("main"),
+            FileChunk::test("mod", "/r/src/core/mod.rs", (1, 5)).with_symbol("core"),
+            FileChunk::test("cli", "/r/src/cli/mod.rs", (1, 5)).with_symbol("cli"),
+        ])
+    }
+
+    #[test]
+    fn test_frames_map_by_path_suffix() {
+        let store = store();
+        assert_eq!(
+            indexed_file(&store, "/build/ci/src/core/store.rs"),
+            Some("/r/src/core/store.rs")
+        );
+        assert_eq!(
+            indexed_file(&store, "./src/main.rs"),
+            Some("/r/src/main.rs")
+        );
+        // Only the file name matches two files
+        assert_eq!(indexed_file(&store, "mod.rs"), None);
+        assert_eq!(indexed_file(&store, "src/other.rs"), None);
+
+        let matches = map_frames(
+            &store,
+            &[
+                frame("src/core/store.rs", 44, None),
+                Frame {
+                    file: "src/core/store.rs".to_string(),
+                    line: None,
+                    function: Some("searchgrep::core::store::load::h0123456789abcdef".to_string()),
+                },
+                frame("/rustc/library/std/panicking.rs", 10, None),
+            ],
+        );
+        assert_eq!(matches[0].chunk.as_ref().unwrap().id, "load");
+        assert_eq!(matches[1].chunk.as_ref().unwrap().id, "load");
+        assert!(matches[2].file.is_none() && matches[2].chunk.is_none());
+    }
+
+    #[test]
+    fn test_message_matches_format_literal() {
+        let matcher = MessageMatcher::new().unwrap();
+        let message = "Store default is corrupt: unexpected end of file";
+        assert!(matcher
+            .coverage("Store {} is corrupt: {}", message)
+            .is_some());
+        assert!(matcher
+            .coverage("Store %s is corrupt: %v", message)
+            .is_some());
+        assert!(matcher
+            .coverage("Store ${name} is corrupt: ${err}", message)
+            .is_some());
+        // Fragments out of order, or too little fixed text
+        assert!(matcher.coverage("is corrupt: {} Store", message).is_none());
+        assert!(matcher.coverage("{}: {}", message).is_none());
+
+        let sites = matcher.raise_sites(&store(), message, 5);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].chunk.id, "load");
+        assert_eq!(sites[0].line, 41);
+        assert_eq!(sites[0].literal, "Store {} is corrupt: {}");
+    }
+}
@@ -7,6 +7,8 @@ use std::path::PathBuf;
 
 use crate::core::config::Config;
 use crate::core::graph::KnowledgeGraph;
+use crate::core::levels::LevelIndex;
+use crate::core::trigram::{TrigramIndex, TrigramQuery};
 use crate::core::vector_index::VectorIndex;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +34,47 @@ pub struct FileChunk {
     pub hierarchy_path: Option<String>,
 }
 
+#[cfg(test)]
+impl FileChunk {
+    /// Empty Rust function chunk of `file_path` spanning `lines`
+    pub fn test(id: &str, file_path: &str, lines: (usize, usize)) -> Self {
+        Self {
+            id: id.to_string(),
+            file_path: file_path.to_string(),
+            content: String::new(),
+            start_line: lines.0,
+            end_line: lines.1,
+            chunk_type: "function".to_string(),
+            language: Some("rust".to_string()),
+            embedding: Vec::new(),
+            token_embeddings: None,
+            symbol_name: None,
+            parent_name: None,
+            hierarchy_path: None,
+        }
+    }
+
+    pub fn with_content(mut self, content: &str) -> Self {
+        self.content = content.to_string();
+        self
+    }
+
+    pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = embedding;
+        self
+    }
+
+    pub fn with_symbol(mut self, symbol: &str) -> Self {
+        self.symbol_name = Some(symbol.to_string());
+        self
+    }
+
+    pub fn with_parent(mut self, parent: &str) -> Self {
+        self.parent_name = Some(parent.to_string());
+        self
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedFile {
     pub path: String,
@@ -63,6 +106,12 @@ pub struct VectorStore {
     ann_threshold: usize,
     /// Knowledge graph for relationships
     pub graph: KnowledgeGraph,
+    /// Trigram index for literal/regex search - `None` until built for
+    /// stores saved before it existed
+    trigram_index: Option<TrigramIndex>,
+    /// File and directory vectors, re-pooled from changed files by the
+    /// indexer (`sync_levels`)
+    level_index: LevelIndex,
 }
 
 impl Default for VectorStore {
@@ -75,6 +124,8 @@ impl Default for VectorStore {
             ann_index: None,
             ann_threshold: 1000, // Use brute force below 1K chunks
             graph: KnowledgeGraph::new(),
+            trigram_index: Some(TrigramIndex::new()),
+            level_index: LevelIndex::default(),
         }
     }
 }
@@ -101,6 +152,40 @@ impl VectorStore {
         Ok(config_dir.join(format!("{}.graph.bin", name)))
     }
 
+    /// Trigram index path
+    pub fn trigram_path(store_name: Option<&str>) -> Result<PathBuf> {
+        let config_dir = Config::config_dir()?;
+        let name = store_name.unwrap_or("default");
+        Ok(config_dir.join(format!("{}.trigram.bin", name)))
+    }
+
+    /// File/directory vector path
+    pub fn levels_path(store_name: Option<&str>) -> Result<PathBuf> {
+        let config_dir = Config::config_dir()?;
+        let name = store_name.unwrap_or("default");
+        Ok(config_dir.join(format!("{}.levels.bin", name)))
+    }
+
+    /// Changes whenever the store is saved: the store file's size and
+    /// modification time. 0 if nothing has been indexed.
+    pub fn generation(store_name: Option<&str>) -> Result<u64> {
+        let bin_path = Self::store_path_bin(store_name)?;
+        let path = if bin_path.exists() {
+            bin_path
+        } else {
+            Self::store_path(store_name)?
+        };
+        let Ok(metadata) = fs::metadata(&path) else {
+            return Ok(0);
+        };
+        let modified = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Ok(modified ^ metadata.len().rotate_left(48))
+    }
+
     /// Load store - prefers binary format, falls back to JSON
     pub fn load(store_name: Option<&str>) -> Result<Self> {
         let bin_path = Self::store_path_bin(store_name)?;
@@ -125,6 +210,8 @@ impl VectorStore {
                 }
             }
 
+            store.load_trigram_index(store_name)?;
+            store.load_level_index(store_name)?;
             return Ok(store);
         }
 
@@ -165,6 +252,56 @@ impl VectorStore {
         Ok(KnowledgeGraph::new())
     }
 
+    /// Load the trigram index if present and saved with the same files and
+    /// chunks (see `fingerprint`)
+    fn load_trigram_index(&mut self, store_name: Option<&str>) -> Result<()> {
+        let path = Self::trigram_path(store_name)?;
+        if path.exists() {
+            if let Ok(data) = fs::read(&path) {
+                if let Ok((fingerprint, index)) =
+                    bincode::deserialize::<(String, TrigramIndex)>(&data)
+                {
+                    if fingerprint == self.fingerprint() {
+                        self.trigram_index = Some(index);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Hash of every indexed file's path, content hash and chunk ids, so
+    /// side files can tell whether they were saved with these chunks
+    fn fingerprint(&self) -> String {
+        let mut files: Vec<&IndexedFile> = self.files.values().collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut hasher = Sha256::new();
+        for file in files {
+            hasher.update(file.path.as_bytes());
+            hasher.update([0]);
+            hasher.update(file.hash.as_bytes());
+            for id in &file.chunks {
+                hasher.update([0]);
+                hasher.update(id.as_bytes());
+            }
+            hasher.update([1]);
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Load file/directory vectors if present; the next index run pools
+    /// any that are missing or stale
+    fn load_level_index(&mut self, store_name: Option<&str>) -> Result<()> {
+        let path = Self::levels_path(store_name)?;
+        if let Ok(data) = fs::read(&path) {
+            if let Ok(index) = bincode::deserialize::<LevelIndex>(&data) {
+                self.level_index = index;
+            }
+        }
+        Ok(())
+    }
+
     /// Convert from serializable data
     fn from_data(data: VectorStoreData) -> Self {
         Self {
@@ -175,6 +312,8 @@ impl VectorStore {
             ann_index: None,
             ann_threshold: 1000,
             graph: KnowledgeGraph::new(),
+            trigram_index: None,
+            level_index: LevelIndex::default(),
         }
     }
 
@@ -204,6 +343,24 @@ impl VectorStore {
         let graph_data = bincode::serialize(&self.graph)?;
         fs::write(graph_path, graph_data)?;
 
+        // Save trigram index separately, building it for older stores
+        let trigram_path = Self::trigram_path(store_name)?;
+        let fingerprint = self.fingerprint();
+        let trigram_data = match self.trigram_index {
+            Some(ref index) if index.needs_compaction() => {
+                bincode::serialize(&(&fingerprint, &index.compacted()))?
+            }
+            Some(ref index) => bincode::serialize(&(&fingerprint, index))?,
+            None => bincode::serialize(&(&fingerprint, &self.build_trigram_index()))?,
+        };
+        fs::write(trigram_path, trigram_data)?;
+
+        // Save file/directory vectors separately
+        fs::write(
+            Self::levels_path(store_name)?,
+            bincode::serialize(&self.level_index)?,
+        )?;
+
         Ok(())
     }
 
@@ -280,12 +437,50 @@ impl VectorStore {
             .unwrap_or(false)
     }
 
+    fn build_trigram_index(&self) -> TrigramIndex {
+        let mut index = TrigramIndex::new();
+        for chunk in self.chunks.values() {
+            index.add(&chunk.id, &chunk.content);
+        }
+        index
+    }
+
+    /// File and directory vectors as of the last `sync_levels`
+    pub fn level_index(&self) -> &LevelIndex {
+        &self.level_index
+    }
+
+    /// Name texts of files changed since the last `sync_levels`, to embed
+    /// with `levels::embed_names`
+    pub fn stale_level_names(&self) -> Vec<(String, String)> {
+        self.level_index.stale_names(&self.files, &self.chunks)
+    }
+
+    /// Re-pool the vectors of changed files with their embedded `names` and
+    /// drop removed files
+    pub fn sync_levels(&mut self, names: &HashMap<String, Vec<f32>>) {
+        self.level_index.sync(&self.files, &self.chunks, names);
+    }
+
+    /// Chunks that may match a trigram query, or `None` when every chunk
+    /// must be checked (no index, or the query has no usable trigrams)
+    pub fn trigram_candidates(&self, query: &TrigramQuery) -> Option<Vec<&FileChunk>> {
+        let ids = self.trigram_index.as_ref()?.candidates(query)?;
+        Some(
+            ids.into_iter()
+                .filter_map(|id| self.chunks.get(id))
+                .collect(),
+        )
+    }
+
     pub fn clear(&mut self) {
         self.files.clear();
         self.chunks.clear();
         self.bm25_idf.clear();
         self.doc_count = 0;
         self.graph.clear();
+        self.trigram_index = Some(TrigramIndex::new());
+        self.level_index = LevelIndex::default();
     }
 
     pub fn add_file(&mut self, file: IndexedFile) {
@@ -293,12 +488,18 @@ impl VectorStore {
     }
 
     pub fn add_chunk(&mut self, chunk: FileChunk) {
+        if let Some(ref mut index) = self.trigram_index {
+            index.add(&chunk.id, &chunk.content);
+        }
         self.chunks.insert(chunk.id.clone(), chunk);
     }
 
     pub fn remove_file(&mut self, path: &str) {
         if let Some(file) = self.files.remove(path) {
             for chunk_id in file.chunks {
+                if let Some(ref mut index) = self.trigram_index {
+                    index.remove(&chunk_id);
+                }
                 self.chunks.remove(&chunk_id);
             }
         }
@@ -369,6 +570,29 @@ impl VectorStore {
     }
 }
 
+#[cfg(test)]
+impl VectorStore {
+    /// Store holding `chunks`, with one indexed file per path in chunk order
+    pub fn with_test_chunks(chunks: Vec<FileChunk>) -> Self {
+        let mut store = Self::default();
+        for chunk in chunks {
+            store
+                .files
+                .entry(chunk.file_path.clone())
+                .or_insert_with(|| IndexedFile {
+                    path: chunk.file_path.clone(),
+                    hash: String::new(),
+                    chunks: Vec::new(),
+                    indexed_at: String::new(),
+                })
+                .chunks
+                .push(chunk.id.clone());
+            store.add_chunk(chunk);
+        }
+        store
+    }
+}
+
 pub fn compute_file_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
@@ -380,3 +604,35 @@ pub fn generate_chunk_id(file_path: &str, start_line: usize, end_line: usize) ->
     hasher.update(format!("{}:{}:{}", file_path, start_line, end_line).as_bytes());
     hex::encode(&hasher.finalize()[..8])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_tracks_file_content_and_chunks() {
+        let mut store = VectorStore::with_test_chunks(vec![
+            FileChunk::test("a1", "src/a.rs", (1, 5)),
+            FileChunk::test("b1", "src/b.rs", (1, 5)),
+        ]);
+        let original = store.fingerprint();
+        assert_eq!(original, store.fingerprint());
+
+        // Same chunk count, different content
+        store.files.get_mut("src/a.rs").unwrap().hash = "edited".to_string();
+        let edited = store.fingerprint();
+        assert_ne!(original, edited);
+
+        // Same chunk count, different chunk
+        store.remove_file("src/b.rs");
+        store.add_chunk(FileChunk::test("c1", "src/c.rs", (1, 5)));
+        store.add_file(IndexedFile {
+            path: "src/c.rs".to_string(),
+            hash: String::new(),
+            chunks: vec!["c1".to_string()],
+            indexed_at: String::new(),
+        });
+        assert_eq!(store.chunk_count(), 2);
+        assert_ne!(edited, store.fingerprint());
+    }
+}
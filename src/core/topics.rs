@@ -0,0 +1,440 @@
+//! Topic map of the codebase
+//!
+//! Chunk embeddings are clustered with spherical k-means. Each cluster is
+//! labelled with the identifiers that are frequent in it but rare elsewhere
+//! (tf-idf over chunks) and with the directories its chunks come from.
+//! Directories whose chunks fall into several topics are reported as mixed.
+
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::core::filter::SearchFilter;
+use crate::core::store::{FileChunk, VectorStore};
+
+/// Share of a directory's chunks a topic needs to count towards mixing
+const MIXED_SHARE: f32 = 0.2;
+/// Directories with fewer chunks are never reported as mixed
+const MIXED_MIN_CHUNKS: usize = 4;
+
+/// Words that say nothing about what the code does
+const STOPWORDS: &[&str] = &[
+    "and", "as", "async", "await", "bool", "break", "case", "catch", "class", "const", "continue",
+    "def", "default", "else", "enum", "err", "except", "export", "false", "fn", "for", "from",
+    "func", "function", "if", "impl", "import", "in", "int", "let", "match", "mod", "mut", "new",
+    "none", "not", "null", "ok", "or", "package", "pass", "pub", "return", "self", "some",
+    "static", "str", "string", "struct", "super", "switch", "the", "this", "throw", "true", "try",
+    "type", "use", "usize", "var", "vec", "void", "where", "while", "with", "yield",
+];
+
+#[derive(Debug, Clone)]
+pub struct TopicOptions {
+    /// Number of topics; derived from the chunk count when unset
+    pub k: Option<usize>,
+    /// Maximum k-means iterations
+    pub iterations: usize,
+    /// Identifiers and path prefixes kept per topic
+    pub labels: usize,
+}
+
+impl Default for TopicOptions {
+    fn default() -> Self {
+        Self {
+            k: None,
+            iterations: 25,
+            labels: 5,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Topic {
+    /// 1-based, largest topic first
+    pub id: usize,
+    /// Number of chunks
+    pub size: usize,
+    /// Most distinctive identifiers
+    pub terms: Vec<String>,
+    /// Directory prefixes and the share of the topic's chunks under each
+    pub paths: Vec<(String, f32)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectoryTopics {
+    /// Directory relative to the common root of the clustered files
+    pub path: String,
+    pub chunks: usize,
+    /// Topic ids and the share of the directory's chunks in each, largest first
+    pub topics: Vec<(usize, f32)>,
+    /// Several topics each hold a sizeable share of the directory
+    pub mixed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TopicMap {
+    pub root: String,
+    pub topics: Vec<Topic>,
+    pub directories: Vec<DirectoryTopics>,
+}
+
+/// Cluster the indexed chunks (matching `filter`) into topics
+pub fn topic_map(
+    store: &VectorStore,
+    options: &TopicOptions,
+    filter: Option<&SearchFilter>,
+) -> TopicMap {
+    let filter = filter.filter(|f| !f.is_empty());
+    let mut chunks: Vec<&FileChunk> = store
+        .all_chunks()
+        .filter(|c| !c.embedding.is_empty())
+        .filter(|c| filter.is_none_or(|f| f.matches_chunk(c)))
+        .collect();
+    chunks.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let root = common_root(&chunks);
+    let k = options
+        .k
+        .unwrap_or_else(|| ((chunks.len() as f32 / 2.0).sqrt().round() as usize).clamp(2, 30))
+        .min(chunks.len());
+    let assignments = kmeans(&chunks, k, options.iterations);
+
+    // Renumber so that topic 1 is the largest
+    let mut sizes = vec![0usize; k];
+    for &a in &assignments {
+        sizes[a] += 1;
+    }
+    let mut order: Vec<usize> = (0..k).filter(|&t| sizes[t] > 0).collect();
+    order.sort_by_key(|&t| std::cmp::Reverse(sizes[t]));
+    let mut renumber = vec![0usize; k];
+    for (i, &t) in order.iter().enumerate() {
+        renumber[t] = i;
+    }
+    let assignments: Vec<usize> = assignments.iter().map(|&a| renumber[a]).collect();
+
+    let mut members: Vec<Vec<&FileChunk>> = vec![Vec::new(); order.len()];
+    for (chunk, &topic) in chunks.iter().zip(&assignments) {
+        members[topic].push(chunk);
+    }
+
+    let identifiers: Vec<HashSet<String>> = chunks.iter().map(|c| identifiers(c)).collect();
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for ids in &identifiers {
+        for id in ids {
+            *document_frequency.entry(id.as_str()).or_default() += 1;
+        }
+    }
+
+    let topics = members
+        .iter()
+        .enumerate()
+        .map(|(topic, members)| {
+            let mut frequency: HashMap<&str, usize> = HashMap::new();
+            for (ids, _) in identifiers
+                .iter()
+                .zip(&assignments)
+                .filter(|(_, &a)| a == topic)
+            {
+                for id in ids {
+                    *frequency.entry(id.as_str()).or_default() += 1;
+                }
+            }
+            let mut terms: Vec<(&str, f32)> = frequency
+                .into_iter()
+                .filter(|&(_, n)| n >= 2 || members.len() == 1)
+                .map(|(id, n)| {
+                    let idf = (chunks.len() as f32 / document_frequency[id] as f32).ln();
+                    (id, n as f32 / members.len() as f32 * idf)
+                })
+                .collect();
+            terms.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+
+            let mut paths: BTreeMap<String, usize> = BTreeMap::new();
+            for chunk in members {
+                *paths.entry(prefix(&root, &chunk.file_path, 2)).or_default() += 1;
+            }
+            let mut paths: Vec<(String, f32)> = paths
+                .into_iter()
+                .map(|(path, n)| (path, n as f32 / members.len() as f32))
+                .collect();
+            paths.sort_by(|a, b| b.1.total_cmp(&a.1));
+            paths.truncate(options.labels);
+
+            Topic {
+                id: topic + 1,
+                size: members.len(),
+                terms: terms
+                    .into_iter()
+                    .take(options.labels)
+                    .map(|(id, _)| id.to_string())
+                    .collect(),
+                paths,
+            }
+        })
+        .collect();
+
+    let mut by_directory: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (chunk, &topic) in chunks.iter().zip(&assignments) {
+        let dir = Path::new(&relative(&root, &chunk.file_path))
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        by_directory.entry(dir).or_default().push(topic);
+    }
+    let mut directories: Vec<DirectoryTopics> = by_directory
+        .into_iter()
+        .map(|(path, topics)| {
+            let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+            for &t in &topics {
+                *counts.entry(t + 1).or_default() += 1;
+            }
+            let mut shares: Vec<(usize, f32)> = counts
+                .into_iter()
+                .map(|(t, n)| (t, n as f32 / topics.len() as f32))
+                .collect();
+            shares.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let significant = shares.iter().filter(|(_, s)| *s >= MIXED_SHARE).count();
+            DirectoryTopics {
+                path: if path.is_empty() {
+                    ".".to_string()
+                } else {
+                    path
+                },
+                chunks: topics.len(),
+                mixed: topics.len() >= MIXED_MIN_CHUNKS && significant >= 2,
+                topics: shares,
+            }
+        })
+        .collect();
+    directories.sort_by(|a, b| b.mixed.cmp(&a.mixed).then(a.path.cmp(&b.path)));
+
+    TopicMap {
+        root,
+        topics,
+        directories,
+    }
+}
+
+/// Spherical k-means over the chunk embeddings; returns each chunk's cluster
+fn kmeans(chunks: &[&FileChunk], k: usize, iterations: usize) -> Vec<usize> {
+    if k == 0 {
+        return Vec::new();
+    }
+    let points: Vec<Vec<f32>> = chunks
+        .par_iter()
+        .map(|c| normalized(&c.embedding))
+        .collect();
+    let mut centroids = seed(&points, k);
+    let mut assignments = vec![usize::MAX; points.len()];
+
+    for _ in 0..iterations.max(1) {
+        let next: Vec<usize> = points
+            .par_iter()
+            .map(|p| nearest(&centroids, p).0)
+            .collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+
+        let dim = points[0].len();
+        let mut sums = vec![vec![0.0f32; dim]; k];
+        for (p, &a) in points.iter().zip(&assignments) {
+            for (s, x) in sums[a].iter_mut().zip(p) {
+                *s += x;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // An emptied cluster keeps its old centroid
+            if sum.iter().any(|&x| x != 0.0) {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+    assignments
+}
+
+/// k-means++ seeding with a fixed-seed generator, so runs are repeatable
+fn seed(points: &[Vec<f32>], k: usize) -> Vec<Vec<f32>> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    let mut centroids =
+        vec![points[(random() * points.len() as f64) as usize % points.len()].clone()];
+    while centroids.len() < k {
+        let distances: Vec<f64> = points
+            .par_iter()
+            .map(|p| (1.0 - nearest(&centroids, p).1 as f64).max(0.0).powi(2))
+            .collect();
+        let total: f64 = distances.iter().sum();
+        if total <= 0.0 {
+            break;
+        }
+        let mut target = random() * total;
+        let pick = distances
+            .iter()
+            .position(|&d| {
+                target -= d;
+                target <= 0.0
+            })
+            .unwrap_or(points.len() - 1);
+        centroids.push(points[pick].clone());
+    }
+    centroids
+}
+
+/// Index of and similarity to the most similar centroid
+fn nearest(centroids: &[Vec<f32>], point: &[f32]) -> (usize, f32) {
+    centroids
+        .iter()
+        .map(|c| c.iter().zip(point).map(|(a, b)| a * b).sum::<f32>())
+        .enumerate()
+        .fold(
+            (0, f32::MIN),
+            |best, (i, s)| if s > best.1 { (i, s) } else { best },
+        )
+}
+
+fn normalized(v: &[f32]) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return v.to_vec();
+    }
+    v.iter().map(|x| x / norm).collect()
+}
+
+/// Lowercased identifiers of a chunk, without keywords and short tokens
+fn identifiers(chunk: &FileChunk) -> HashSet<String> {
+    chunk
+        .content
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .chain(chunk.symbol_name.as_deref())
+        .filter(|t| t.len() >= 3 && !t.chars().all(|c| c.is_ascii_digit() || c == '_'))
+        .map(|t| t.to_lowercase())
+        .filter(|t| !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+/// Deepest directory containing every chunk's file
+fn common_root(chunks: &[&FileChunk]) -> String {
+    let mut root: Option<&Path> = None;
+    for chunk in chunks {
+        let dir = Path::new(&chunk.file_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        root = Some(match root {
+            None => dir,
+            Some(root) => root
+                .ancestors()
+                .find(|a| dir.starts_with(a))
+                .unwrap_or(Path::new("")),
+        });
+    }
+    root.map(|r| r.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn relative(root: &str, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(root)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// The first `depth` directories of `path` below `root`
+fn prefix(root: &str, path: &str, depth: usize) -> String {
+    let relative = relative(root, path);
+    let dirs: Vec<&str> = Path::new(&relative)
+        .parent()
+        .map(|p| p.iter().filter_map(|c| c.to_str()).take(depth).collect())
+        .unwrap_or_default();
+    if dirs.is_empty() {
+        ".".to_string()
+    } else {
+        dirs.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &str, file: &str, content: &str, embedding: Vec<f32>) -> FileChunk {
+        FileChunk::test(id, file, (1, 10))
+            .with_content(content)
+            .with_embedding(embedding)
+    }
+
+    #[test]
+    fn test_topic_map() {
+        let store = VectorStore::with_test_chunks(vec![
+            chunk(
+                "1",
+                "/r/src/db/pool.rs",
+                "fn connect(pool: Pool) -> Connection",
+                vec![1.0, 0.0, 0.1],
+            ),
+            chunk(
+                "2",
+                "/r/src/db/query.rs",
+                "fn execute(conn: Connection, sql: Query)",
+                vec![0.9, 0.1, 0.0],
+            ),
+            chunk(
+                "3",
+                "/r/src/util/mix.rs",
+                "fn reconnect(pool: Pool) -> Connection",
+                vec![0.95, 0.0, 0.05],
+            ),
+            chunk(
+                "4",
+                "/r/src/ui/render.rs",
+                "fn render(widget: Widget, frame: Frame)",
+                vec![0.0, 1.0, 0.1],
+            ),
+            chunk(
+                "5",
+                "/r/src/ui/layout.rs",
+                "fn layout(widget: Widget) -> Frame",
+                vec![0.1, 0.9, 0.0],
+            ),
+            chunk(
+                "6",
+                "/r/src/util/mix.rs",
+                "fn draw(widget: Widget, frame: Frame)",
+                vec![0.0, 0.95, 0.05],
+            ),
+        ]);
+
+        let options = TopicOptions {
+            k: Some(2),
+            ..Default::default()
+        };
+        let map = topic_map(&store, &options, None);
+        assert_eq!(map.root, "/r/src");
+        assert_eq!(map.topics.len(), 2);
+        assert!(map.topics.iter().all(|t| t.size == 3));
+
+        let db = map
+            .topics
+            .iter()
+            .find(|t| t.paths[0].0 == "db")
+            .expect("database topic");
+        assert!(db.terms.contains(&"connection".to_string()));
+        assert!(db.terms.contains(&"pool".to_string()));
+        assert!(!db.terms.contains(&"widget".to_string()));
+
+        let mixed: Vec<&str> = map
+            .directories
+            .iter()
+            .filter(|d| d.topics.len() > 1)
+            .map(|d| d.path.as_str())
+            .collect();
+        assert_eq!(mixed, vec!["util"]);
+    }
+}
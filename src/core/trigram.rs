@@ -0,0 +1,429 @@
+//! Trigram index over chunk contents
+//!
+//! Maps every (ASCII-lowercased) three-byte sequence to the chunks that
+//! contain it, so literal and regex searches only verify a small candidate
+//! set instead of every chunk. Regexes are decomposed into a boolean query
+//! over trigrams that any match must satisfy, following the approach of
+//! Google Code Search: literal runs become AND-ed trigrams, alternations
+//! become ORs, and anything too open-ended (`.*`, `\w+`) matches all.
+//!
+//! The index is updated alongside `VectorStore::add_chunk`/`remove_file`.
+//! Removed chunks are tombstoned and dropped when the index is saved.
+
+use regex_syntax::hir::{Class, Hir, HirKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Largest set of exact strings tracked before falling back to trigrams
+const MAX_EXACT: usize = 64;
+
+/// Largest character class expanded into exact strings
+const MAX_CLASS: usize = 8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrigramIndex {
+    /// Chunk id per document number (`None` once removed)
+    docs: Vec<Option<String>>,
+    doc_ids: HashMap<String, u32>,
+    /// Sorted document numbers per trigram
+    postings: HashMap<u32, Vec<u32>>,
+    removed: usize,
+}
+
+impl TrigramIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index (or re-index) a chunk
+    pub fn add(&mut self, chunk_id: &str, content: &str) {
+        self.remove(chunk_id);
+
+        let doc = self.docs.len() as u32;
+        self.docs.push(Some(chunk_id.to_string()));
+        self.doc_ids.insert(chunk_id.to_string(), doc);
+
+        let trigrams: HashSet<u32> = trigrams(content.as_bytes()).collect();
+        for trigram in trigrams {
+            // Documents are numbered in insertion order, so postings stay sorted
+            self.postings.entry(trigram).or_default().push(doc);
+        }
+    }
+
+    pub fn remove(&mut self, chunk_id: &str) {
+        if let Some(doc) = self.doc_ids.remove(chunk_id) {
+            self.docs[doc as usize] = None;
+            self.removed += 1;
+        }
+    }
+
+    /// Chunk ids that may match `query`, or `None` if every chunk may match
+    pub fn candidates(&self, query: &TrigramQuery) -> Option<Vec<&str>> {
+        let docs = self.eval(query)?;
+        Some(
+            docs.into_iter()
+                .filter_map(|doc| self.docs[doc as usize].as_deref())
+                .collect(),
+        )
+    }
+
+    fn eval(&self, query: &TrigramQuery) -> Option<Vec<u32>> {
+        match query {
+            TrigramQuery::All => None,
+            TrigramQuery::Trigram(t) => Some(self.postings.get(t).cloned().unwrap_or_default()),
+            TrigramQuery::And(parts) => parts
+                .iter()
+                .filter_map(|q| self.eval(q))
+                .reduce(|a, b| intersect(&a, &b)),
+            TrigramQuery::Or(parts) => {
+                let mut docs = Vec::new();
+                for part in parts {
+                    docs.extend(self.eval(part)?);
+                }
+                docs.sort_unstable();
+                docs.dedup();
+                Some(docs)
+            }
+        }
+    }
+
+    /// True when more than half the document slots are tombstones
+    pub fn needs_compaction(&self) -> bool {
+        self.removed > self.doc_ids.len()
+    }
+
+    /// Copy of the index without removed chunks
+    pub fn compacted(&self) -> Self {
+        let mut remap: Vec<Option<u32>> = Vec::with_capacity(self.docs.len());
+        let mut docs = Vec::with_capacity(self.doc_ids.len());
+        let mut doc_ids = HashMap::with_capacity(self.doc_ids.len());
+        for id in &self.docs {
+            match id {
+                Some(id) => {
+                    let doc = docs.len() as u32;
+                    remap.push(Some(doc));
+                    doc_ids.insert(id.clone(), doc);
+                    docs.push(Some(id.clone()));
+                }
+                None => remap.push(None),
+            }
+        }
+
+        let postings = self
+            .postings
+            .iter()
+            .filter_map(|(&trigram, list)| {
+                let list: Vec<u32> = list.iter().filter_map(|&doc| remap[doc as usize]).collect();
+                (!list.is_empty()).then_some((trigram, list))
+            })
+            .collect();
+
+        Self {
+            docs,
+            doc_ids,
+            postings,
+            removed: 0,
+        }
+    }
+}
+
+/// Boolean trigram query that every match of a pattern satisfies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrigramQuery {
+    /// No constraint
+    All,
+    Trigram(u32),
+    And(Vec<TrigramQuery>),
+    Or(Vec<TrigramQuery>),
+}
+
+impl TrigramQuery {
+    /// Decompose a regex. Unparseable patterns match all chunks.
+    pub fn from_regex(pattern: &str, ignore_case: bool) -> Self {
+        regex_syntax::ParserBuilder::new()
+            .case_insensitive(ignore_case)
+            .build()
+            .parse(pattern)
+            .map(|hir| analyze(&hir).into_query())
+            .unwrap_or(TrigramQuery::All)
+    }
+
+    fn and(self, other: TrigramQuery) -> Self {
+        match (self, other) {
+            (TrigramQuery::All, q) | (q, TrigramQuery::All) => q,
+            (TrigramQuery::And(mut a), TrigramQuery::And(b)) => {
+                a.extend(b);
+                TrigramQuery::And(a)
+            }
+            (TrigramQuery::And(mut a), q) | (q, TrigramQuery::And(mut a)) => {
+                a.push(q);
+                TrigramQuery::And(a)
+            }
+            (a, b) => TrigramQuery::And(vec![a, b]),
+        }
+    }
+
+    fn or(parts: Vec<TrigramQuery>) -> Self {
+        if parts.contains(&TrigramQuery::All) {
+            return TrigramQuery::All;
+        }
+        match parts.len() {
+            1 => parts.into_iter().next().unwrap(),
+            _ => TrigramQuery::Or(parts),
+        }
+    }
+
+    /// Any of `strings` must occur
+    fn from_exact(strings: &HashSet<Vec<u8>>) -> Self {
+        TrigramQuery::or(
+            strings
+                .iter()
+                .map(|s| {
+                    trigrams(s).fold(TrigramQuery::All, |q, t| q.and(TrigramQuery::Trigram(t)))
+                })
+                .collect(),
+        )
+    }
+}
+
+/// What a sub-expression is known to match: either a small set of exact
+/// (lowercased) strings, or a trigram query
+struct Info {
+    exact: Option<HashSet<Vec<u8>>>,
+    query: TrigramQuery,
+}
+
+impl Info {
+    fn exact(strings: HashSet<Vec<u8>>) -> Self {
+        Self {
+            exact: Some(strings),
+            query: TrigramQuery::All,
+        }
+    }
+
+    fn inexact(query: TrigramQuery) -> Self {
+        Self { exact: None, query }
+    }
+
+    fn empty() -> Self {
+        Self::exact(HashSet::from([Vec::new()]))
+    }
+
+    fn into_query(self) -> TrigramQuery {
+        match self.exact {
+            Some(ref strings) => TrigramQuery::from_exact(strings),
+            None => self.query,
+        }
+    }
+}
+
+fn analyze(hir: &Hir) -> Info {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => Info::empty(),
+        HirKind::Literal(lit) => Info::exact(HashSet::from([lowercase(&lit.0)])),
+        HirKind::Class(class) => match class_strings(class) {
+            Some(strings) => Info::exact(strings),
+            None => Info::inexact(TrigramQuery::All),
+        },
+        HirKind::Capture(capture) => analyze(&capture.sub),
+        HirKind::Repetition(rep) => {
+            let sub = analyze(&rep.sub);
+            match (rep.min, rep.max) {
+                (1, Some(1)) => sub,
+                (0, Some(1)) => match sub.exact {
+                    Some(mut strings) if strings.len() < MAX_EXACT => {
+                        strings.insert(Vec::new());
+                        Info::exact(strings)
+                    }
+                    _ => Info::inexact(TrigramQuery::All),
+                },
+                (0, _) => Info::inexact(TrigramQuery::All),
+                // At least one occurrence of the sub-expression
+                _ => Info::inexact(sub.into_query()),
+            }
+        }
+        HirKind::Concat(parts) => {
+            let mut query = TrigramQuery::All;
+            let mut current: HashSet<Vec<u8>> = HashSet::from([Vec::new()]);
+            let mut all_exact = true;
+
+            for part in parts {
+                let info = analyze(part);
+                match info.exact {
+                    Some(next) if current.len() * next.len() <= MAX_EXACT => {
+                        current = cross(&current, &next);
+                    }
+                    Some(next) => {
+                        query = query.and(TrigramQuery::from_exact(&current));
+                        current = next;
+                        all_exact = false;
+                    }
+                    None => {
+                        query = query
+                            .and(TrigramQuery::from_exact(&current))
+                            .and(info.query);
+                        current = HashSet::from([Vec::new()]);
+                        all_exact = false;
+                    }
+                }
+            }
+
+            if all_exact {
+                Info::exact(current)
+            } else {
+                Info::inexact(query.and(TrigramQuery::from_exact(&current)))
+            }
+        }
+        HirKind::Alternation(alternatives) => {
+            let infos: Vec<Info> = alternatives.iter().map(analyze).collect();
+            let total: usize = infos
+                .iter()
+                .map(|i| i.exact.as_ref().map_or(usize::MAX, HashSet::len))
+                .fold(0, usize::saturating_add);
+            if total <= MAX_EXACT {
+                Info::exact(infos.into_iter().flat_map(|i| i.exact.unwrap()).collect())
+            } else {
+                Info::inexact(TrigramQuery::or(
+                    infos.into_iter().map(Info::into_query).collect(),
+                ))
+            }
+        }
+    }
+}
+
+/// Expand a small character class into its (lowercased) members
+fn class_strings(class: &Class) -> Option<HashSet<Vec<u8>>> {
+    let mut strings = HashSet::new();
+    match class {
+        Class::Unicode(class) => {
+            for range in class.iter() {
+                for c in range.start()..=range.end() {
+                    let mut buf = [0; 4];
+                    strings.insert(lowercase(c.encode_utf8(&mut buf).as_bytes()));
+                    if strings.len() > MAX_CLASS {
+                        return None;
+                    }
+                }
+            }
+        }
+        Class::Bytes(class) => {
+            for range in class.iter() {
+                for b in range.start()..=range.end() {
+                    strings.insert(vec![b.to_ascii_lowercase()]);
+                    if strings.len() > MAX_CLASS {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+    Some(strings)
+}
+
+fn cross(a: &HashSet<Vec<u8>>, b: &HashSet<Vec<u8>>) -> HashSet<Vec<u8>> {
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| [x.as_slice(), y.as_slice()].concat()))
+        .collect()
+}
+
+fn lowercase(bytes: &[u8]) -> Vec<u8> {
+    bytes.to_ascii_lowercase()
+}
+
+/// Packed, ASCII-lowercased trigrams of `bytes`
+fn trigrams(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes.windows(3).map(|w| {
+        u32::from_be_bytes([
+            0,
+            w[0].to_ascii_lowercase(),
+            w[1].to_ascii_lowercase(),
+            w[2].to_ascii_lowercase(),
+        ])
+    })
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                out.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> TrigramIndex {
+        let mut index = TrigramIndex::new();
+        index.add("a", "fn load() { config.unwrap() }");
+        index.add("b", "fn save() -> Result<()> { Ok(()) }");
+        index.add("c", "let client = HttpClient::new();");
+        index
+    }
+
+    fn candidates(index: &TrigramIndex, pattern: &str, ignore_case: bool) -> Option<Vec<String>> {
+        let query = TrigramQuery::from_regex(pattern, ignore_case);
+        index.candidates(&query).map(|ids| {
+            let mut ids: Vec<String> = ids.into_iter().map(String::from).collect();
+            ids.sort();
+            ids
+        })
+    }
+
+    #[test]
+    fn test_literal_and_alternation() {
+        let index = index();
+        assert_eq!(
+            candidates(&index, r"unwrap\(\)", false),
+            Some(vec!["a".into()])
+        );
+        assert_eq!(
+            candidates(&index, "fn (load|save)", false),
+            Some(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(
+            candidates(&index, "HTTPCLIENT", true),
+            Some(vec!["c".into()])
+        );
+        assert_eq!(candidates(&index, "missing_symbol", false), Some(vec![]));
+    }
+
+    #[test]
+    fn test_open_patterns_match_all() {
+        let index = index();
+        assert_eq!(candidates(&index, r"\w+", false), None);
+        assert_eq!(candidates(&index, "ab", false), None);
+        // The required literal still narrows an otherwise open pattern
+        assert_eq!(
+            candidates(&index, r"Http\w*::new", false),
+            Some(vec!["c".into()])
+        );
+    }
+
+    #[test]
+    fn test_remove_and_compact() {
+        let mut index = index();
+        index.add("a", "fn load() { config.expect(\"x\") }");
+        index.remove("c");
+        assert_eq!(candidates(&index, "unwrap", false), Some(vec![]));
+        assert_eq!(candidates(&index, "expect", false), Some(vec!["a".into()]));
+
+        let compacted = index.compacted();
+        assert_eq!(compacted.doc_ids.len(), 2);
+        assert_eq!(
+            candidates(&compacted, "expect", false),
+            Some(vec!["a".into()])
+        );
+        assert_eq!(candidates(&compacted, "client", false), Some(vec![]));
+    }
+}
@@ -0,0 +1,352 @@
+//! Training triples mined from the repository
+//!
+//! Learned fusion (see `core::fusion_layer`) is fit on (query, positive,
+//! negative) text triples from two sources that need no labeling:
+//!
+//! - doc comments and docstrings as queries for the code they document,
+//!   with another documented item of the same file as the negative
+//! - commit messages as queries for the lines the commit added, with an
+//!   unrelated commit as the negative
+
+use crate::core::git::CommitPatch;
+use crate::core::scanner::ScannedFile;
+
+/// Longest code excerpt, in lines and bytes
+const MAX_LINES: usize = 40;
+const MAX_BYTES: usize = 2000;
+/// Shortest doc comment or commit message used as a query
+const MIN_WORDS: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct Triple {
+    pub query: String,
+    pub positive: String,
+    pub negative: String,
+}
+
+/// Doc-comment triples from `files`, at most `limit` spread evenly over them
+pub fn doc_triples(files: &[ScannedFile], limit: usize) -> Vec<Triple> {
+    let mut files: Vec<&ScannedFile> = files.iter().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let per_file: Vec<Vec<(String, String)>> =
+        files.iter().map(|f| doc_pairs(&f.content)).collect();
+    let all_bodies: Vec<&str> = per_file
+        .iter()
+        .flatten()
+        .map(|(_, body)| body.as_str())
+        .collect();
+    if all_bodies.len() < 2 {
+        return Vec::new();
+    }
+
+    let mut triples = Vec::new();
+    let mut index = 0;
+    for pairs in &per_file {
+        for (k, (doc, body)) in pairs.iter().enumerate() {
+            // A neighbour in the same file shares vocabulary: a hard negative
+            let negative = if pairs.len() > 1 {
+                pairs[(k + 1) % pairs.len()].1.as_str()
+            } else {
+                all_bodies[(index + all_bodies.len() / 2) % all_bodies.len()]
+            };
+            index += 1;
+            if negative != body {
+                triples.push(Triple {
+                    query: doc.clone(),
+                    positive: body.clone(),
+                    negative: negative.to_string(),
+                });
+            }
+        }
+    }
+    spread(triples, limit)
+}
+
+/// Commit-message triples, at most `limit`
+pub fn commit_triples(patches: &[CommitPatch], limit: usize) -> Vec<Triple> {
+    let usable: Vec<&CommitPatch> = patches
+        .iter()
+        .filter(|p| p.message.split_whitespace().count() >= MIN_WORDS && !p.added.trim().is_empty())
+        .collect();
+    if usable.len() < 2 {
+        return Vec::new();
+    }
+
+    let triples = usable
+        .iter()
+        .enumerate()
+        .map(|(i, patch)| Triple {
+            query: excerpt(&patch.message, 10),
+            positive: excerpt(&patch.added, MAX_LINES),
+            negative: excerpt(
+                &usable[(i + usable.len() / 2) % usable.len()].added,
+                MAX_LINES,
+            ),
+        })
+        .collect();
+    spread(triples, limit)
+}
+
+/// Doc comments (or Python docstrings) and the code they document
+pub fn doc_pairs(content: &str) -> Vec<(String, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut pairs = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        // Python: docstring on the line after `def`/`class`
+        if is_python_declaration(lines[i]) {
+            if let Some((doc, after)) = docstring(&lines, i + 1) {
+                let mut body = vec![lines[i]];
+                body.extend(block(&lines, i, after));
+                push_pair(&mut pairs, &doc, &body);
+            }
+            i += 1;
+            continue;
+        }
+
+        let Some((doc, next)) = doc_comment(&lines, i) else {
+            i += 1;
+            continue;
+        };
+        // Attributes and decorators sit between the comment and the item
+        let mut decl = next;
+        while decl < lines.len() && is_attribute(lines[decl]) {
+            decl += 1;
+        }
+        if decl < lines.len() && !lines[decl].trim().is_empty() {
+            let mut body = vec![lines[decl]];
+            body.extend(block(&lines, decl, decl + 1));
+            push_pair(&mut pairs, &doc, &body);
+        }
+        i = next;
+    }
+    pairs
+}
+
+fn push_pair(pairs: &mut Vec<(String, String)>, doc: &str, body: &[&str]) {
+    if doc.split_whitespace().count() >= MIN_WORDS && body.len() >= 2 {
+        pairs.push((doc.to_string(), excerpt(&body.join("\n"), MAX_LINES)));
+    }
+}
+
+/// Text of the comment starting at `start` and the line after it
+fn doc_comment(lines: &[&str], start: usize) -> Option<(String, usize)> {
+    let first = lines[start].trim_start();
+
+    // Block comments: /** ... */
+    if first.starts_with("/**") {
+        let mut text = Vec::new();
+        for (i, line) in lines.iter().enumerate().skip(start) {
+            let line = line.trim();
+            let closed = line.ends_with("*/");
+            let line = line
+                .trim_start_matches("/**")
+                .trim_end_matches("*/")
+                .trim_start_matches('*')
+                .trim();
+            if !line.is_empty() {
+                text.push(line);
+            }
+            if closed {
+                return Some((text.join(" "), i + 1));
+            }
+        }
+        return None;
+    }
+
+    // Runs of line comments: ///, // or #
+    let mut text = Vec::new();
+    let mut end = start;
+    while end < lines.len() {
+        match line_comment(lines[end]) {
+            Some(line) => {
+                if !line.is_empty() {
+                    text.push(line);
+                }
+                end += 1;
+            }
+            None => break,
+        }
+    }
+    (end > start).then(|| (text.join(" "), end))
+}
+
+fn line_comment(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with("//!") || line.starts_with("#!") || is_attribute(line) {
+        return None;
+    }
+    if let Some(rest) = line.strip_prefix("///") {
+        return Some(rest.trim());
+    }
+    if let Some(rest) = line.strip_prefix("//") {
+        return Some(rest.trim());
+    }
+    let rest = line.strip_prefix('#')?;
+    // Preprocessor directives are not comments
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+fn is_attribute(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("#[") || line.starts_with('@')
+}
+
+fn is_python_declaration(line: &str) -> bool {
+    let line = line.trim_start();
+    (line.starts_with("def ") || line.starts_with("async def ") || line.starts_with("class "))
+        && line.trim_end().ends_with(':')
+}
+
+/// Docstring starting at `start` and the line after it
+fn docstring(lines: &[&str], start: usize) -> Option<(String, usize)> {
+    let first = lines.get(start)?.trim();
+    let quote = ["\"\"\"", "'''"]
+        .into_iter()
+        .find(|q| first.starts_with(q))?;
+
+    let rest = &first[3..];
+    if let Some(end) = rest.find(quote) {
+        return Some((rest[..end].trim().to_string(), start + 1));
+    }
+    let mut text = vec![rest.trim()];
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if let Some(end) = line.find(quote) {
+            text.push(line[..end].trim());
+            let text: Vec<&str> = text.into_iter().filter(|l| !l.is_empty()).collect();
+            return Some((text.join(" "), i + 1));
+        }
+        text.push(line.trim());
+    }
+    None
+}
+
+/// Lines from `from` that belong to the item declared on line `decl`: more
+/// indented, blank, or a closing bracket at the declaration's indent
+fn block<'a>(lines: &[&'a str], decl: usize, from: usize) -> Vec<&'a str> {
+    let base = indent(lines[decl]);
+    let mut body = Vec::new();
+    for line in lines.iter().skip(from) {
+        if body.len() >= MAX_LINES {
+            break;
+        }
+        if line.trim().is_empty() || indent(line) > base {
+            body.push(*line);
+            continue;
+        }
+        if line.trim_start().starts_with(['}', ')', ']']) {
+            body.push(*line);
+        }
+        break;
+    }
+    while body.last().is_some_and(|l| l.trim().is_empty()) {
+        body.pop();
+    }
+    body
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// At most `max_lines` lines and `MAX_BYTES` bytes of `text`
+fn excerpt(text: &str, max_lines: usize) -> String {
+    let mut excerpt = text.lines().take(max_lines).collect::<Vec<_>>().join("\n");
+    if excerpt.len() > MAX_BYTES {
+        let mut end = MAX_BYTES;
+        while !excerpt.is_char_boundary(end) {
+            end -= 1;
+        }
+        excerpt.truncate(end);
+    }
+    excerpt
+}
+
+/// Every n-th triple so that at most `limit` remain
+fn spread(triples: Vec<Triple>, limit: usize) -> Vec<Triple> {
+    if triples.len() <= limit {
+        return triples;
+    }
+    let step = triples.len() as f64 / limit as f64;
+    (0..limit)
+        .map(|i| triples[(i as f64 * step) as usize].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_pairs() {
+        let rust = "\
+use std::fs;
+
+/// Load the index from disk,
+/// creating it when missing
+#[inline]
+pub fn load(path: &str) -> Store {
+    let data = fs::read(path);
+
+    decode(data)
+}
+
+// short
+fn helper() {
+    todo!()
+}
+";
+        let pairs = doc_pairs(rust);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            pairs[0].0,
+            "Load the index from disk, creating it when missing"
+        );
+        assert!(pairs[0].1.starts_with("pub fn load"));
+        assert!(pairs[0].1.ends_with('}'));
+
+        let python = "\
+class Cache:
+    def get(self, key):
+        \"\"\"Return the cached value
+        for key, or None.\"\"\"
+        value = self.items.get(key)
+        return value
+
+    def put(self, key, value):
+        self.items[key] = value
+";
+        let pairs = doc_pairs(python);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0, "Return the cached value for key, or None.");
+        assert_eq!(
+            pairs[0].1,
+            "    def get(self, key):\n        value = self.items.get(key)\n        return value"
+        );
+    }
+
+    #[test]
+    fn test_commit_triples() {
+        let patch = |message: &str, added: &str| CommitPatch {
+            message: message.to_string(),
+            added: added.to_string(),
+        };
+        let patches = vec![
+            patch("Add retry with backoff", "fn retry() {}"),
+            patch("Fix", "x"),
+            patch("Cache parsed queries per session", "struct QueryCache;"),
+            patch("Merge adjacent search hits", "fn merge() {}"),
+        ];
+        let triples = commit_triples(&patches, 10);
+        assert_eq!(triples.len(), 3);
+        for triple in &triples {
+            assert_ne!(triple.positive, triple.negative);
+        }
+        assert_eq!(commit_triples(&patches, 2).len(), 2);
+    }
+}
@@ -82,6 +82,12 @@ impl VectorIndex {
         self.chunk_to_id.insert(chunk_id.to_string(), key);
 
         if let Some(ref index) = self.index {
+            // usearch doesn't grow on add; writing past the reservation is UB
+            if index.size() >= index.capacity() {
+                index
+                    .reserve((index.capacity() * 2).max(64))
+                    .context("Failed to reserve index capacity")?;
+            }
             index
                 .add(key, embedding)
                 .context("Failed to add vector to index")?;
@@ -7,7 +7,16 @@ mod core;
 mod mcp;
 pub mod ui;
 
-use commands::{clean, compile, config, graph, history, index, search, status, watch};
+use commands::{
+    calibrate, clean, compile, config, dupes, eval, feedback, graph, history, index, intent, repl,
+    search, similar, status, topics, trace, train_fusion, tui, tune, watch,
+};
+use core::dupes::DupeOptions;
+use core::feedback::FeedbackLabel;
+use core::hybrid_embedder::FusionStrategy;
+use core::levels::SearchLevel;
+use core::topics::TopicOptions;
+use ui::output::OutputFormat;
 
 #[derive(Parser)]
 #[command(name = "sgrep")]
@@ -28,8 +37,9 @@ enum Commands {
     /// Search files using natural language
     #[command(alias = "s")]
     Search {
-        /// Natural language search query
-        pattern: String,
+        /// Natural language search query (optional with --regex/--fixed/--batch)
+        #[arg(required_unless_present_any = ["regex", "fixed", "batch"])]
+        pattern: Option<String>,
 
         /// Path to search in (defaults to current directory)
         path: Option<String>,
@@ -42,6 +52,18 @@ enum Commands {
         #[arg(short = 'c', long)]
         content: bool,
 
+        /// Show NUM lines after each result, read from the file on disk
+        #[arg(short = 'A', long, value_name = "NUM")]
+        after_context: Option<usize>,
+
+        /// Show NUM lines before each result, read from the file on disk
+        #[arg(short = 'B', long, value_name = "NUM")]
+        before_context: Option<usize>,
+
+        /// Show NUM lines before and after each result
+        #[arg(short = 'C', long, value_name = "NUM")]
+        context: Option<usize>,
+
         /// Generate AI answer from search results
         #[arg(short = 'a', long)]
         answer: bool,
@@ -62,6 +84,14 @@ enum Commands {
         #[arg(short = 't', long = "type", value_name = "EXT")]
         file_types: Vec<String>,
 
+        /// Include files matching glob (prefix with ! to exclude), repeatable
+        #[arg(short = 'g', long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Exclude files matching glob, repeatable
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
         /// Use alternative store name
         #[arg(long)]
         store: Option<String>,
@@ -74,10 +104,18 @@ enum Commands {
         #[arg(long)]
         hybrid: bool,
 
-        /// Output results as JSON
+        /// Don't route by query intent; embed and score every query the same way
         #[arg(long)]
+        no_route: bool,
+
+        /// Output ripgrep-compatible JSON lines (same as --format json)
+        #[arg(long, conflicts_with = "format")]
         json: bool,
 
+        /// Output format: pretty, json, json-summary, vimgrep or quickfix
+        #[arg(long, value_name = "FORMAT", default_value = "pretty")]
+        format: String,
+
         /// Include related files (imports/importers) in results
         #[arg(long)]
         related: bool,
@@ -85,6 +123,248 @@ enum Commands {
         /// Depth for related file traversal (default: 1)
         #[arg(long, default_value = "1")]
         related_depth: usize,
+
+        /// Diversify results (MMR) to avoid near-duplicate hits
+        #[arg(long)]
+        diverse: bool,
+
+        /// Relevance vs. diversity trade-off for --diverse (0.0-1.0)
+        #[arg(long, default_value = "0.7")]
+        lambda: f32,
+
+        /// Maximum number of results from a single file
+        #[arg(long, value_name = "N")]
+        max_per_file: Option<usize>,
+
+        /// Keep overlapping/adjacent hits from the same file as separate results
+        #[arg(long)]
+        no_merge: bool,
+
+        /// Match lines against a regex; ranked by PATTERN when one is given
+        #[arg(short = 'e', long, value_name = "REGEX", conflicts_with = "fixed")]
+        regex: Option<String>,
+
+        /// Match lines against a literal string; ranked by PATTERN when one is given
+        #[arg(short = 'F', long, value_name = "STRING")]
+        fixed: Option<String>,
+
+        /// Case-insensitive --regex/--fixed matching
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+
+        /// Show score components, matched terms, reranking and candidate source for each result
+        #[arg(long)]
+        explain: bool,
+
+        /// Drop results below this calibrated confidence (0.0-1.0)
+        #[arg(long, value_name = "CONFIDENCE")]
+        min_score: Option<f32>,
+
+        /// Skip the first NUM results
+        #[arg(long, value_name = "NUM", default_value = "0", conflicts_with_all = ["page", "cursor"])]
+        offset: usize,
+
+        /// Show page NUM (1-based) of --max-count results
+        #[arg(long, value_name = "NUM", conflicts_with = "cursor")]
+        page: Option<usize>,
+
+        /// Continue from the cursor printed by a previous page of the same query
+        #[arg(long)]
+        cursor: Option<String>,
+
+        /// Rank chunks, whole files or directories: chunk, file or dir
+        #[arg(long, value_name = "LEVEL", default_value = "chunk")]
+        level: SearchLevel,
+
+        /// Only search the N files picked by directory and file vectors
+        #[arg(long, value_name = "N")]
+        narrow: Option<usize>,
+
+        /// Run each line of FILE as a query (- for stdin), printing JSON lines
+        #[arg(long, value_name = "FILE")]
+        batch: Option<String>,
+    },
+
+    /// Find code similar to the indexed chunk at a location or symbol
+    Similar {
+        /// Location inside the chunk, as path:line
+        #[arg(required_unless_present = "symbol", conflicts_with = "symbol")]
+        location: Option<String>,
+
+        /// Symbol to match instead of a location (name or Parent::name)
+        #[arg(long)]
+        symbol: Option<String>,
+
+        /// Maximum number of results
+        #[arg(short = 'm', long, default_value = "10")]
+        max_count: usize,
+
+        /// Show file content snippets
+        #[arg(short = 'c', long)]
+        content: bool,
+
+        /// Include files matching glob (prefix with ! to exclude), repeatable
+        #[arg(short = 'g', long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Exclude files matching glob, repeatable
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Output ripgrep-compatible JSON lines (same as --format json)
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+
+        /// Output format: pretty, json, json-summary, vimgrep or quickfix
+        #[arg(long, value_name = "FORMAT", default_value = "pretty")]
+        format: String,
+    },
+
+    /// Find duplicate and near-duplicate code across the index
+    Dupes {
+        /// Only report code under this path
+        path: Option<String>,
+
+        /// Minimum embedding similarity for a candidate pair (0.0-1.0)
+        #[arg(long, default_value = "0.95")]
+        threshold: f32,
+
+        /// Minimum token overlap that confirms a candidate pair (0.0-1.0)
+        #[arg(long, default_value = "0.5")]
+        token_threshold: f32,
+
+        /// Ignore chunks shorter than this many lines
+        #[arg(long, default_value = "5")]
+        min_lines: usize,
+
+        /// Include files matching glob (prefix with ! to exclude), repeatable
+        #[arg(short = 'g', long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Exclude files matching glob, repeatable
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Show the code of each cluster
+        #[arg(short = 'c', long)]
+        content: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Exit with an error when duplicates are found
+        #[arg(long)]
+        fail: bool,
+    },
+
+    /// Cluster the index into topics and show which directories mix them
+    Topics {
+        /// Only cluster code under this path
+        path: Option<String>,
+
+        /// Number of topics (default: from the number of chunks)
+        #[arg(short = 'k', long)]
+        k: Option<usize>,
+
+        /// Identifiers and paths shown per topic
+        #[arg(long, default_value = "5")]
+        labels: usize,
+
+        /// List every directory, not only those mixing several topics
+        #[arg(long)]
+        all_dirs: bool,
+
+        /// Include files matching glob (prefix with ! to exclude), repeatable
+        #[arg(short = 'g', long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Exclude files matching glob, repeatable
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Browse search results interactively in a full-screen terminal UI
+    Tui {
+        /// Initial query
+        query: Option<String>,
+
+        /// Path to search in (defaults to current directory)
+        path: Option<String>,
+
+        /// Maximum number of results
+        #[arg(short = 'm', long, default_value = "50")]
+        max_count: usize,
+
+        /// Include files matching glob (prefix with ! to exclude), repeatable
+        #[arg(short = 'g', long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Exclude files matching glob, repeatable
+        #[arg(long = "exclude", value_name = "GLOB")]
+        excludes: Vec<String>,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Use CodeRankEmbed for query embedding (for indexes built with --code)
+        #[arg(long)]
+        code: bool,
+
+        /// Use hybrid BGE + CodeRankEmbed fusion
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Don't route by query intent; embed and score every query the same way
+        #[arg(long)]
+        no_route: bool,
+    },
+
+    /// Run successive queries against a store and models loaded once
+    Repl {
+        /// Path to search in (defaults to current directory)
+        path: Option<String>,
+
+        /// Maximum number of results
+        #[arg(short = 'm', long, default_value = "10")]
+        max_count: usize,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Use CodeRankEmbed for query embedding (for indexes built with --code)
+        #[arg(long)]
+        code: bool,
+
+        /// Use hybrid BGE + CodeRankEmbed fusion
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Use ColBERT token-level matching (local only)
+        #[arg(long)]
+        colbert: bool,
+
+        /// Don't route by query intent; embed and score every query the same way
+        #[arg(long)]
+        no_route: bool,
     },
 
     /// Index files and watch for changes
@@ -137,6 +417,42 @@ enum Commands {
         #[arg(long)]
         local_url: Option<String>,
 
+        /// Enable the local cross-encoder reranker: a BERT cross-encoder id, "default" or "none"
+        #[arg(long)]
+        rerank_model: Option<String>,
+
+        /// Set number of candidates scored by the local reranker
+        #[arg(long)]
+        rerank_top_n: Option<usize>,
+
+        /// Set rerank endpoint URL ("none" to remove)
+        #[arg(long)]
+        rerank_url: Option<String>,
+
+        /// Set rerank endpoint format (jina, cohere, or tei)
+        #[arg(long)]
+        rerank_format: Option<String>,
+
+        /// Set the model sent to the rerank endpoint ("none" to remove)
+        #[arg(long)]
+        rerank_endpoint_model: Option<String>,
+
+        /// Set the environment variable holding the rerank endpoint's API key ("none" to remove)
+        #[arg(long)]
+        rerank_auth_env: Option<String>,
+
+        /// Set the rerank endpoint timeout in seconds
+        #[arg(long)]
+        rerank_timeout: Option<u64>,
+
+        /// Let intent routing embed code-shaped queries with hybrid fusion (true/false)
+        #[arg(long)]
+        route_embedder: Option<bool>,
+
+        /// Set --hybrid query fusion (weighted, concatenate, maxpool or adaptive)
+        #[arg(long)]
+        hybrid_fusion: Option<FusionStrategy>,
+
         /// Show current configuration
         #[arg(long)]
         show: bool,
@@ -189,6 +505,186 @@ enum Commands {
         json: bool,
     },
 
+    /// Fit score-to-confidence calibration from labeled queries
+    Calibrate {
+        /// JSON file of {"query", "relevant": ["path[:start-end]", ...]} entries
+        labels: String,
+
+        /// Path to search in (defaults to current directory)
+        path: Option<String>,
+
+        /// Results per query used for fitting
+        #[arg(short = 'm', long, default_value = "20")]
+        max_count: usize,
+
+        /// Use alternative store name
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Calibrate CodeRankEmbed query embeddings
+        #[arg(long)]
+        code: bool,
+
+        /// Calibrate hybrid BGE + CodeRankEmbed fusion
+        #[arg(long)]
+        hybrid: bool,
+
+        /// Calibrate unrouted scores, as searched with --no-route
+        #[arg(long)]
+        no_route: bool,
+
+        /// Show the fit without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Measure retrieval quality (recall@k, MRR, nDCG) on labeled queries
+    Eval {
+        /// JSON file of {"query", "relevant": [...], "symbols": [...]} entries
+        labels: String,
+
+        /// Path to search in (defaults to current directory)
+        path: Option<String>,
+
+        /// Embedding modes to run, as mode or mode@store (default, code, hybrid, colbert)
+        #[arg(long, value_delimiter = ',', default_value = "default")]
+        modes: Vec<String>,
+
+        /// Run with reranking on, off or both
+        #[arg(long, default_value = "on")]
+        rerank: String,
+
+        /// Results scored per query
+        #[arg(short = 'k', long, default_value = "10")]
+        k: usize,
+
+        /// Use alternative store name for modes without @store
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Compare with a report saved by --save
+        #[arg(long, value_name = "REPORT")]
+        compare: Option<String>,
+
+        /// Save the report as JSON
+        #[arg(long, value_name = "REPORT")]
+        save: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Rate a search result good or bad, for tuning fusion weights
+    Feedback {
+        /// Query id printed with the search results
+        query_id: String,
+
+        /// Rank of the result on that page (1 = first)
+        rank: usize,
+
+        /// good or bad
+        label: FeedbackLabel,
+    },
+
+    /// Fit BM25/vector fusion weights and the hybrid alpha to logged feedback
+    Tune {
+        /// Store holding the chunks of hybrid queries
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Show the fit without saving it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Remove tuned weights from the config
+        #[arg(long, conflicts_with = "dry_run")]
+        reset: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Train learned hybrid fusion on doc comments and commits of a repository
+    TrainFusion {
+        /// Repository to mine training triples from (defaults to current directory)
+        path: Option<String>,
+
+        /// Store whose chunk embeddings the fused queries are trained against
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Fusion to train: adaptive (per-dimension gate) or concatenate (projection)
+        #[arg(long, default_value = "adaptive")]
+        strategy: FusionStrategy,
+
+        /// Most doc-comment triples
+        #[arg(long, default_value = "300")]
+        docs: usize,
+
+        /// Most commit-message triples (0 to skip history)
+        #[arg(long, default_value = "200")]
+        commits: usize,
+
+        /// Passes over the training triples
+        #[arg(long, default_value = "30")]
+        epochs: usize,
+
+        /// Show the fit without saving it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Find the code behind a pasted panic, traceback or error message
+    Trace {
+        /// File holding the trace (reads stdin when omitted or `-`)
+        file: Option<String>,
+
+        /// Store name to use
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Most raise sites and related results
+        #[arg(short = 'm', long, default_value = "5")]
+        max_count: usize,
+
+        /// Skip the semantic search for the error message
+        #[arg(long)]
+        no_semantic: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show how a query is routed, or fit the intent classifier to labeled queries
+    Intent {
+        /// Query to classify
+        #[arg(required_unless_present_any = ["train", "reset"])]
+        query: Option<String>,
+
+        /// Labeled queries, one {"query": ..., "intent": ...} JSON object per line
+        #[arg(long, value_name = "FILE", conflicts_with = "query")]
+        train: Option<String>,
+
+        /// Store whose embeddings decide the routed embedder
+        #[arg(long)]
+        store: Option<String>,
+
+        /// Show the fit without saving it
+        #[arg(long, requires = "train")]
+        dry_run: bool,
+
+        /// Remove the trained classifier and use the rules again
+        #[arg(long, conflicts_with_all = ["query", "train"])]
+        reset: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Compile codebase map for LLM consumption (90% token reduction)
     Compile {
         /// Path to compile (defaults to current directory)
@@ -404,23 +900,60 @@ async fn main() -> Result<()> {
             path,
             max_count,
             content,
+            after_context,
+            before_context,
+            context,
             answer,
             sync,
             no_rerank,
             colbert,
             file_types,
+            globs,
+            excludes,
             store,
             code,
             hybrid,
+            no_route,
             json,
+            format,
             related,
             related_depth,
+            diverse,
+            lambda,
+            max_per_file,
+            no_merge,
+            regex,
+            fixed,
+            ignore_case,
+            explain,
+            min_score,
+            offset,
+            page,
+            cursor,
+            level,
+            narrow,
+            batch,
         }) => {
+            // `sgrep search -e PATTERN src/` (or `--batch FILE src/`): a lone
+            // positional that names an existing path is the search path, as
+            // in ripgrep
+            let grep_mode = regex.is_some() || fixed.is_some();
+            let (pattern, path) = match (pattern, path) {
+                (Some(p), None)
+                    if (grep_mode || batch.is_some()) && std::path::Path::new(&p).exists() =>
+                {
+                    (String::new(), Some(p))
+                }
+                (pattern, path) => (pattern.unwrap_or_default(), path),
+            };
+
             search::run(search::SearchOptions {
                 pattern,
                 path,
                 max_count,
                 content,
+                before_context: before_context.or(context).unwrap_or(0),
+                after_context: after_context.or(context).unwrap_or(0),
                 answer,
                 sync,
                 rerank: !no_rerank,
@@ -430,12 +963,161 @@ async fn main() -> Result<()> {
                 } else {
                     Some(file_types)
                 },
+                globs,
+                excludes,
                 store,
                 code,
                 hybrid,
-                json,
+                route: !no_route,
+                format: if json {
+                    OutputFormat::Json
+                } else {
+                    OutputFormat::parse(&format)?
+                },
                 related,
                 related_depth,
+                diverse,
+                lambda,
+                max_per_file,
+                merge: !no_merge,
+                regex,
+                fixed,
+                ignore_case,
+                explain,
+                min_score,
+                offset: match page {
+                    Some(page) => page.saturating_sub(1) * max_count,
+                    None => offset,
+                },
+                cursor,
+                level,
+                narrow,
+                batch,
+            })
+            .await?;
+        }
+        Some(Commands::Similar {
+            location,
+            symbol,
+            max_count,
+            content,
+            globs,
+            excludes,
+            store,
+            json,
+            format,
+        }) => {
+            similar::run(similar::SimilarOptions {
+                location,
+                symbol,
+                max_count,
+                content,
+                globs,
+                excludes,
+                store,
+                format: if json {
+                    OutputFormat::Json
+                } else {
+                    OutputFormat::parse(&format)?
+                },
+            })
+            .await?;
+        }
+        Some(Commands::Dupes {
+            path,
+            threshold,
+            token_threshold,
+            min_lines,
+            globs,
+            excludes,
+            store,
+            content,
+            json,
+            fail,
+        }) => {
+            dupes::run(dupes::DupesOptions {
+                path,
+                globs,
+                excludes,
+                store,
+                detect: DupeOptions {
+                    threshold,
+                    token_threshold,
+                    min_lines,
+                    ..Default::default()
+                },
+                content,
+                json,
+                fail,
+            })
+            .await?;
+        }
+        Some(Commands::Topics {
+            path,
+            k,
+            labels,
+            all_dirs,
+            globs,
+            excludes,
+            store,
+            json,
+        }) => {
+            topics::run(topics::TopicsOptions {
+                path,
+                globs,
+                excludes,
+                store,
+                cluster: TopicOptions {
+                    k,
+                    labels,
+                    ..Default::default()
+                },
+                all_dirs,
+                json,
+            })
+            .await?;
+        }
+        Some(Commands::Tui {
+            query,
+            path,
+            max_count,
+            globs,
+            excludes,
+            store,
+            code,
+            hybrid,
+            no_route,
+        }) => {
+            tui::run(tui::TuiOptions {
+                query,
+                path,
+                globs,
+                excludes,
+                store,
+                max_count,
+                code,
+                hybrid,
+                route: !no_route,
+            })
+            .await?;
+        }
+        Some(Commands::Repl {
+            path,
+            max_count,
+            store,
+            code,
+            hybrid,
+            colbert,
+            no_route,
+        }) => {
+            repl::run(repl::ReplOptions {
+                path,
+                store,
+                max_count,
+                code,
+                hybrid,
+                colbert,
+                route: !no_route,
             })
             .await?;
         }
@@ -463,6 +1145,15 @@ async fn main() -> Result<()> {
             base_url,
             provider,
             local_url,
+            rerank_model,
+            rerank_top_n,
+            rerank_url,
+            rerank_format,
+            rerank_endpoint_model,
+            rerank_auth_env,
+            rerank_timeout,
+            route_embedder,
+            hybrid_fusion,
             show,
             clear,
             download_model,
@@ -473,6 +1164,15 @@ async fn main() -> Result<()> {
                 base_url,
                 provider,
                 local_url,
+                rerank_model,
+                rerank_top_n,
+                rerank_url,
+                rerank_format,
+                rerank_endpoint_model,
+                rerank_auth_env,
+                rerank_timeout,
+                route_embedder,
+                hybrid_fusion,
                 show,
                 clear,
                 download_model,
@@ -500,6 +1200,132 @@ async fn main() -> Result<()> {
             })
             .await?;
         }
+        Some(Commands::Calibrate {
+            labels,
+            path,
+            max_count,
+            store,
+            code,
+            hybrid,
+            no_route,
+            dry_run,
+        }) => {
+            calibrate::run(calibrate::CalibrateOptions {
+                labels,
+                path,
+                store,
+                max_count,
+                code,
+                hybrid,
+                route: !no_route,
+                dry_run,
+            })
+            .await?;
+        }
+        Some(Commands::Eval {
+            labels,
+            path,
+            modes,
+            rerank,
+            k,
+            store,
+            compare,
+            save,
+            json,
+        }) => {
+            eval::run(eval::EvalOptions {
+                labels,
+                path,
+                store,
+                modes,
+                rerank: eval::parse_rerank(&rerank)?,
+                k,
+                compare,
+                save,
+                json,
+            })
+            .await?;
+        }
+        Some(Commands::Feedback {
+            query_id,
+            rank,
+            label,
+        }) => {
+            feedback::run(feedback::FeedbackOptions {
+                query_id,
+                rank,
+                label,
+            })
+            .await?;
+        }
+        Some(Commands::Tune {
+            store,
+            dry_run,
+            reset,
+            json,
+        }) => {
+            tune::run(tune::TuneOptions {
+                store,
+                dry_run,
+                reset,
+                json,
+            })
+            .await?;
+        }
+        Some(Commands::TrainFusion {
+            path,
+            store,
+            strategy,
+            docs,
+            commits,
+            epochs,
+            dry_run,
+        }) => {
+            train_fusion::run(train_fusion::TrainFusionOptions {
+                path,
+                store,
+                strategy,
+                docs,
+                commits,
+                epochs,
+                dry_run,
+            })
+            .await?;
+        }
+        Some(Commands::Trace {
+            file,
+            store,
+            max_count,
+            no_semantic,
+            json,
+        }) => {
+            trace::run(trace::TraceOptions {
+                text: trace::read_input(file.as_deref())?,
+                store,
+                max_count,
+                semantic: !no_semantic,
+                json,
+            })
+            .await?;
+        }
+        Some(Commands::Intent {
+            query,
+            train,
+            store,
+            dry_run,
+            reset,
+            json,
+        }) => {
+            intent::run(intent::IntentOptions {
+                query,
+                train,
+                store,
+                dry_run,
+                reset,
+                json,
+            })
+            .await?;
+        }
         Some(Commands::Compile {
             path,
             show,
@@ -895,17 +1721,36 @@ async fn main() -> Result<()> {
                 path,
                 max_count,
                 content: false,
+                before_context: 0,
+                after_context: 0,
                 answer: true,
                 sync,
                 rerank: true,
                 colbert: false,
                 file_types: None,
+                globs: Vec::new(),
+                excludes: Vec::new(),
                 store,
                 code: false,
                 hybrid: false,
-                json: false,
+                route: true,
+                format: OutputFormat::Pretty,
                 related: false,
                 related_depth: 1,
+                diverse: false,
+                lambda: 0.7,
+                max_per_file: None,
+                merge: true,
+                regex: None,
+                fixed: None,
+                ignore_case: false,
+                explain: false,
+                min_score: None,
+                offset: 0,
+                cursor: None,
+                level: SearchLevel::Chunk,
+                narrow: None,
+                batch: None,
             })
             .await?;
         }
@@ -1616,17 +2461,36 @@ export default tool({
                     path: None,
                     max_count: 10,
                     content: false,
+                    before_context: 0,
+                    after_context: 0,
                     answer: false,
                     sync: false,
                     rerank: true,
                     colbert: false,
                     file_types: None,
+                    globs: Vec::new(),
+                    excludes: Vec::new(),
                     store: None,
                     code: false,
                     hybrid: false,
-                    json: false,
+                    route: true,
+                    format: OutputFormat::Pretty,
                     related: false,
                     related_depth: 1,
+                    diverse: false,
+                    lambda: 0.7,
+                    max_per_file: None,
+                    merge: true,
+                    regex: None,
+                    fixed: None,
+                    ignore_case: false,
+                    explain: false,
+                    min_score: None,
+                    offset: 0,
+                    cursor: None,
+                    level: SearchLevel::Chunk,
+                    narrow: None,
+                    batch: None,
                 })
                 .await?;
             } else {
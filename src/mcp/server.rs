@@ -8,10 +8,24 @@ use std::fs;
 use std::io::{self, BufRead, Write};
 use std::path::Path;
 
+use crate::core::calibration::{self, Calibration, Calibrations, DEFAULT_MIN_CONFIDENCE};
 use crate::core::codemap::CodeMap;
+use crate::core::config::Config;
+use crate::core::diversify::{diversify, DiversifyOptions, DEFAULT_LAMBDA};
+use crate::core::feedback::{self, FeedbackLabel};
+use crate::core::filter::SearchFilter;
 use crate::core::hybrid_embedder::HybridEmbedder;
+use crate::core::intent::Router;
 use crate::core::local_embeddings::{LocalEmbedder, SpeedMode};
-use crate::core::search::HybridSearcher;
+use crate::core::merge::merge_results;
+use crate::core::query::{repo_path_for, ParsedQuery};
+use crate::core::result_cache::{
+    cache_key, cursor, parse_cursor, Cached, Page, RankedQuery, ResultCache,
+};
+use crate::core::search::{HybridSearcher, SearchResult};
+use crate::core::session::{EmbedMode, SearchTrace};
+use crate::core::similar;
+use crate::core::stacktrace::{map_frames, MessageMatcher, TraceParser};
 use crate::core::store::VectorStore;
 
 use super::protocol::*;
@@ -106,32 +120,103 @@ impl McpServer {
                     "properties": {
                         "query": {
                             "type": "string",
-                            "description": "Natural language search query (e.g., 'error handling for HTTP requests', 'database connection pooling', 'authentication middleware')"
+                            "description": "Natural language search query (e.g., 'error handling for HTTP requests', 'database connection pooling', 'authentication middleware'). Supports inline filters: lang:, kind:, symbol:, parent:, path:, -path:, ext:, since: (e.g. 'retry logic lang:rust path:src/core -path:tests since:2w')"
                         },
                         "path": {
                             "type": "string",
-                            "description": "Optional: Directory path to search in (defaults to current indexed directory)"
+                            "description": "Optional: Restrict results to this directory or file (defaults to the whole index)"
+                        },
+                        "glob": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Optional: Include globs relative to 'path' (e.g. 'src/**/*.rs'); prefix with '!' to exclude"
+                        },
+                        "exclude": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Optional: Exclude globs (e.g. 'vendor/**', '*_test.go')"
+                        },
+                        "file_types": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Optional: File extensions to include (e.g. ['rs', 'py'])"
                         },
                         "max_results": {
                             "type": "integer",
-                            "description": "Maximum number of results to return (default: 10, max: 50)",
+                            "description": "Maximum number of results per page (default: 10, max: 50)",
                             "default": 10
                         },
+                        "offset": {
+                            "type": "integer",
+                            "description": "Optional: Number of results to skip",
+                            "default": 0
+                        },
+                        "cursor": {
+                            "type": "string",
+                            "description": "Optional: Cursor from a previous response to fetch the next page; send the same query and options with it"
+                        },
                         "mode": {
                             "type": "string",
-                            "enum": ["balanced", "code", "hybrid"],
-                            "description": "Search mode: 'balanced' (general), 'code' (code-optimized), 'hybrid' (best quality, combines both)",
-                            "default": "balanced"
+                            "enum": ["auto", "balanced", "code", "hybrid"],
+                            "description": "Search mode: 'auto' (picked from the query: identifiers, code and errors weigh exact tokens more and use hybrid embeddings), 'balanced' (general), 'code' (code-optimized), 'hybrid' (best quality, combines both)",
+                            "default": "auto"
                         },
                         "include_content": {
                             "type": "boolean",
                             "description": "Include file content in results",
                             "default": true
+                        },
+                        "diverse": {
+                            "type": "boolean",
+                            "description": "Diversify results (MMR) so near-duplicate chunks don't crowd out other matches",
+                            "default": false
+                        },
+                        "lambda": {
+                            "type": "number",
+                            "description": "Relevance vs. diversity trade-off when 'diverse' is set (0.0-1.0, default: 0.7)",
+                            "default": 0.7
+                        },
+                        "max_per_file": {
+                            "type": "integer",
+                            "description": "Optional: Maximum number of results from a single file"
+                        },
+                        "merge": {
+                            "type": "boolean",
+                            "description": "Merge overlapping or adjacent hits from the same file into one span",
+                            "default": true
+                        },
+                        "min_score": {
+                            "type": "number",
+                            "description": "Minimum calibrated confidence (0.0-1.0) that a result is relevant (default: 0.5 once `sgrep calibrate` has fitted the model, otherwise no minimum). When nothing reaches it, the response says there are no confident results and lists the weak matches separately.",
+                            "default": 0.5
                         }
                     },
                     "required": ["query"]
                 }),
             },
+            ToolDefinition {
+                name: "search_feedback".to_string(),
+                description: "Rate a semantic_search result as good or bad. Ratings are logged locally and used by 'sgrep tune' to fit the keyword/vector weights of the search.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "query_id": {
+                            "type": "string",
+                            "description": "Query id printed at the end of the semantic_search results"
+                        },
+                        "rank": {
+                            "type": "integer",
+                            "description": "Number of the result in the semantic_search output (1 = first)"
+                        },
+                        "label": {
+                            "type": "string",
+                            "enum": ["good", "bad"],
+                            "description": "Whether the result was relevant to the query"
+                        }
+                    },
+                    "required": ["query_id", "rank", "label"]
+                }),
+            },
             ToolDefinition {
                 name: "index_directory".to_string(),
                 description: "Index a directory for semantic search. Creates vector embeddings of all code files for fast semantic search.".to_string(),
@@ -231,6 +316,14 @@ impl McpServer {
                             "type": "string",
                             "description": "Or: path to a file to find similar files to"
                         },
+                        "location": {
+                            "type": "string",
+                            "description": "Or: path:line inside an indexed chunk; reuses its stored embedding"
+                        },
+                        "symbol": {
+                            "type": "string",
+                            "description": "Or: indexed symbol name (name or Parent::name)"
+                        },
                         "max_results": {
                             "type": "integer",
                             "description": "Maximum results to return (default: 10)",
@@ -240,6 +333,25 @@ impl McpServer {
                     "required": []
                 }),
             },
+            ToolDefinition {
+                name: "search_stack_trace".to_string(),
+                description: "Find the code behind a panic, traceback, exception stack or error message. Maps Rust, Python, JavaScript/TypeScript, Go and Java frames onto indexed code and finds the string literals the error message was formatted from. For related code, call semantic_search with the error message.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "trace": {
+                            "type": "string",
+                            "description": "The stack trace or error output, pasted as is"
+                        },
+                        "max_results": {
+                            "type": "integer",
+                            "description": "Maximum raise sites to return (default: 5)",
+                            "default": 5
+                        }
+                    },
+                    "required": ["trace"]
+                }),
+            },
             ToolDefinition {
                 name: "ask_codebase".to_string(),
                 description: "Ask a natural language question about the codebase and get an AI-synthesized answer based on relevant code. Best for understanding how things work.".to_string(),
@@ -315,11 +427,13 @@ impl McpServer {
 
         let result = match call.name.as_str() {
             "semantic_search" => self.execute_semantic_search(call.arguments),
+            "search_feedback" => self.execute_search_feedback(call.arguments),
             "index_directory" => self.execute_index_directory(call.arguments),
             "get_codebase_map" => self.execute_get_codebase_map(call.arguments),
             "search_symbols" => self.execute_search_symbols(call.arguments),
             "expand_symbol" => self.execute_expand_symbol(call.arguments),
             "find_similar_code" => self.execute_find_similar_code(call.arguments),
+            "search_stack_trace" => self.execute_search_stack_trace(call.arguments),
             "ask_codebase" => self.execute_ask_codebase(call.arguments),
             "get_file_context" => self.execute_get_file_context(call.arguments),
             "list_indexed_files" => self.execute_list_indexed_files(call.arguments),
@@ -340,26 +454,67 @@ impl McpServer {
             None => return ToolCallResult::error("Missing required 'query' argument".to_string()),
         };
 
+        // Inline operators (lang:, path:, kind:, ...) become filters
+        let parsed = match ParsedQuery::parse(&query) {
+            Ok(p) if p.text.is_empty() => {
+                return ToolCallResult::error(
+                    "Query has no search text besides filters".to_string(),
+                )
+            }
+            Ok(p) => p,
+            Err(e) => return ToolCallResult::error(format!("Invalid query: {}", e)),
+        };
+
         let max_results = args
             .get("max_results")
             .and_then(|v| v.as_u64())
             .unwrap_or(10)
             .min(50) as usize;
 
-        let mode = args
-            .get("mode")
-            .and_then(|v| v.as_str())
-            .unwrap_or("balanced");
+        let mode = args.get("mode").and_then(|v| v.as_str()).unwrap_or("auto");
 
         let include_content = args
             .get("include_content")
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
 
-        let store_path = args.get("path").and_then(|v| v.as_str());
+        let diverse = args
+            .get("diverse")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let lambda = args
+            .get("lambda")
+            .and_then(|v| v.as_f64())
+            .map(|v| v as f32)
+            .unwrap_or(DEFAULT_LAMBDA);
+
+        let max_per_file = args
+            .get("max_per_file")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize);
+
+        let merge = args.get("merge").and_then(|v| v.as_bool()).unwrap_or(true);
+
+        let min_score = args
+            .get("min_score")
+            .and_then(|v| v.as_f64())
+            .map(|v| v as f32);
+
+        let diversify_options = if diverse || max_per_file.is_some() {
+            let lambda = if diverse { lambda } else { 1.0 };
+            Some(DiversifyOptions::new(lambda).with_max_per_file(max_per_file))
+        } else {
+            None
+        };
+
+        let filter = match search_filter(&args, &parsed) {
+            Ok(f) => f,
+            Err(e) => return ToolCallResult::error(format!("Invalid filter: {}", e)),
+        };
 
         // Load the vector store
-        let store = match VectorStore::load(store_path) {
+        let store = match VectorStore::load(None) {
             Ok(s) => s,
             Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
         };
@@ -371,46 +526,158 @@ impl McpServer {
             );
         }
 
-        // Generate query embedding based on mode
-        let query_embedding = match mode {
-            "hybrid" => match HybridEmbedder::new() {
-                Ok(mut embedder) => match embedder.embed_query(&query) {
-                    Ok(emb) => emb,
-                    Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-                },
-                Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
-            },
-            "code" => match LocalEmbedder::with_speed_mode(SpeedMode::Code) {
-                Ok(mut embedder) => match embedder.embed_query(&query) {
-                    Ok(emb) => emb,
-                    Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
-                },
-                Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
+        // Ranked lists are cached per query and store generation, so later
+        // pages skip embedding and search. Paging and presentation arguments
+        // don't change the ranking and are left out of the key.
+        let mut ranking_args = args.clone();
+        if let Some(map) = ranking_args.as_object_mut() {
+            for name in [
+                "cursor",
+                "offset",
+                "max_results",
+                "include_content",
+                "min_score",
+            ] {
+                map.remove(name);
+            }
+        }
+        let config = Config::load().unwrap_or_default();
+        let fusion = format!(
+            "{:?} {}",
+            config.get_fusion_weights(),
+            config.get_hybrid_alpha()
+        );
+
+        // Auto mode routes by query intent; embeddings here are always local
+        let route =
+            (mode == "auto").then(|| Router::new(&config, &store, true).route(&parsed.text));
+        let mode = match route {
+            Some(ref route) if route.mode == EmbedMode::Hybrid => "hybrid",
+            Some(_) => "balanced",
+            None => mode,
+        };
+
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let key = cache_key(&[
+            "mcp",
+            &cwd.to_string_lossy(),
+            &ranking_args.to_string(),
+            &fusion,
+            route.as_ref().map_or("", |r| r.intent.as_str()),
+        ]);
+        let offset = match args.get("cursor").and_then(|v| v.as_str()) {
+            Some(cursor) => match parse_cursor(cursor, &key) {
+                Ok(offset) => offset,
+                Err(e) => return ToolCallResult::error(e.to_string()),
             },
-            _ => {
-                // balanced mode
-                match LocalEmbedder::with_speed_mode(SpeedMode::Balanced) {
-                    Ok(mut embedder) => match embedder.embed_query(&query) {
-                        Ok(emb) => emb,
-                        Err(e) => return ToolCallResult::error(format!("Embedding failed: {}", e)),
+            None => args.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize,
+        };
+        let needed = offset + max_results;
+        let generation = VectorStore::generation(None).unwrap_or(0);
+        let cache = ResultCache::open().ok();
+
+        let cached = match cache
+            .as_ref()
+            .and_then(|c| c.get(&key, generation, &store, needed))
+        {
+            Some(cached) => cached,
+            None => {
+                // Generate query embedding based on mode
+                let query_embedding = match mode {
+                    "hybrid" => match HybridEmbedder::with_strategy(config.hybrid_strategy()) {
+                        Ok(mut embedder) => match embedder.embed_query(&parsed.text) {
+                            Ok(emb) => emb,
+                            Err(e) => {
+                                return ToolCallResult::error(format!("Embedding failed: {}", e))
+                            }
+                        },
+                        Err(e) => {
+                            return ToolCallResult::error(format!("Model load failed: {}", e))
+                        }
+                    },
+                    "code" => match LocalEmbedder::with_speed_mode(SpeedMode::Code) {
+                        Ok(mut embedder) => match embedder.embed_query(&parsed.text) {
+                            Ok(emb) => emb,
+                            Err(e) => {
+                                return ToolCallResult::error(format!("Embedding failed: {}", e))
+                            }
+                        },
+                        Err(e) => {
+                            return ToolCallResult::error(format!("Model load failed: {}", e))
+                        }
                     },
-                    Err(e) => return ToolCallResult::error(format!("Model load failed: {}", e)),
+                    _ => {
+                        // balanced mode
+                        match LocalEmbedder::with_speed_mode(SpeedMode::Balanced) {
+                            Ok(mut embedder) => match embedder.embed_query(&parsed.text) {
+                                Ok(emb) => emb,
+                                Err(e) => {
+                                    return ToolCallResult::error(format!(
+                                        "Embedding failed: {}",
+                                        e
+                                    ))
+                                }
+                            },
+                            Err(e) => {
+                                return ToolCallResult::error(format!("Model load failed: {}", e))
+                            }
+                        }
+                    }
+                };
+
+                // Search (over a larger pool when merging or diversifying)
+                let searcher = match route {
+                    Some(ref route) => route.searcher(),
+                    None => HybridSearcher::from_config(&config),
+                };
+                let pool = if merge || diversify_options.is_some() {
+                    needed * 3
+                } else {
+                    needed
+                };
+                let mut results = searcher.search(
+                    &store,
+                    &query_embedding,
+                    &parsed.text,
+                    pool,
+                    Some(&filter),
+                    false,
+                    None,
+                );
+
+                // Confidence is calibrated on the score before merge bonuses
+                let trace = SearchTrace::retrieved(&results);
+
+                if merge {
+                    results = merge_results(&store, results);
+                }
+
+                if let Some(ref diversify_options) = diversify_options {
+                    results = diversify(results, needed, diversify_options);
+                } else {
+                    results.truncate(needed);
+                }
+
+                if let Some(ref cache) = cache {
+                    let ranked_for = RankedQuery {
+                        text: &parsed.text,
+                        mode,
+                    };
+                    let _ = cache.put(&key, generation, ranked_for, &results, &trace, needed);
+                }
+                Cached {
+                    exhausted: results.len() < needed,
+                    results,
+                    trace,
                 }
             }
         };
+        let page = Page::new(&key, &cached, offset, max_results);
+        let results = page.results;
 
-        // Search
-        let searcher = HybridSearcher::default();
-        let results = searcher.search(
-            &store,
-            &query_embedding,
-            &query,
-            max_results,
-            None,
-            false,
-            None,
-        );
-
+        if results.is_empty() && offset > 0 {
+            return ToolCallResult::success(format!("No more results for query: '{}'", query));
+        }
         if results.is_empty() {
             return ToolCallResult::success(format!(
                 "No results found for query: '{}'\n\nTry:\n- Different search terms\n- Check if the directory is indexed",
@@ -418,21 +685,92 @@ impl McpServer {
             ));
         }
 
+        // Calibrated confidence separates "not in this codebase" from weak
+        // matches. The built-in calibration isn't fitted to any model, so
+        // it only filters when asked to.
+        let model = match mode {
+            "hybrid" => calibration::HYBRID,
+            "code" => calibration::LOCAL_CODE,
+            _ => calibration::LOCAL_BALANCED,
+        };
+        let calibration = match Calibrations::load() {
+            Ok(calibrations) => {
+                calibrations.get(&calibration::calibration_key(model, route.as_ref()))
+            }
+            Err(_) => Calibration::default(),
+        };
+        let min_score = min_score.unwrap_or(if calibration.is_fitted() {
+            DEFAULT_MIN_CONFIDENCE
+        } else {
+            0.0
+        });
+        let confidences: Vec<f32> = results
+            .iter()
+            .map(|r| calibration.confidence(cached.trace.retrieval_score(r)))
+            .collect();
+        let confident = confidences.iter().filter(|&&c| c >= min_score).count();
+
+        if confident == 0 && offset > 0 {
+            return ToolCallResult::success(format!(
+                "No more confident results for query: '{}' (minimum {:.0}% confidence)",
+                query,
+                min_score * 100.0
+            ));
+        }
+        if confident == 0 {
+            let mut output = format!(
+                "No confident results for: '{}'\n\nBest match has {:.0}% confidence (minimum {:.0}%). This code is likely not in the indexed codebase. Weak matches:\n\n",
+                query,
+                confidences.iter().copied().fold(0.0, f32::max) * 100.0,
+                min_score * 100.0
+            );
+            for (result, confidence) in results.iter().zip(&confidences).take(3) {
+                output.push_str(&format!(
+                    "- {} lines {}-{} ({:.0}% confidence)\n",
+                    result.chunk.file_path,
+                    result.chunk.start_line,
+                    result.chunk.end_line,
+                    confidence * 100.0
+                ));
+            }
+            return ToolCallResult::success(output);
+        }
+
+        // Once weak results show up, later pages only hold weaker ones
+        let next_cursor = page.next_cursor.filter(|_| confident == results.len());
+        let results: Vec<_> = results
+            .into_iter()
+            .zip(confidences)
+            .filter(|(_, confidence)| *confidence >= min_score)
+            .collect();
+
         // Format results
         let mut output = format!("Found {} results for: '{}'\n\n", results.len(), query);
 
-        for (i, result) in results.iter().enumerate() {
-            let score_pct = (result.score * 100.0) as u32;
+        for (i, (result, confidence)) in results.iter().enumerate() {
+            let strength = if calibration.is_fitted() {
+                format!("{:.0}% confidence", confidence * 100.0)
+            } else {
+                format!("score {:.2}", cached.trace.retrieval_score(result))
+            };
             output.push_str(&format!(
-                "{}. {} ({}% match)\n",
-                i + 1,
+                "{}. {} ({})\n",
+                offset + i + 1,
                 result.chunk.file_path,
-                score_pct
+                strength
             ));
             output.push_str(&format!(
                 "   Lines {}-{}\n",
                 result.chunk.start_line, result.chunk.end_line
             ));
+            if result.matched_ranges.len() > 1 {
+                let matched: Vec<String> = result
+                    .matched_ranges
+                    .iter()
+                    .map(|(start, end)| format!("{}-{}", start, end))
+                    .collect();
+                output.push_str(&format!("   Matched lines {}\n", matched.join(", ")));
+            }
 
             if include_content {
                 output.push_str("   ```\n");
@@ -447,9 +785,73 @@ impl McpServer {
             output.push('\n');
         }
 
+        if let Some(cursor) = next_cursor {
+            output.push_str(&format!(
+                "More results available: call semantic_search again with the same query and \"cursor\": \"{}\"\n",
+                cursor
+            ));
+        }
+        if let Some(ref route) = route {
+            output.push_str(&format!("Route: {}\n", route.summary()));
+        }
+        // Record the page as numbered above so ratings outlive the cache
+        let shown: Vec<SearchResult> = results.iter().map(|(r, _)| r.clone()).collect();
+        let ranked_for = RankedQuery {
+            text: &parsed.text,
+            mode,
+        };
+        if cache
+            .as_ref()
+            .is_some_and(|c| c.record_shown(&key, ranked_for, offset, &shown).is_ok())
+        {
+            output.push_str(&format!(
+                "Query id: {} (rate a result with search_feedback and its number above)\n",
+                cursor(&key, 0)
+            ));
+        }
+
         ToolCallResult::success(output)
     }
 
+    fn execute_search_feedback(&self, args: Option<Value>) -> ToolCallResult {
+        let args = match args {
+            Some(a) => a,
+            None => return ToolCallResult::error("Missing arguments".to_string()),
+        };
+
+        let query_id = match args.get("query_id").and_then(|v| v.as_str()) {
+            Some(id) => id,
+            None => {
+                return ToolCallResult::error("Missing required 'query_id' argument".to_string())
+            }
+        };
+        let rank = match args.get("rank").and_then(|v| v.as_u64()) {
+            Some(rank) => rank as usize,
+            None => return ToolCallResult::error("Missing required 'rank' argument".to_string()),
+        };
+        let label = match args
+            .get("label")
+            .and_then(|v| v.as_str())
+            .map(str::parse::<FeedbackLabel>)
+        {
+            Some(Ok(label)) => label,
+            Some(Err(e)) => return ToolCallResult::error(e.to_string()),
+            None => return ToolCallResult::error("Missing required 'label' argument".to_string()),
+        };
+
+        match feedback::record(query_id, rank, label) {
+            Ok(entry) => ToolCallResult::success(format!(
+                "Marked {} lines {}-{} as {} for '{}'",
+                entry.file_path,
+                entry.start_line,
+                entry.end_line,
+                entry.label.as_str(),
+                entry.query
+            )),
+            Err(e) => ToolCallResult::error(e.to_string()),
+        }
+    }
+
     fn execute_index_directory(&self, args: Option<Value>) -> ToolCallResult {
         let args = match args {
             Some(a) => a,
@@ -717,6 +1119,12 @@ impl McpServer {
             .and_then(|v| v.as_u64())
             .unwrap_or(10) as usize;
 
+        let location = args.get("location").and_then(|v| v.as_str());
+        let symbol = args.get("symbol").and_then(|v| v.as_str());
+        if location.is_some() || symbol.is_some() {
+            return self.similar_to_indexed(location, symbol, max_results);
+        }
+
         let query_text = match (code, file) {
             (Some(c), _) => c.to_string(),
             (_, Some(f)) => match fs::read_to_string(f) {
@@ -725,7 +1133,7 @@ impl McpServer {
             },
             (None, None) => {
                 return ToolCallResult::error(
-                    "Either 'code' or 'file' argument is required".to_string(),
+                    "One of 'code', 'file', 'location' or 'symbol' is required".to_string(),
                 );
             }
         };
@@ -752,7 +1160,7 @@ impl McpServer {
         };
 
         // Search for similar code
-        let searcher = HybridSearcher::default();
+        let searcher = HybridSearcher::from_config(&Config::load().unwrap_or_default());
         let results = searcher.search(
             &store,
             &query_embedding,
@@ -763,32 +1171,133 @@ impl McpServer {
             None,
         );
 
-        if results.is_empty() {
-            return ToolCallResult::success("No similar code found.".to_string());
+        similar_output(&results)
+    }
+
+    /// Neighbours of an indexed chunk, using its stored embedding
+    fn similar_to_indexed(
+        &self,
+        location: Option<&str>,
+        symbol: Option<&str>,
+        max_results: usize,
+    ) -> ToolCallResult {
+        let store = match VectorStore::load(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+
+        let chunk = match (location, symbol) {
+            (Some(location), _) => similar::parse_location(location)
+                .and_then(|(path, line)| similar::chunk_at(&store, path, line)),
+            (None, Some(symbol)) => similar::chunk_for_symbol(&store, symbol),
+            (None, None) => Err(anyhow::anyhow!("Either 'location' or 'symbol' is required")),
+        };
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => return ToolCallResult::error(e.to_string()),
+        };
+
+        similar_output(&similar::similar_to(&store, chunk, max_results, None))
+    }
+
+    fn execute_search_stack_trace(&self, args: Option<Value>) -> ToolCallResult {
+        let args = args.unwrap_or(json!({}));
+        let Some(text) = args.get("trace").and_then(|v| v.as_str()) else {
+            return ToolCallResult::error("Missing required 'trace' argument".to_string());
+        };
+        let max_results = args
+            .get("max_results")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(5) as usize;
+
+        let trace = match TraceParser::new() {
+            Ok(parser) => parser.parse(text),
+            Err(e) => return ToolCallResult::error(e.to_string()),
+        };
+        if trace.is_empty() {
+            return ToolCallResult::error(
+                "No stack frames or error message found in 'trace'".to_string(),
+            );
+        }
+
+        let store = match VectorStore::load(None) {
+            Ok(s) => s,
+            Err(e) => return ToolCallResult::error(format!("Failed to load index: {}", e)),
+        };
+        if store.chunk_count() == 0 {
+            return ToolCallResult::error(
+                "No files indexed. Run 'sgrep watch <path>' first.".to_string(),
+            );
         }
 
-        let mut output = format!("Found {} similar code snippets:\n\n", results.len());
+        let mut output = String::new();
+        if let Some(format) = trace.format {
+            output.push_str(&format!("{} trace", format.as_str()));
+        }
+        if let Some(ref error_type) = trace.error_type {
+            output.push_str(&format!(" {}", error_type));
+        }
+        if let Some(ref message) = trace.message {
+            output.push_str(&format!(": {}", message));
+        }
+        output.push_str("\n\n");
 
-        for (i, result) in results.iter().enumerate() {
-            let score_pct = (result.score * 100.0) as u32;
+        let frames = map_frames(&store, &trace.frames);
+        let outside = frames.iter().filter(|f| f.file.is_none()).count();
+        if frames.len() > outside {
+            output.push_str("Frames (innermost first):\n");
+        }
+        for (i, frame) in frames.iter().filter(|f| f.file.is_some()).enumerate() {
+            let file = frame.file.as_deref().unwrap_or_default();
             output.push_str(&format!(
-                "{}. {} ({}% similar)\n",
+                "{}. {}{}",
                 i + 1,
-                result.chunk.file_path,
-                score_pct
+                file,
+                frame
+                    .frame
+                    .line
+                    .map(|l| format!(":{}", l))
+                    .unwrap_or_default()
             ));
-            output.push_str(&format!(
-                "   Lines {}-{}\n",
-                result.chunk.start_line, result.chunk.end_line
-            ));
-            output.push_str("   ```\n");
-            for line in result.chunk.content.lines().take(10) {
-                output.push_str(&format!("   {}\n", line));
+            if let Some(ref function) = frame.frame.function {
+                output.push_str(&format!(" in {}", function));
             }
-            if result.chunk.content.lines().count() > 10 {
-                output.push_str("   ...\n");
+            output.push('\n');
+            if let Some(ref chunk) = frame.chunk {
+                output.push_str(&format!(
+                    "   Chunk lines {}-{}{}\n",
+                    chunk.start_line,
+                    chunk.end_line,
+                    chunk
+                        .symbol_name
+                        .as_ref()
+                        .map(|s| format!(" ({})", s))
+                        .unwrap_or_default()
+                ));
+            }
+        }
+        if outside > 0 {
+            output.push_str(&format!("{} frame(s) outside the index\n", outside));
+        }
+
+        if let Some(ref message) = trace.message {
+            let sites = match MessageMatcher::new() {
+                Ok(matcher) => matcher.raise_sites(&store, message, max_results),
+                Err(e) => return ToolCallResult::error(e.to_string()),
+            };
+            if !sites.is_empty() {
+                output.push_str("\nMessage raised at:\n");
+            }
+            for (i, site) in sites.iter().enumerate() {
+                output.push_str(&format!(
+                    "{}. {}:{} \"{}\" ({}% of the message)\n",
+                    i + 1,
+                    site.chunk.file_path,
+                    site.line,
+                    site.literal,
+                    (site.coverage * 100.0) as u32
+                ));
             }
-            output.push_str("   ```\n\n");
         }
 
         ToolCallResult::success(output)
@@ -834,7 +1343,7 @@ impl McpServer {
         };
 
         // Search for relevant context
-        let searcher = HybridSearcher::default();
+        let searcher = HybridSearcher::from_config(&Config::load().unwrap_or_default());
         let results = searcher.search(
             &store,
             &query_embedding,
@@ -1060,3 +1569,60 @@ impl Default for McpServer {
         Self::new()
     }
 }
+
+fn similar_output(results: &[SearchResult]) -> ToolCallResult {
+    if results.is_empty() {
+        return ToolCallResult::success("No similar code found.".to_string());
+    }
+
+    let mut output = format!("Found {} similar code snippets:\n\n", results.len());
+
+    for (i, result) in results.iter().enumerate() {
+        let score_pct = (result.score * 100.0) as u32;
+        output.push_str(&format!(
+            "{}. {} ({}% similar)\n",
+            i + 1,
+            result.chunk.file_path,
+            score_pct
+        ));
+        output.push_str(&format!(
+            "   Lines {}-{}\n",
+            result.chunk.start_line, result.chunk.end_line
+        ));
+        output.push_str("   ```\n");
+        for line in result.chunk.content.lines().take(10) {
+            output.push_str(&format!("   {}\n", line));
+        }
+        if result.chunk.content.lines().count() > 10 {
+            output.push_str("   ...\n");
+        }
+        output.push_str("   ```\n\n");
+    }
+
+    ToolCallResult::success(output)
+}
+
+/// Build a search filter from `path`, `glob`, `exclude` and `file_types`
+/// arguments plus the query's inline operators
+fn search_filter(args: &Value, query: &ParsedQuery) -> Result<SearchFilter> {
+    let strings = |key: &str| -> Vec<String> {
+        args.get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| {
+                a.iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let path = args.get("path").and_then(|v| v.as_str());
+    let mut filter = SearchFilter::new();
+    if let Some(path) = path {
+        filter = filter.with_root(path)?;
+    }
+    filter = filter
+        .with_globs(&strings("glob"), &strings("exclude"))?
+        .with_file_types(&strings("file_types"));
+    query.apply(filter, &repo_path_for(path))
+}
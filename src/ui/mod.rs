@@ -6,6 +6,8 @@
 //! - Animated progress indicators
 //! - Rich syntax highlighting
 
+pub mod output;
 pub mod progress;
 pub mod search_display;
 pub mod theme;
+pub mod tui;
@@ -0,0 +1,293 @@
+//! Machine-readable output formats
+//!
+//! - `vimgrep`: `file:line:col:text`, one line per match (as `rg --vimgrep`)
+//! - `quickfix`: `file:line:col: message`, one entry per result, for
+//!   `:cfile`, `compilation-mode` and problem matchers
+//! - `json`: ripgrep's `--json` event stream, with `begin`/`match`/`end`
+//!   messages per file and a final `summary`
+//! - `json-summary`: a single JSON document with all results
+
+use anyhow::{bail, Result};
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Pretty,
+    Json,
+    JsonSummary,
+    Vimgrep,
+    Quickfix,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "json" => Ok(OutputFormat::Json),
+            "json-summary" => Ok(OutputFormat::JsonSummary),
+            "vimgrep" => Ok(OutputFormat::Vimgrep),
+            "quickfix" => Ok(OutputFormat::Quickfix),
+            other => bail!(
+                "Unknown output format '{}' (expected pretty, json, json-summary, vimgrep or quickfix)",
+                other
+            ),
+        }
+    }
+
+    /// Human-oriented output with colors and progress animation
+    pub fn is_pretty(&self) -> bool {
+        *self == OutputFormat::Pretty
+    }
+}
+
+/// Matches in one file, in output order
+pub struct OutputFile {
+    /// Absolute path, used to compute byte offsets
+    pub path: String,
+    pub matches: Vec<OutputMatch>,
+}
+
+/// One match: a line (grep mode) or a chunk (semantic results)
+pub struct OutputMatch {
+    pub line_number: usize,
+    /// Matched line(s), without a trailing newline
+    pub text: String,
+    /// Byte ranges within `text`
+    pub submatches: Vec<(usize, usize)>,
+    /// Short description for quickfix entries (defaults to the first line)
+    pub label: Option<String>,
+    pub score: Option<f32>,
+}
+
+impl OutputMatch {
+    /// A whole chunk as one match, with the submatch starting at its first
+    /// non-blank character so editors jump to the code
+    pub fn chunk(line_number: usize, text: &str, label: Option<String>, score: f32) -> Self {
+        let start = text.len() - text.trim_start().len();
+        Self {
+            line_number,
+            text: text.to_string(),
+            submatches: vec![(start, text.trim_end().len().max(start))],
+            label,
+            score: Some(score),
+        }
+    }
+
+    /// Line number and 1-based column of a byte offset within `text`
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset];
+        let line = self.line_number + before.matches('\n').count();
+        let col = offset - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, col)
+    }
+
+    fn line_at(&self, offset: usize) -> &str {
+        let start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |i| offset + i);
+        &self.text[start..end]
+    }
+}
+
+/// Path relative to the current directory, as ripgrep prints it
+pub fn relative_path(path: &str) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    Path::new(path)
+        .strip_prefix(&cwd)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// `file:line:col:text`, one line per submatch
+pub fn print_vimgrep(files: &[OutputFile]) {
+    for file in files {
+        let path = relative_path(&file.path);
+        for m in &file.matches {
+            for &(start, _) in &m.submatches {
+                let (line, col) = m.position(start);
+                println!("{}:{}:{}:{}", path, line, col, m.line_at(start));
+            }
+        }
+    }
+}
+
+/// `file:line:col: message`, one entry per match
+pub fn print_quickfix(files: &[OutputFile]) {
+    for file in files {
+        let path = relative_path(&file.path);
+        for m in &file.matches {
+            let start = m.submatches.first().map_or(0, |s| s.0);
+            let (line, col) = m.position(start);
+            let label = m
+                .label
+                .clone()
+                .unwrap_or_else(|| m.line_at(start).trim().to_string());
+            match m.score {
+                Some(score) => println!("{}:{}:{}: [{:.2}] {}", path, line, col, score, label),
+                None => println!("{}:{}:{}: {}", path, line, col, label),
+            }
+        }
+    }
+}
+
+/// ripgrep `--json` event stream
+pub fn print_json_events(files: &[OutputFile], elapsed: Duration) {
+    let mut total = Stats::default();
+
+    for file in files {
+        let path = relative_path(&file.path);
+        let content = std::fs::read(&file.path).unwrap_or_default();
+        let offsets = line_offsets(&content);
+        let mut stats = Stats {
+            searches: 1,
+            searches_with_match: usize::from(!file.matches.is_empty()),
+            bytes_searched: content.len(),
+            ..Default::default()
+        };
+
+        stats.bytes_printed += emit(json!({
+            "type": "begin",
+            "data": { "path": { "text": path } }
+        }));
+
+        for m in &file.matches {
+            let submatches: Vec<_> = m
+                .submatches
+                .iter()
+                .map(|&(start, end)| {
+                    json!({ "match": { "text": &m.text[start..end] }, "start": start, "end": end })
+                })
+                .collect();
+            let mut data = json!({
+                "path": { "text": path },
+                "lines": { "text": format!("{}\n", m.text) },
+                "line_number": m.line_number,
+                "absolute_offset": offsets.get(m.line_number.saturating_sub(1)).copied().unwrap_or(0),
+                "submatches": submatches,
+            });
+            if let Some(score) = m.score {
+                data["score"] = json!(score);
+            }
+
+            stats.matched_lines += m.text.lines().count().max(1);
+            stats.matches += m.submatches.len();
+            stats.bytes_printed += emit(json!({ "type": "match", "data": data }));
+        }
+
+        stats.bytes_printed += emit(json!({
+            "type": "end",
+            "data": {
+                "path": { "text": path },
+                "binary_offset": null,
+                "stats": stats.to_json(elapsed),
+            }
+        }));
+        total.add(&stats);
+    }
+
+    emit(json!({
+        "type": "summary",
+        "data": {
+            "elapsed_total": elapsed_json(elapsed),
+            "stats": total.to_json(elapsed),
+        }
+    }));
+}
+
+/// Print one JSON line, returning the number of bytes written
+fn emit(value: serde_json::Value) -> usize {
+    let line = value.to_string();
+    println!("{}", line);
+    line.len() + 1
+}
+
+/// Byte offset of the start of each line
+fn line_offsets(content: &[u8]) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(
+            content
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b == b'\n')
+                .map(|(i, _)| i + 1),
+        )
+        .collect()
+}
+
+#[derive(Default)]
+struct Stats {
+    searches: usize,
+    searches_with_match: usize,
+    bytes_searched: usize,
+    bytes_printed: usize,
+    matched_lines: usize,
+    matches: usize,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.bytes_searched += other.bytes_searched;
+        self.bytes_printed += other.bytes_printed;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+
+    fn to_json(&self, elapsed: Duration) -> serde_json::Value {
+        json!({
+            "elapsed": elapsed_json(elapsed),
+            "searches": self.searches,
+            "searches_with_match": self.searches_with_match,
+            "bytes_searched": self.bytes_searched,
+            "bytes_printed": self.bytes_printed,
+            "matched_lines": self.matched_lines,
+            "matches": self.matches,
+        })
+    }
+}
+
+fn elapsed_json(elapsed: Duration) -> serde_json::Value {
+    json!({
+        "secs": elapsed.as_secs(),
+        "nanos": elapsed.subsec_nanos(),
+        "human": format!("{:.6}s", elapsed.as_secs_f64()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_positions() {
+        let m = OutputMatch {
+            line_number: 10,
+            text: "fn a() {\n    x.unwrap()\n}".to_string(),
+            submatches: vec![(15, 23)],
+            label: None,
+            score: None,
+        };
+        assert_eq!(m.position(15), (11, 7));
+        assert_eq!(m.line_at(15), "    x.unwrap()");
+        assert_eq!(m.position(0), (10, 1));
+    }
+
+    #[test]
+    fn test_chunk_match_starts_at_code() {
+        let m = OutputMatch::chunk(5, "    pub fn run() {}\n", None, 0.5);
+        assert_eq!(m.submatches, vec![(4, 19)]);
+        assert_eq!(m.position(4), (5, 5));
+    }
+
+    #[test]
+    fn test_line_offsets() {
+        assert_eq!(line_offsets(b"ab\ncd\n"), vec![0, 3, 6]);
+        assert!(OutputFormat::parse("vimgrep").is_ok());
+        assert!(OutputFormat::parse("xml").is_err());
+    }
+}
@@ -10,10 +10,20 @@ use colored::Colorize;
 use std::path::Path;
 
 use super::theme::{BoxChars, Theme};
+use crate::core::explain::Explanation;
 use crate::core::search::SearchResult;
-
-/// Display search results with beautiful formatting
-pub fn display_results(query: &str, results: &[SearchResult], show_content: bool) {
+use crate::core::snippet::{Freshness, Snippet};
+
+/// Display search results with beautiful formatting. `snippets`, when given,
+/// holds one on-disk snippet per result and replaces the stored content preview.
+/// `explanations`, when given, adds a score breakdown under each result.
+pub fn display_results(
+    query: &str,
+    results: &[SearchResult],
+    show_content: bool,
+    snippets: Option<&[Snippet]>,
+    explanations: Option<&[Explanation]>,
+) {
     let term_width = terminal_width();
 
     // Header
@@ -26,7 +36,11 @@ pub fn display_results(query: &str, results: &[SearchResult], show_content: bool
 
     // Results
     for (i, result) in results.iter().enumerate() {
-        print_result(i + 1, result, show_content, term_width);
+        let snippet = snippets.and_then(|s| s.get(i));
+        print_result(i + 1, result, show_content, snippet, term_width);
+        if let Some(explanation) = explanations.and_then(|e| e.get(i)) {
+            print_explanation(explanation);
+        }
     }
 
     // Footer
@@ -75,12 +89,20 @@ fn print_header(query: &str, count: usize, width: usize) {
 }
 
 /// Print a single search result
-fn print_result(index: usize, result: &SearchResult, show_content: bool, width: usize) {
+fn print_result(
+    index: usize,
+    result: &SearchResult,
+    show_content: bool,
+    snippet: Option<&Snippet>,
+    width: usize,
+) {
     let score = result.score;
     let score_pct = (score * 100.0) as u32;
     let file_path = &result.chunk.file_path;
-    let start_line = result.chunk.start_line;
-    let end_line = result.chunk.end_line;
+    let (start_line, end_line) = match snippet {
+        Some(s) => (s.start_line, s.end_line),
+        None => (result.chunk.start_line, result.chunk.end_line),
+    };
     let lang = result.chunk.language.as_deref().unwrap_or("text");
 
     // Extract just the filename and parent dir for display
@@ -97,12 +119,30 @@ fn print_result(index: usize, result: &SearchResult, show_content: bool, width:
     let lang_badge = format!(" {} ", lang.to_uppercase());
     let lang_color = Theme::lang_color(lang);
 
-    // Line range
-    let line_range = format!("L{}-{}", start_line, end_line);
+    // Line range, plus the matched sub-ranges of a merged span
+    let line_range = if result.matched_ranges.len() > 1 {
+        let matched: Vec<String> = result
+            .matched_ranges
+            .iter()
+            .map(|(start, end)| format!("{}-{}", start, end))
+            .collect();
+        format!("L{}-{} [{}]", start_line, end_line, matched.join(","))
+    } else {
+        format!("L{}-{}", start_line, end_line)
+    };
+
+    // Flag results whose file changed since indexing
+    let freshness_badge = match snippet.map(|s| s.freshness) {
+        Some(Freshness::Moved(from)) => format!("  moved from L{}", from),
+        Some(Freshness::Changed) => "  file changed".to_string(),
+        Some(Freshness::Stale) => "  stale: reindex".to_string(),
+        Some(Freshness::Missing) => "  file missing".to_string(),
+        Some(Freshness::Fresh) | None => String::new(),
+    };
 
     // Main result line
     println!(
-        "{} {}{}  {}  {}  {}  {}",
+        "{} {}{}  {}  {}  {}  {}{}",
         BoxChars::V_LINE.color(Theme::BORDER),
         index_badge
             .on_color(Theme::BORDER_ACCENT)
@@ -112,16 +152,19 @@ fn print_result(index: usize, result: &SearchResult, show_content: bool, width:
         score_text.color(Theme::score_color(score)).bold(),
         display_path.color(Theme::NEON_CYAN),
         line_range.color(Theme::SUBTLE),
-        lang_badge.on_color(lang_color).color(colored::Color::Black)
+        lang_badge.on_color(lang_color).color(colored::Color::Black),
+        freshness_badge.color(Theme::SCORE_LOW)
     );
 
     // Content preview if enabled
-    if show_content {
+    if let Some(snippet) = snippet {
+        print_snippet_preview(snippet, width, lang);
+    } else if show_content {
         print_content_preview(&result.chunk.content, width, lang);
     }
 
     // Subtle separator between results
-    if !show_content {
+    if !show_content && snippet.is_none() {
         println!(
             "{} {}",
             BoxChars::V_LINE.color(Theme::BORDER),
@@ -132,6 +175,83 @@ fn print_result(index: usize, result: &SearchResult, show_content: bool, width:
     }
 }
 
+/// Print score components, matched terms, reranking and candidate source
+fn print_explanation(explanation: &Explanation) {
+    let prefix = format!("{}   ", BoxChars::V_LINE.color(Theme::BORDER));
+
+    let shares = explanation.shares();
+    let components: Vec<String> = explanation
+        .components
+        .iter()
+        .zip(&shares)
+        .map(|(c, share)| {
+            let raw = if c.raw != c.normalized {
+                format!(" (raw {:.2})", c.raw)
+            } else {
+                String::new()
+            };
+            format!(
+                "{} {:.3}{} x {:.2} = {:.3} [{:.0}%]",
+                c.name,
+                c.normalized,
+                raw,
+                c.weight,
+                c.contribution(),
+                share * 100.0
+            )
+        })
+        .collect();
+    println!(
+        "{}{} {}",
+        prefix,
+        "score".color(Theme::SUBTLE),
+        components.join("  +  ")
+    );
+
+    let terms: Vec<String> = explanation
+        .terms
+        .iter()
+        .map(|t| {
+            let text = format!("{} idf {:.2} tf {}", t.term, t.idf, t.tf);
+            if t.tf > 0 {
+                text.color(Theme::NEON_GREEN).to_string()
+            } else {
+                text.color(Theme::DIM).to_string()
+            }
+        })
+        .collect();
+    println!(
+        "{}{} {}",
+        prefix,
+        "terms".color(Theme::SUBTLE),
+        terms.join(", ")
+    );
+
+    let retrieval_rank = explanation
+        .retrieval_rank
+        .map_or("-".to_string(), |r| format!("#{}", r));
+    let rerank = match (explanation.reranked, explanation.rerank_delta()) {
+        (Some((rank, score)), Some(delta)) => format!(
+            "reranked #{} -> #{}, {:.3} -> {:.3} ({:+.3})",
+            retrieval_rank, rank, explanation.retrieval_score, score, delta
+        ),
+        _ => format!(
+            "retrieval {} {:.3}, not reranked",
+            retrieval_rank, explanation.retrieval_score
+        ),
+    };
+    println!(
+        "{}{} {}  final #{} {:.3}  {} {}",
+        prefix,
+        "rank ".color(Theme::SUBTLE),
+        rerank,
+        explanation.final_rank,
+        explanation.final_score,
+        "source".color(Theme::SUBTLE),
+        explanation.source.as_str()
+    );
+}
+
 /// Print syntax-highlighted code preview
 fn print_content_preview(content: &str, width: usize, lang: &str) {
     let preview_lines: Vec<&str> = content.lines().take(6).collect();
@@ -184,8 +304,81 @@ fn print_content_preview(content: &str, width: usize, lang: &str) {
     );
 }
 
+/// Print an on-disk snippet with line numbers, dimming context lines
+fn print_snippet_preview(snippet: &Snippet, width: usize, lang: &str) {
+    let gutter = snippet
+        .after
+        .last()
+        .or(snippet.lines.last())
+        .map(|(n, _)| n.to_string().len())
+        .unwrap_or(1);
+
+    println!(
+        "{} {}{}{}",
+        BoxChars::V_LINE.color(Theme::BORDER),
+        BoxChars::L_TL.color(Theme::DIM),
+        BoxChars::L_H_LINE
+            .repeat(width.saturating_sub(6))
+            .color(Theme::DIM),
+        BoxChars::L_TR.color(Theme::DIM)
+    );
+
+    let print_line = |number: usize, line: &str, context: bool| {
+        let trimmed = truncate_str(line, width.saturating_sub(10 + gutter));
+        let text = if context {
+            trimmed.color(Theme::DIM).to_string()
+        } else {
+            highlight_code(trimmed, lang)
+        };
+        let separator = if context { "-" } else { ":" };
+        println!(
+            "{} {} {}{} {}",
+            BoxChars::V_LINE.color(Theme::BORDER),
+            BoxChars::L_V_LINE.color(Theme::DIM),
+            format!("{:>width$}", number, width = gutter).color(Theme::SUBTLE),
+            separator.color(Theme::DIM),
+            text
+        );
+    };
+
+    for (number, line) in &snippet.before {
+        print_line(*number, line, true);
+    }
+    // With context around it, show the whole chunk rather than a preview
+    let limit = if snippet.before.is_empty() && snippet.after.is_empty() {
+        6
+    } else {
+        snippet.lines.len()
+    };
+    for (number, line) in snippet.lines.iter().take(limit) {
+        print_line(*number, line, false);
+    }
+    if snippet.lines.len() > limit {
+        let more = format!("... +{} more lines", snippet.lines.len() - limit);
+        println!(
+            "{} {} {}",
+            BoxChars::V_LINE.color(Theme::BORDER),
+            BoxChars::L_V_LINE.color(Theme::DIM),
+            more.color(Theme::SUBTLE).italic()
+        );
+    }
+    for (number, line) in &snippet.after {
+        print_line(*number, line, true);
+    }
+
+    println!(
+        "{} {}{}{}",
+        BoxChars::V_LINE.color(Theme::BORDER),
+        BoxChars::L_BL.color(Theme::DIM),
+        BoxChars::L_H_LINE
+            .repeat(width.saturating_sub(6))
+            .color(Theme::DIM),
+        BoxChars::L_BR.color(Theme::DIM)
+    );
+}
+
 /// Simple syntax highlighting for code
-fn highlight_code(line: &str, lang: &str) -> String {
+pub fn highlight_code(line: &str, lang: &str) -> String {
     // Keywords to highlight based on language
     let keywords: &[&str] = match lang.to_lowercase().as_str() {
         "rust" | "rs" => &[
@@ -0,0 +1,480 @@
+//! Interactive result browser
+//!
+//! Full-screen loop over a warm `SearchSession`: the query is re-run as you
+//! type (after a short pause), results are listed on the left, and the
+//! selected hit is previewed on the right from the current file on disk.
+//!
+//! Keys: type to search, ↑/↓ select, PgUp/PgDn scroll the preview,
+//! Enter opens the hit in `$EDITOR`, Ctrl-L/Ctrl-T cycle the language and
+//! chunk type filters, Ctrl-E cycles the embedding mode (queries are routed
+//! by intent until then), Esc quits.
+
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use super::output::relative_path;
+use super::search_display::highlight_code;
+use super::theme::{BoxChars, Theme};
+use crate::core::filter::SearchFilter;
+use crate::core::query::ParsedQuery;
+use crate::core::search::SearchResult;
+use crate::core::session::{EmbedMode, SearchSession};
+use crate::core::snippet::{load_snippet, Snippet};
+
+/// Pause after the last keystroke before re-running the query
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+const MODES: [EmbedMode; 4] = [
+    EmbedMode::Default,
+    EmbedMode::Code,
+    EmbedMode::Hybrid,
+    EmbedMode::Colbert,
+];
+
+pub struct Browser {
+    session: SearchSession,
+    /// Path/glob scoping from the command line
+    base_filter: SearchFilter,
+    repo_path: String,
+    query: String,
+    /// Query edited since the last search
+    dirty: bool,
+    last_edit: Instant,
+    results: Vec<SearchResult>,
+    selected: usize,
+    list_offset: usize,
+    preview_scroll: usize,
+    /// Snippet of the previewed hit, keyed by result index and body height
+    preview: Option<((usize, usize), Snippet)>,
+    /// Screen out of date; set by input, resizes and searches
+    needs_draw: bool,
+    languages: Vec<String>,
+    language: Option<usize>,
+    kinds: Vec<String>,
+    kind: Option<usize>,
+    status: String,
+}
+
+impl Browser {
+    pub fn new(
+        session: SearchSession,
+        base_filter: SearchFilter,
+        repo_path: String,
+        query: String,
+    ) -> Self {
+        let languages: BTreeSet<String> = session
+            .store
+            .chunks
+            .values()
+            .filter_map(|c| c.language.clone())
+            .collect();
+        let kinds: BTreeSet<String> = session
+            .store
+            .chunks
+            .values()
+            .map(|c| c.chunk_type.clone())
+            .collect();
+
+        Self {
+            session,
+            base_filter,
+            repo_path,
+            dirty: !query.is_empty(),
+            query,
+            last_edit: Instant::now() - DEBOUNCE,
+            results: Vec::new(),
+            selected: 0,
+            list_offset: 0,
+            preview_scroll: 0,
+            preview: None,
+            needs_draw: true,
+            languages: languages.into_iter().collect(),
+            language: None,
+            kinds: kinds.into_iter().collect(),
+            kind: None,
+            status: "Type to search".to_string(),
+        }
+    }
+
+    /// Run until the user quits
+    pub async fn run(mut self) -> Result<()> {
+        let _guard = TerminalGuard::enter()?;
+        let mut stdout = io::stdout();
+
+        loop {
+            if self.dirty && self.last_edit.elapsed() >= DEBOUNCE {
+                self.status = "Searching...".to_string();
+                self.draw(&mut stdout)?;
+                self.search().await;
+            }
+            if self.needs_draw {
+                self.draw(&mut stdout)?;
+                self.needs_draw = false;
+            }
+
+            if !event::poll(Duration::from_millis(50))? {
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    self.needs_draw = true;
+                    if !self.handle_key(key, &mut stdout)? {
+                        return Ok(());
+                    }
+                }
+                Event::Resize(..) => self.needs_draw = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns false to quit
+    fn handle_key(&mut self, key: KeyEvent, stdout: &mut io::Stdout) -> Result<bool> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if ctrl => return Ok(false),
+            KeyCode::Char('u') if ctrl => self.edit(|q| q.clear()),
+            KeyCode::Char('l') if ctrl => {
+                self.language = cycle(self.language, self.languages.len());
+                self.refresh();
+            }
+            KeyCode::Char('t') if ctrl => {
+                self.kind = cycle(self.kind, self.kinds.len());
+                self.refresh();
+            }
+            KeyCode::Char('e') if ctrl => {
+                let current = MODES
+                    .iter()
+                    .position(|m| *m == self.session.embedder.mode())
+                    .unwrap_or(0);
+                self.session.stop_routing();
+                self.session
+                    .embedder
+                    .set_mode(MODES[(current + 1) % MODES.len()]);
+                self.refresh();
+            }
+            KeyCode::Char(c) if !ctrl => self.edit(|q| q.push(c)),
+            KeyCode::Backspace => self.edit(|q| {
+                q.pop();
+            }),
+            KeyCode::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                self.preview_scroll = 0;
+            }
+            KeyCode::Down if self.selected + 1 < self.results.len() => {
+                self.selected += 1;
+                self.preview_scroll = 0;
+            }
+            KeyCode::PageDown => self.preview_scroll += 10,
+            KeyCode::PageUp => self.preview_scroll = self.preview_scroll.saturating_sub(10),
+            // Search now if the query changed, otherwise open the hit
+            KeyCode::Enter if self.dirty => self.last_edit = Instant::now() - DEBOUNCE,
+            KeyCode::Enter => {
+                if let Some(result) = self.results.get(self.selected) {
+                    let snippet = load_snippet(&self.session.store, &result.chunk, 0, 0);
+                    let path = result.chunk.file_path.clone();
+                    suspend(stdout, || open_in_editor(&path, snippet.start_line))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn edit(&mut self, f: impl FnOnce(&mut String)) {
+        f(&mut self.query);
+        self.refresh();
+    }
+
+    /// Re-run the query after the debounce pause
+    fn refresh(&mut self) {
+        self.dirty = true;
+        self.last_edit = Instant::now();
+    }
+
+    async fn search(&mut self) {
+        self.dirty = false;
+        self.selected = 0;
+        self.list_offset = 0;
+        self.preview_scroll = 0;
+        self.preview = None;
+        self.needs_draw = true;
+
+        let started = Instant::now();
+        match self.run_query().await {
+            Ok(results) => {
+                self.status = format!(
+                    "{} results in {}ms",
+                    results.len(),
+                    started.elapsed().as_millis()
+                );
+                self.results = results;
+            }
+            Err(e) => {
+                self.status = format!("Error: {}", e);
+                self.results.clear();
+            }
+        }
+    }
+
+    async fn run_query(&mut self) -> Result<Vec<SearchResult>> {
+        let query = ParsedQuery::parse(&self.query)?;
+        if query.text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut filter = self.base_filter.clone();
+        if let Some(i) = self.language {
+            filter = filter.with_languages(&self.languages[i..=i], &[]);
+        }
+        if let Some(i) = self.kind {
+            filter = filter.with_kinds(&self.kinds[i..=i], &[]);
+        }
+        let filter = query.apply(filter, &self.repo_path)?;
+
+        self.session.search(&query, &filter, &|_| {}).await
+    }
+
+    fn draw(&mut self, out: &mut io::Stdout) -> Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let (width, height) = (cols as usize, rows as usize);
+        if height < 5 || width < 40 {
+            return Ok(());
+        }
+        let body = height - 3;
+        let list_width = (width * 2 / 5).max(30);
+        let preview_width = width.saturating_sub(list_width + 1);
+
+        queue!(out, Clear(ClearType::All))?;
+
+        // Header: query input and active filters
+        let filters = format!(
+            "lang:{}  type:{}  mode:{} ",
+            self.language.map_or("any", |i| self.languages[i].as_str()),
+            self.kind.map_or("any", |i| self.kinds[i].as_str()),
+            if self.session.is_routed() {
+                "routed"
+            } else {
+                self.session.embedder.mode().as_str()
+            }
+        );
+        let prompt = format!(" {} ", BoxChars::DIAMOND);
+        let input_width = width.saturating_sub(prompt.chars().count() + filters.len() + 1);
+        queue!(
+            out,
+            MoveTo(0, 0),
+            Print(prompt.color(Theme::NEON_CYAN).bold()),
+            Print(fit(&format!("{}_", self.query), input_width).color(Theme::NEON_MAGENTA)),
+            Print(filters.color(Theme::SUBTLE)),
+            MoveTo(0, 1),
+            Print(BoxChars::H_LINE.repeat(width).color(Theme::BORDER))
+        )?;
+
+        // Result list
+        if self.selected < self.list_offset {
+            self.list_offset = self.selected;
+        } else if self.selected >= self.list_offset + body {
+            self.list_offset = self.selected + 1 - body;
+        }
+        for row in 0..body {
+            queue!(out, MoveTo(0, (row + 2) as u16))?;
+            let i = self.list_offset + row;
+            if let Some(result) = self.results.get(i) {
+                let marker = if i == self.selected {
+                    BoxChars::ARROW_RIGHT
+                } else {
+                    " "
+                };
+                let label = format!(
+                    "{}:{} {}",
+                    relative_path(&result.chunk.file_path),
+                    result.chunk.start_line,
+                    result.chunk.symbol_name.as_deref().unwrap_or("")
+                );
+                let line = fit(&label, list_width.saturating_sub(8));
+                let score =
+                    format!(" {:.2} ", result.score).color(Theme::score_color(result.score));
+                if i == self.selected {
+                    queue!(
+                        out,
+                        Print(marker.color(Theme::NEON_CYAN)),
+                        Print(score),
+                        Print(line.color(Theme::NEON_CYAN).bold())
+                    )?;
+                } else {
+                    queue!(out, Print(marker), Print(score), Print(line))?;
+                }
+            }
+            queue!(
+                out,
+                MoveTo(list_width as u16, (row + 2) as u16),
+                Print(BoxChars::L_V_LINE.color(Theme::BORDER))
+            )?;
+        }
+
+        // Preview of the selected hit, read from disk with context once per selection
+        if let Some(result) = self.results.get(self.selected) {
+            let key = (self.selected, body);
+            if self.preview.as_ref().map(|(k, _)| *k) != Some(key) {
+                let snippet = load_snippet(&self.session.store, &result.chunk, 3, body);
+                self.preview = Some((key, snippet));
+            }
+            let snippet = &self.preview.as_ref().expect("preview loaded above").1;
+            let lang = result.chunk.language.as_deref().unwrap_or("text");
+            let gutter = snippet
+                .after
+                .last()
+                .or(snippet.lines.last())
+                .map_or(1, |(n, _)| n.to_string().len());
+            let lines: Vec<(usize, &String, bool)> = snippet
+                .before
+                .iter()
+                .map(|(n, l)| (*n, l, false))
+                .chain(snippet.lines.iter().map(|(n, l)| (*n, l, true)))
+                .chain(snippet.after.iter().map(|(n, l)| (*n, l, false)))
+                .collect();
+            self.preview_scroll = self.preview_scroll.min(lines.len().saturating_sub(1));
+
+            for (row, (number, line, in_chunk)) in lines
+                .iter()
+                .skip(self.preview_scroll)
+                .take(body)
+                .enumerate()
+            {
+                let text = fit(
+                    &line.replace('\t', "    "),
+                    preview_width.saturating_sub(gutter + 3),
+                );
+                let number = format!(" {:>width$} ", number, width = gutter);
+                queue!(
+                    out,
+                    MoveTo((list_width + 1) as u16, (row + 2) as u16),
+                    Print(if *in_chunk {
+                        number.color(Theme::NEON_GREEN)
+                    } else {
+                        number.color(Theme::DIM)
+                    }),
+                    Print(if *in_chunk {
+                        highlight_code(&text, lang)
+                    } else {
+                        text.color(Theme::DIM).to_string()
+                    })
+                )?;
+            }
+        }
+
+        // Status and key help
+        let help = "↑↓ select  Enter open  ^L lang  ^T type  ^E mode  Esc quit ";
+        queue!(
+            out,
+            MoveTo(0, (height - 1) as u16),
+            Print(
+                fit(
+                    &format!(" {}", self.status),
+                    width.saturating_sub(help.chars().count())
+                )
+                .color(Theme::SUBTLE)
+            ),
+            Print(help.color(Theme::DIM))
+        )?;
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Advance an optional index through `len` values and back to `None`
+fn cycle(current: Option<usize>, len: usize) -> Option<usize> {
+    match current {
+        None if len > 0 => Some(0),
+        Some(i) if i + 1 < len => Some(i + 1),
+        _ => None,
+    }
+}
+
+/// Truncate or pad `s` to exactly `width` characters
+fn fit(s: &str, width: usize) -> String {
+    let mut out: String = s.chars().take(width).collect();
+    let len = out.chars().count();
+    out.extend(std::iter::repeat_n(' ', width - len));
+    out
+}
+
+/// Raw mode + alternate screen, restored on drop (including on errors)
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Leave the full-screen UI while `f` runs
+fn suspend(out: &mut io::Stdout, f: impl FnOnce() -> Result<()>) -> Result<()> {
+    execute!(out, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    let result = f();
+    terminal::enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, Hide)?;
+    result
+}
+
+/// Open `path` at `line` in `$VISUAL`/`$EDITOR`
+fn open_in_editor(path: &str, line: usize) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(program);
+
+    let location: Vec<String> = match name {
+        "code" | "code-insiders" | "cursor" | "codium" => {
+            vec!["--goto".to_string(), format!("{}:{}", path, line)]
+        }
+        "subl" | "zed" | "hx" => vec![format!("{}:{}", path, line)],
+        _ => vec![format!("+{}", line), path.to_string()],
+    };
+
+    Command::new(program).args(parts).args(location).status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_and_fit() {
+        assert_eq!(cycle(None, 2), Some(0));
+        assert_eq!(cycle(Some(1), 2), None);
+        assert_eq!(cycle(None, 0), None);
+        assert_eq!(fit("héllo", 3), "hél");
+        assert_eq!(fit("ab", 4), "ab  ");
+    }
+}